@@ -0,0 +1,64 @@
+//! Serial vs parallel type aggregation, to locate the size at which
+//! `get_memory_by_type` should switch to the rayon path.
+//!
+//! Run with `cargo bench --bench aggregation --features parallel`. Each size
+//! is timed as the best of several runs, printed as one row per size with the
+//! parallel/serial ratio; a ratio below 1.0 means the parallel path wins.
+
+use memscope_rs::test_support::{aggregate_by_type_parallel, aggregate_by_type_serial};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SIZES: [usize; 7] = [1_000, 10_000, 25_000, 50_000, 100_000, 500_000, 2_000_000];
+const TYPE_COUNT: usize = 64;
+const RUNS: usize = 7;
+
+fn entries(len: usize) -> Vec<(Option<String>, usize)> {
+    (0..len)
+        .map(|i| {
+            // Every tenth allocation is untyped, as in a typical active set
+            let type_name = (i % 10 != 0).then(|| format!("Type{}", i % TYPE_COUNT));
+            (type_name, 16 + i % 4096)
+        })
+        .collect()
+}
+
+fn best_of<F: FnMut()>(mut run: F) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    println!("rayon threads: {}", rayon::current_num_threads());
+    println!(
+        "{:>10} {:>12} {:>12} {:>8}",
+        "entries", "serial", "parallel", "ratio"
+    );
+    for len in SIZES {
+        let entries = entries(len);
+        assert_eq!(
+            aggregate_by_type_serial(&entries),
+            aggregate_by_type_parallel(&entries)
+        );
+
+        let serial = best_of(|| {
+            black_box(aggregate_by_type_serial(black_box(&entries)));
+        });
+        let parallel = best_of(|| {
+            black_box(aggregate_by_type_parallel(black_box(&entries)));
+        });
+        println!(
+            "{:>10} {:>12?} {:>12?} {:>8.2}",
+            len,
+            serial,
+            parallel,
+            parallel.as_secs_f64() / serial.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Aggregate-only tracking for very high allocation volumes.
+//!
+//! `AggregateOnlyTracker` keeps running per-type `(bytes, count)` totals and the
+//! overall statistics, but never stores an `AllocationInfo` per allocation. Its
+//! memory overhead is proportional to the number of distinct types rather than
+//! the number of allocations, at the cost of losing per-allocation detail.
+
+use crate::tracker::{build_hierarchical_json_structure, AtomicStats};
+use crate::types::{MemoryStats, TrackingError, TrackingResult, TypeMemoryUsage};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Tracker that maintains only aggregate statistics.
+///
+/// Because no per-pointer record is kept, deallocations must be reported with
+/// the same size and type name as the matching allocation.
+#[derive(Default)]
+pub struct AggregateOnlyTracker {
+    /// Overall statistics
+    stats: AtomicStats,
+    /// type name -> (active bytes, active count)
+    by_type: Mutex<HashMap<String, (usize, usize)>>,
+}
+
+impl AggregateOnlyTracker {
+    /// Create a new aggregate-only tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an allocation of `size` bytes, optionally attributed to a type.
+    pub fn track_allocation(&self, size: usize, type_name: Option<&str>) -> TrackingResult<()> {
+        self.stats.record_allocation(size);
+
+        let mut by_type = match self.by_type.lock() {
+            Ok(by_type) => by_type,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let (bytes, count) = by_type
+            .entry(type_name.unwrap_or("Unknown").to_string())
+            .or_insert((0, 0));
+        *bytes = bytes.saturating_add(size);
+        *count = count.saturating_add(1);
+        Ok(())
+    }
+
+    /// Record a deallocation of `size` bytes previously tracked under `type_name`.
+    pub fn track_deallocation(&self, size: usize, type_name: Option<&str>) -> TrackingResult<()> {
+        self.stats.record_deallocation(size);
+
+        let mut by_type = match self.by_type.lock() {
+            Ok(by_type) => by_type,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some((bytes, count)) = by_type.get_mut(type_name.unwrap_or("Unknown")) {
+            *bytes = bytes.saturating_sub(size);
+            *count = count.saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    /// Get current memory usage statistics.
+    pub fn get_stats(&self) -> TrackingResult<MemoryStats> {
+        Ok(self.stats.snapshot())
+    }
+
+    /// Get active memory grouped by type, sorted by total size descending.
+    pub fn get_memory_by_type(&self) -> TrackingResult<Vec<TypeMemoryUsage>> {
+        let by_type = match self.by_type.lock() {
+            Ok(by_type) => by_type.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+
+        let mut result: Vec<TypeMemoryUsage> = by_type
+            .into_iter()
+            .filter(|(_, (_, count))| *count > 0)
+            .map(
+                |(type_name, (total_size, allocation_count))| TypeMemoryUsage {
+                    type_name,
+                    total_size,
+                    allocation_count,
+                },
+            )
+            .collect();
+        result.sort_by_key(|t| std::cmp::Reverse(t.total_size));
+        Ok(result)
+    }
+
+    /// Export the type breakdown in the same hierarchical JSON format as
+    /// `MemoryTracker::export_to_json`, without per-allocation entries.
+    pub fn export_to_json<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        let memory_by_type = self.get_memory_by_type()?;
+        let stats = self.get_stats()?;
+
+        let enhanced_types = crate::export_enhanced::enhance_type_information(&memory_by_type, &[]);
+        let hierarchical_data =
+            build_hierarchical_json_structure(&enhanced_types, &[], &stats, Default::default());
+
+        let file = std::fs::File::create(path.as_ref())?;
+        serde_json::to_writer_pretty(file, &hierarchical_data)
+            .map_err(|e| TrackingError::SerializationError(format!("JSON export failed: {e}")))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+//! Custom global allocator for tracking memory allocations.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+
+/// A custom allocator that tracks memory allocations and deallocations.
+///
+/// This allocator wraps an inner allocator (the system allocator by default)
+/// and records all allocation and deallocation events through the global
+/// memory tracker, from the moment it is created by `init`, `get_global_tracker`
+/// or `init_global_tracker`; earlier events are not tracked. Register it with
+/// `#[global_allocator] static A: TrackingAllocator = TrackingAllocator::new();`,
+/// or wrap another allocator with `TrackingAllocator::wrap`.
+///
+/// # Re-entrancy
+///
+/// Tracking an event allocates too (e.g. growing the tracker's maps), and
+/// those allocations come straight back through this allocator. A
+/// thread-local "in tracker" flag is set for the duration of each tracking
+/// call; allocations and frees made while it is set are forwarded to the
+/// inner allocator without being tracked, so tracking never recurses. The
+/// tracker's own bookkeeping therefore never appears in the statistics.
+pub struct TrackingAllocator<A: GlobalAlloc = System> {
+    inner: A,
+    /// Whether `inner` is the system allocator, so glibc can report usable sizes
+    inner_is_system: bool,
+}
+
+impl TrackingAllocator<System> {
+    /// Create a new tracking allocator instance over the system allocator.
+    pub const fn new() -> Self {
+        Self {
+            inner: System,
+            inner_is_system: true,
+        }
+    }
+}
+
+impl<A: GlobalAlloc> TrackingAllocator<A> {
+    /// Create a tracking allocator that forwards to `inner`.
+    pub const fn wrap(inner: A) -> Self {
+        Self {
+            inner,
+            inner_is_system: false,
+        }
+    }
+
+    fn track_allocation(&self, ptr: *mut u8, layout: Layout) {
+        let usable = if self.inner_is_system {
+            usable_size(ptr)
+        } else {
+            None
+        };
+        with_tracking_guard(|tracker| {
+            // Ignore errors to prevent allocation failures from breaking the program
+            let _ = tracker.track_allocator_allocation(
+                ptr as usize,
+                layout.size(),
+                layout.align(),
+                usable,
+            );
+        });
+    }
+
+    fn track_deallocation(&self, ptr: *mut u8) {
+        with_tracking_guard(|tracker| {
+            // Ignore errors to prevent deallocation failures from breaking the program
+            let _ = tracker.track_deallocation(ptr as usize);
+        });
+    }
+}
+
+// Thread-local flag to prevent recursive tracking
+thread_local! {
+    static TRACKING_DISABLED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Run `track` against the global tracker, if created, unless this thread is
+/// already inside a tracking call, with further tracking disabled meanwhile
+fn with_tracking_guard(track: impl FnOnce(&crate::tracker::MemoryTracker)) {
+    // Check if tracking is disabled for this thread to prevent recursion
+    let should_track = TRACKING_DISABLED.with(|disabled| !disabled.get());
+    if !should_track {
+        return;
+    }
+
+    // Temporarily disable tracking to prevent recursion during tracking operations
+    TRACKING_DISABLED.with(|disabled| disabled.set(true));
+
+    // Track the event - the tracker uses try_lock to avoid deadlocks
+    if let Some(tracker) = crate::tracker::global_tracker_if_initialized() {
+        track(tracker);
+    }
+
+    // Re-enable tracking
+    TRACKING_DISABLED.with(|disabled| disabled.set(false));
+}
+
+/// Keeps this thread's allocations untracked while alive.
+///
+/// Queries that copy tracker data (cloning allocations out of the shards or
+/// the history) hold one, so the copies are not recorded as new allocations
+/// in the shards they are not currently reading.
+pub(crate) struct UntrackedScope {
+    was_disabled: bool,
+}
+
+impl UntrackedScope {
+    pub(crate) fn enter() -> Self {
+        let was_disabled = TRACKING_DISABLED.with(|disabled| disabled.replace(true));
+        Self { was_disabled }
+    }
+}
+
+impl Drop for UntrackedScope {
+    fn drop(&mut self) {
+        TRACKING_DISABLED.with(|disabled| disabled.set(self.was_disabled));
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // Allocate memory first
+        let ptr = self.inner.alloc(layout);
+
+        // Track the allocation if it succeeded
+        if !ptr.is_null() {
+            self.track_allocation(ptr, layout);
+        }
+
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            self.track_allocation(ptr, layout);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // Track the deallocation first
+        self.track_deallocation(ptr);
+
+        // Deallocate the memory
+        self.inner.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+
+        // On failure the old block is untouched and stays tracked
+        if !new_ptr.is_null() {
+            let usable = if self.inner_is_system {
+                usable_size(new_ptr)
+            } else {
+                None
+            };
+            // A first-class reallocation keeps the variable and type association
+            with_tracking_guard(|tracker| {
+                let _ = tracker.track_allocator_reallocation(
+                    ptr as usize,
+                    new_ptr as usize,
+                    new_size,
+                    layout.align(),
+                    usable,
+                );
+            });
+        }
+
+        new_ptr
+    }
+}
+
+/// Bytes actually reserved by the system allocator for a live allocation
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn usable_size(ptr: *mut u8) -> Option<usize> {
+    extern "C" {
+        fn malloc_usable_size(ptr: *mut std::ffi::c_void) -> usize;
+    }
+    // SAFETY: `ptr` was just returned by the system allocator, which is glibc's malloc
+    Some(unsafe { malloc_usable_size(ptr.cast()) })
+}
+
+/// Bytes actually reserved by the system allocator; unknown on this platform
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn usable_size(_ptr: *mut u8) -> Option<usize> {
+    None
+}
+
+impl Default for TrackingAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,70 @@
+//! Backtrace capture for attributing allocations to call sites.
+
+use std::backtrace::Backtrace;
+
+/// Most frames kept per backtrace, counted from the innermost user frame
+const MAX_FRAMES: usize = 16;
+
+/// Frames from these paths belong to the standard library's capture
+/// machinery and are trimmed from the top of a backtrace
+const INTERNAL_PREFIXES: [&str; 1] = ["std::backtrace"];
+
+/// Frames whose symbol mentions this crate path belong to the tracker,
+/// including trait impls (`<memscope_rs::… as …>`) and generic wrappers, and
+/// are trimmed from the top of a backtrace too
+const TRACKER_PATH: &str = "memscope_rs::";
+
+/// Frames from these paths are the runtime's entry points and are trimmed
+/// from the bottom
+const RUNTIME_PREFIXES: [&str; 6] = [
+    "std::rt::",
+    "std::sys",
+    "core::ops::function::",
+    "std::panicking::",
+    "__libc_start",
+    "_start",
+];
+
+/// Capture the current call stack as resolved `symbol at file:line` frames,
+/// innermost first, without the tracker's own frames.
+pub(crate) fn capture_frames() -> Vec<String> {
+    let backtrace = Backtrace::force_capture().to_string();
+    let frames = parse_frames(&backtrace);
+
+    let user_start = frames
+        .iter()
+        .position(|frame| !is_internal(frame))
+        .unwrap_or(frames.len());
+    frames
+        .into_iter()
+        .skip(user_start)
+        .take_while(|frame| !RUNTIME_PREFIXES.iter().any(|p| frame.starts_with(p)))
+        .take(MAX_FRAMES)
+        .collect()
+}
+
+/// Whether a `symbol at location` frame belongs to the tracker or the capture itself
+fn is_internal(frame: &str) -> bool {
+    let symbol = frame.split(" at ").next().unwrap_or(frame);
+    INTERNAL_PREFIXES.iter().any(|p| symbol.starts_with(p)) || symbol.contains(TRACKER_PATH)
+}
+
+/// Parse std's backtrace rendering, where each frame is a numbered
+/// `N: symbol` line optionally followed by an `at file:line:col` line.
+fn parse_frames(backtrace: &str) -> Vec<String> {
+    let mut frames: Vec<String> = Vec::new();
+    for line in backtrace.lines() {
+        let line = line.trim();
+        if let Some(location) = line.strip_prefix("at ") {
+            if let Some(frame) = frames.last_mut() {
+                frame.push_str(" at ");
+                frame.push_str(location);
+            }
+        } else if let Some((index, symbol)) = line.split_once(": ") {
+            if index.chars().all(|c| c.is_ascii_digit()) {
+                frames.push(symbol.to_string());
+            }
+        }
+    }
+    frames
+}
@@ -0,0 +1,140 @@
+//! Allocation event hooks and the JSONL event log built on them.
+
+use crate::types::{TrackEvent, TrackingError, TrackingResult};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Number of events buffered before the event log is flushed to disk
+const EVENT_LOG_FLUSH_INTERVAL: usize = 64;
+
+/// Callback invoked for every tracked allocation and deallocation
+pub type EventHook = Arc<dyn Fn(&TrackEvent) + Send + Sync>;
+
+/// Finalizer registered with `MemoryTracker::on_shutdown`
+pub type ShutdownHook = Box<dyn FnOnce() + Send>;
+
+/// Identifier returned when registering an event hook, used to remove it again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HookId(u64);
+
+/// A tracking event with the time it was observed, as written to the event log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    /// Time the event was tracked (milliseconds since UNIX_EPOCH)
+    pub timestamp: u128,
+    /// The tracked event
+    #[serde(flatten)]
+    pub event: TrackEvent,
+}
+
+impl LoggedEvent {
+    /// Stamp an event with the current time
+    pub(crate) fn now(event: &TrackEvent) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            event: event.clone(),
+        }
+    }
+}
+
+/// Registered event hooks
+#[derive(Default)]
+pub(crate) struct HookRegistry {
+    hooks: RwLock<Vec<(HookId, EventHook)>>,
+    next_id: AtomicU64,
+    /// Fast-path check so tracking pays nothing when no hooks are registered
+    active: AtomicBool,
+}
+
+impl HookRegistry {
+    pub(crate) fn add(&self, hook: EventHook) -> HookId {
+        let id = HookId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut hooks = match self.hooks.write() {
+            Ok(hooks) => hooks,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hooks.push((id, hook));
+        self.active.store(true, Ordering::Release);
+        id
+    }
+
+    pub(crate) fn remove(&self, id: HookId) -> bool {
+        let mut hooks = match self.hooks.write() {
+            Ok(hooks) => hooks,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let before = hooks.len();
+        hooks.retain(|(hook_id, _)| *hook_id != id);
+        self.active.store(!hooks.is_empty(), Ordering::Release);
+        hooks.len() != before
+    }
+
+    /// Invoke every hook with the event.
+    ///
+    /// Hooks are cloned out of the registry first, so a hook may itself
+    /// register or remove hooks without deadlocking.
+    pub(crate) fn emit(&self, event: &TrackEvent) {
+        if !self.active.load(Ordering::Acquire) {
+            return;
+        }
+        let hooks: Vec<EventHook> = match self.hooks.read() {
+            Ok(hooks) => hooks.iter().map(|(_, hook)| Arc::clone(hook)).collect(),
+            Err(poisoned) => poisoned
+                .into_inner()
+                .iter()
+                .map(|(_, hook)| Arc::clone(hook))
+                .collect(),
+        };
+        for hook in hooks {
+            hook(event);
+        }
+    }
+}
+
+/// Buffered JSONL writer behind an active event log
+pub(crate) struct EventLogWriter {
+    writer: BufWriter<File>,
+    pending: usize,
+}
+
+impl EventLogWriter {
+    pub(crate) fn create(path: &Path) -> TrackingResult<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            pending: 0,
+        })
+    }
+
+    /// Append one event line, flushing every few events so a crash loses little
+    pub(crate) fn append(&mut self, event: &TrackEvent) -> TrackingResult<()> {
+        serde_json::to_writer(&mut self.writer, &LoggedEvent::now(event)).map_err(|e| {
+            TrackingError::SerializationError(format!("Event log write failed: {e}"))
+        })?;
+        self.writer.write_all(b"\n")?;
+
+        self.pending += 1;
+        if self.pending >= EVENT_LOG_FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> TrackingResult<()> {
+        self.pending = 0;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// An event log registered on a tracker
+pub(crate) struct ActiveEventLog {
+    pub(crate) hook_id: HookId,
+    pub(crate) writer: Arc<Mutex<EventLogWriter>>,
+}
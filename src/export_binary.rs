@@ -0,0 +1,614 @@
+//! Compact binary dumps of the complete tracker state.
+//!
+//! A dump is the `BINARY_MAGIC` bytes, the `BINARY_FORMAT_VERSION` as a
+//! little-endian `u32`, then the same document `save_state` writes as JSON,
+//! encoded with one tag byte per value: integers and lengths are LEB128
+//! varints, floats are 8 little-endian bytes, arrays and objects run until an
+//! end marker, and each object key is written once and referred to by index
+//! afterwards, so the field names repeated in every allocation cost one or two
+//! bytes each.
+//!
+//! The state is streamed straight into the file as it is serialized. Reading
+//! checks every length prefix against the bytes left in the file before
+//! allocating, and rejects documents nested deeper than `MAX_NESTING`.
+
+use crate::state::{TrackerState, STATE_FORMAT_VERSION};
+use crate::types::{TrackingError, TrackingResult};
+use serde::ser::{self, Serialize};
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Bytes every binary dump starts with
+pub const BINARY_MAGIC: [u8; 8] = *b"MEMSCOPE";
+
+/// Version of the binary encoding written by `export_to_binary`
+pub const BINARY_FORMAT_VERSION: u32 = 2;
+
+/// Deepest array/object nesting accepted when reading a dump
+pub const MAX_NESTING: usize = 64;
+
+const TAG_NULL: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_UINT: u8 = 3;
+/// Negative integer `n`, stored as the varint `-(n + 1)`
+const TAG_NEG_INT: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_STRING: u8 = 6;
+/// Values follow until `TAG_END`
+const TAG_ARRAY: u8 = 7;
+/// Key/value pairs follow until the key index `KEY_END`
+const TAG_OBJECT: u8 = 8;
+const TAG_END: u8 = 9;
+
+/// Closes an object in place of a key; key indexes are stored plus one
+const KEY_END: u64 = 0;
+
+/// Write `state` to `path` as a binary dump
+pub(crate) fn write_binary(state: &TrackerState, path: &Path) -> TrackingResult<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&BINARY_MAGIC)?;
+    writer.write_all(&BINARY_FORMAT_VERSION.to_le_bytes())?;
+    state
+        .serialize(&mut Encoder {
+            writer: &mut writer,
+            keys: HashMap::new(),
+        })
+        .map_err(|e| TrackingError::SerializationError(format!("Binary export failed: {e}")))?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a binary dump written by `write_binary`
+pub(crate) fn read_binary(path: &Path) -> TrackingResult<TrackerState> {
+    let file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0u8; BINARY_MAGIC.len()];
+    let mut version = [0u8; 4];
+    if reader.read_exact(&mut magic).is_err() || magic != BINARY_MAGIC {
+        return Err(TrackingError::SerializationError(format!(
+            "{}: not a memscope binary dump",
+            path.display()
+        )));
+    }
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != BINARY_FORMAT_VERSION {
+        return Err(TrackingError::SerializationError(format!(
+            "{}: binary format version {version} is not supported (expected {BINARY_FORMAT_VERSION})",
+            path.display()
+        )));
+    }
+
+    let document = Decoder {
+        reader: &mut reader,
+        remaining: file_len.saturating_sub((BINARY_MAGIC.len() + 4) as u64),
+        keys: Vec::new(),
+    }
+    .value(0)
+    .map_err(|e| TrackingError::SerializationError(format!("{}: {e}", path.display())))?;
+
+    let format_version = document.get("format_version").and_then(Value::as_u64);
+    if format_version != Some(u64::from(STATE_FORMAT_VERSION)) {
+        return Err(TrackingError::StateVersionMismatch {
+            found: format_version.map_or(0, |v| v as u32),
+            expected: STATE_FORMAT_VERSION,
+        });
+    }
+    serde_json::from_value(document).map_err(|e| {
+        TrackingError::SerializationError(format!("{}: binary import failed: {e}", path.display()))
+    })
+}
+
+/// A value the binary encoding cannot represent, or a write failure
+#[derive(Debug)]
+struct EncodeError(String);
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+impl ser::Error for EncodeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        EncodeError(msg.to_string())
+    }
+}
+
+impl From<std::io::Error> for EncodeError {
+    fn from(error: std::io::Error) -> Self {
+        EncodeError(error.to_string())
+    }
+}
+
+/// Serializes values straight to `writer`, laid out as `serde_json` would
+/// lay out the same value: enums externally tagged, map keys as strings
+/// and non-finite floats as null
+struct Encoder<'a, W: Write> {
+    writer: &'a mut W,
+    /// Index of every object key written so far
+    keys: HashMap<String, u64>,
+}
+
+impl<W: Write> Encoder<'_, W> {
+    fn tag(&mut self, tag: u8) -> Result<(), EncodeError> {
+        Ok(self.writer.write_all(&[tag])?)
+    }
+
+    fn uint(&mut self, n: u64) -> Result<(), EncodeError> {
+        self.tag(TAG_UINT)?;
+        self.varint(n)
+    }
+
+    fn int(&mut self, n: i64) -> Result<(), EncodeError> {
+        if n >= 0 {
+            return self.uint(n as u64);
+        }
+        self.tag(TAG_NEG_INT)?;
+        self.varint(!(n as u64))
+    }
+
+    fn float(&mut self, n: f64) -> Result<(), EncodeError> {
+        if !n.is_finite() {
+            return self.tag(TAG_NULL);
+        }
+        self.tag(TAG_FLOAT)?;
+        Ok(self.writer.write_all(&n.to_le_bytes())?)
+    }
+
+    /// A known key as its index; a new key as the next index followed by its text
+    fn key(&mut self, key: &str) -> Result<(), EncodeError> {
+        if let Some(&index) = self.keys.get(key) {
+            return self.varint(index + 1);
+        }
+        let index = self.keys.len() as u64;
+        self.keys.insert(key.to_string(), index);
+        self.varint(index + 1)?;
+        self.string(key)
+    }
+
+    fn string(&mut self, string: &str) -> Result<(), EncodeError> {
+        self.varint(string.len() as u64)?;
+        Ok(self.writer.write_all(string.as_bytes())?)
+    }
+
+    fn varint(&mut self, mut n: u64) -> Result<(), EncodeError> {
+        let mut bytes = [0u8; 10];
+        let mut len = 0;
+        loop {
+            let low = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                bytes[len] = low;
+                len += 1;
+                break;
+            }
+            bytes[len] = low | 0x80;
+            len += 1;
+        }
+        Ok(self.writer.write_all(&bytes[..len])?)
+    }
+
+    /// Open the single-entry object an enum variant with data is written as
+    fn begin_variant(&mut self, variant: &str) -> Result<(), EncodeError> {
+        self.tag(TAG_OBJECT)?;
+        self.key(variant)
+    }
+
+    fn end_object(&mut self) -> Result<(), EncodeError> {
+        self.varint(KEY_END)
+    }
+}
+
+impl<W: Write> ser::Serializer for &mut Encoder<'_, W> {
+    type Ok = ();
+    type Error = EncodeError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), EncodeError> {
+        self.tag(if v { TAG_TRUE } else { TAG_FALSE })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), EncodeError> {
+        self.int(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), EncodeError> {
+        self.int(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), EncodeError> {
+        self.int(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), EncodeError> {
+        self.int(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), EncodeError> {
+        let v = i64::try_from(v).map_err(|_| EncodeError(format!("{v} is out of range")))?;
+        self.int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), EncodeError> {
+        self.uint(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), EncodeError> {
+        self.uint(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), EncodeError> {
+        self.uint(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), EncodeError> {
+        self.uint(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), EncodeError> {
+        let v = u64::try_from(v).map_err(|_| EncodeError(format!("{v} is out of range")))?;
+        self.uint(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), EncodeError> {
+        self.float(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), EncodeError> {
+        self.float(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), EncodeError> {
+        self.serialize_str(v.encode_utf8(&mut [0u8; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), EncodeError> {
+        self.tag(TAG_STRING)?;
+        self.string(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), EncodeError> {
+        self.tag(TAG_ARRAY)?;
+        for &byte in v {
+            self.uint(byte.into())?;
+        }
+        self.tag(TAG_END)
+    }
+
+    fn serialize_none(self) -> Result<(), EncodeError> {
+        self.tag(TAG_NULL)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), EncodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), EncodeError> {
+        self.tag(TAG_NULL)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), EncodeError> {
+        self.tag(TAG_NULL)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), EncodeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        self.begin_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.end_object()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, EncodeError> {
+        self.tag(TAG_ARRAY)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self, EncodeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self, EncodeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, EncodeError> {
+        self.begin_variant(variant)?;
+        self.tag(TAG_ARRAY)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, EncodeError> {
+        self.tag(TAG_OBJECT)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, EncodeError> {
+        self.tag(TAG_OBJECT)?;
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, EncodeError> {
+        self.begin_variant(variant)?;
+        self.tag(TAG_OBJECT)?;
+        Ok(self)
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for &mut Encoder<'_, W> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.tag(TAG_END)
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for &mut Encoder<'_, W> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.tag(TAG_END)
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for &mut Encoder<'_, W> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.tag(TAG_END)
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for &mut Encoder<'_, W> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.tag(TAG_END)?;
+        self.end_object()
+    }
+}
+
+impl<W: Write> ser::SerializeMap for &mut Encoder<'_, W> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    /// Keys become strings, as `serde_json` writes them
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), EncodeError> {
+        match serde_json::to_value(key).map_err(|e| EncodeError(e.to_string()))? {
+            Value::String(key) => self.key(&key),
+            Value::Number(key) => self.key(&key.to_string()),
+            Value::Bool(key) => self.key(if key { "true" } else { "false" }),
+            _ => Err(EncodeError(
+                "map keys must be strings or numbers".to_string(),
+            )),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), EncodeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.end_object()
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for &mut Encoder<'_, W> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        self.key(key)?;
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.end_object()
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for &mut Encoder<'_, W> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        self.key(key)?;
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.end_object()?;
+        self.end_object()
+    }
+}
+
+struct Decoder<'a, R: Read> {
+    reader: &'a mut R,
+    /// Bytes left in the dump, the bound for every length prefix
+    remaining: u64,
+    /// Object keys in the order they were first written
+    keys: Vec<String>,
+}
+
+impl<R: Read> Decoder<'_, R> {
+    /// The next value, inside `depth` enclosing arrays and objects
+    fn value(&mut self, depth: usize) -> Result<Value, String> {
+        let tag = self.byte()?;
+        self.tagged_value(tag, depth)
+    }
+
+    fn tagged_value(&mut self, tag: u8, depth: usize) -> Result<Value, String> {
+        if matches!(tag, TAG_ARRAY | TAG_OBJECT) && depth >= MAX_NESTING {
+            return Err(format!(
+                "corrupt binary dump: nested deeper than {MAX_NESTING} levels"
+            ));
+        }
+        Ok(match tag {
+            TAG_NULL => Value::Null,
+            TAG_FALSE => Value::Bool(false),
+            TAG_TRUE => Value::Bool(true),
+            TAG_UINT => Value::from(self.varint()?),
+            TAG_NEG_INT => Value::from(!self.varint()? as i64),
+            TAG_FLOAT => {
+                let mut bytes = [0u8; 8];
+                self.read(&mut bytes)?;
+                Number::from_f64(f64::from_le_bytes(bytes)).map_or(Value::Null, Value::Number)
+            }
+            TAG_STRING => Value::String(self.string()?),
+            TAG_ARRAY => {
+                let mut items = Vec::new();
+                loop {
+                    match self.byte()? {
+                        TAG_END => break,
+                        tag => items.push(self.tagged_value(tag, depth + 1)?),
+                    }
+                }
+                Value::Array(items)
+            }
+            TAG_OBJECT => {
+                let mut fields = Map::new();
+                while let Some(key) = self.key()? {
+                    fields.insert(key, self.value(depth + 1)?);
+                }
+                Value::Object(fields)
+            }
+            tag => return Err(format!("corrupt binary dump: unknown value tag {tag}")),
+        })
+    }
+
+    /// The next object key, or `None` at the end of the object
+    fn key(&mut self) -> Result<Option<String>, String> {
+        let index = self.varint()?;
+        if index == KEY_END {
+            return Ok(None);
+        }
+        let index = (index - 1) as usize;
+        match index.cmp(&self.keys.len()) {
+            std::cmp::Ordering::Less => Ok(Some(self.keys[index].clone())),
+            std::cmp::Ordering::Equal => {
+                let key = self.string()?;
+                self.keys.push(key.clone());
+                Ok(Some(key))
+            }
+            std::cmp::Ordering::Greater => {
+                Err(format!("corrupt binary dump: unknown key index {index}"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.varint()?;
+        if len > self.remaining {
+            return Err(format!(
+                "corrupt binary dump: string of {len} bytes with {} left",
+                self.remaining
+            ));
+        }
+        let mut bytes = vec![0u8; len as usize];
+        self.read(&mut bytes)?;
+        String::from_utf8(bytes).map_err(|e| format!("corrupt binary dump: {e}"))
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err("corrupt binary dump: varint too long".to_string())
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let mut byte = [0u8; 1];
+        self.read(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn read(&mut self, bytes: &mut [u8]) -> Result<(), String> {
+        self.reader
+            .read_exact(bytes)
+            .map_err(|e| format!("truncated binary dump: {e}"))?;
+        self.remaining = self.remaining.saturating_sub(bytes.len() as u64);
+        Ok(())
+    }
+}
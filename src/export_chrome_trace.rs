@@ -0,0 +1,118 @@
+//! Chrome Trace Event Format export of allocation lifetimes, for
+//! chrome://tracing and Perfetto
+
+use crate::tracker::MemoryTracker;
+use crate::types::{AllocationInfo, TrackingError, TrackingResult};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Top-level trace document
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChromeTrace {
+    trace_events: Vec<TraceEvent>,
+    display_time_unit: &'static str,
+}
+
+/// One event of the Trace Event Format
+#[derive(Debug, Serialize)]
+struct TraceEvent {
+    name: String,
+    cat: &'static str,
+    /// `X` (complete, with `dur`) for freed allocations, `B` (begin) for live ones
+    ph: &'static str,
+    /// Microseconds since the Unix epoch
+    ts: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<u128>,
+    pid: u32,
+    tid: u64,
+    args: TraceEventArgs,
+}
+
+#[derive(Debug, Serialize)]
+struct TraceEventArgs {
+    size: usize,
+    ptr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    type_name: Option<String>,
+}
+
+/// Render allocation lifetimes as a Trace Event Format JSON document.
+///
+/// Every allocation in the history, plus any live allocation the history no
+/// longer holds, becomes one event on its allocating thread: a complete
+/// (`X`) event spanning its lifetime when freed, or a begin (`B`) event with
+/// no end while still live. Events are named after the variable, else the
+/// type, and carry the size and pointer in `args`.
+pub fn render_chrome_trace(tracker: &MemoryTracker) -> TrackingResult<String> {
+    let mut allocations = tracker.get_allocation_history()?;
+    let recorded: HashSet<u64> = allocations.iter().map(|a| a.seq).collect();
+    allocations.extend(
+        tracker
+            .get_active_allocations()?
+            .into_iter()
+            .filter(|a| !recorded.contains(&a.seq)),
+    );
+    allocations.sort_by_key(|a| (a.timestamp_alloc, a.seq));
+
+    let pid = std::process::id();
+    let trace = ChromeTrace {
+        trace_events: allocations
+            .iter()
+            .map(|allocation| trace_event(allocation, pid))
+            .collect(),
+        display_time_unit: "ms",
+    };
+    serde_json::to_string(&trace).map_err(|e| {
+        TrackingError::SerializationError(format!("Chrome trace serialization failed: {e}"))
+    })
+}
+
+/// Write the trace from `render_chrome_trace` to `path`.
+pub fn export_chrome_trace<P: AsRef<Path>>(tracker: &MemoryTracker, path: P) -> TrackingResult<()> {
+    let trace = render_chrome_trace(tracker)?;
+    let mut writer = BufWriter::new(File::create(path.as_ref())?);
+    writer.write_all(trace.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn trace_event(allocation: &AllocationInfo, pid: u32) -> TraceEvent {
+    let name = allocation
+        .var_name
+        .clone()
+        .or_else(|| allocation.type_name.clone())
+        .unwrap_or_else(|| "allocation".to_string());
+    // Timestamps are milliseconds; the format counts microseconds
+    let ts = allocation.timestamp_alloc.saturating_mul(1000);
+    let dur = allocation.timestamp_dealloc.map(|dealloc| {
+        dealloc
+            .saturating_sub(allocation.timestamp_alloc)
+            .saturating_mul(1000)
+    });
+
+    TraceEvent {
+        name,
+        cat: "memory",
+        ph: if dur.is_some() { "X" } else { "B" },
+        ts,
+        dur,
+        pid,
+        tid: numeric_thread_id(&allocation.thread_id),
+        args: TraceEventArgs {
+            size: allocation.size,
+            ptr: format!("{:#x}", allocation.ptr),
+            type_name: allocation.type_name.clone(),
+        },
+    }
+}
+
+/// The number inside a formatted `ThreadId(N)`, which trace viewers need as `tid`
+fn numeric_thread_id(thread_id: &str) -> u64 {
+    let digits: String = thread_id.chars().filter(char::is_ascii_digit).collect();
+    digits.parse().unwrap_or(0)
+}
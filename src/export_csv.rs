@@ -0,0 +1,63 @@
+//! CSV export of the allocation history for spreadsheets and dataframes
+
+use crate::tracker::MemoryTracker;
+use crate::types::{AllocationInfo, TrackingError, TrackingResult};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Header row of the CSV export
+const CSV_HEADER: &str = "ptr,size,var_name,type_name,timestamp_alloc,timestamp_dealloc";
+
+/// Render the allocation history as RFC 4180 CSV, one row per history entry.
+///
+/// Missing variable names, type names and deallocation times are left as
+/// blank cells.
+pub fn render_csv(tracker: &MemoryTracker) -> TrackingResult<String> {
+    let history = tracker.get_allocation_history()?;
+
+    let mut csv = String::from(CSV_HEADER);
+    csv.push_str("\r\n");
+    for allocation in &history {
+        write_allocation_row(&mut csv, allocation);
+    }
+    Ok(csv)
+}
+
+/// Write the CSV from `render_csv` to `path`.
+pub fn export_to_csv<P: AsRef<Path>>(tracker: &MemoryTracker, path: P) -> TrackingResult<()> {
+    let csv = render_csv(tracker)?;
+    let write = || -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path.as_ref())?);
+        writer.write_all(csv.as_bytes())?;
+        writer.flush()
+    };
+    write().map_err(|e| TrackingError::SerializationError(format!("CSV export failed: {e}")))
+}
+
+fn write_allocation_row(csv: &mut String, allocation: &AllocationInfo) {
+    let fields = [
+        allocation.ptr.to_string(),
+        allocation.size.to_string(),
+        csv_field(allocation.var_name.as_deref()),
+        csv_field(allocation.type_name.as_deref()),
+        allocation.timestamp_alloc.to_string(),
+        allocation
+            .timestamp_dealloc
+            .map_or_else(String::new, |t| t.to_string()),
+    ];
+    csv.push_str(&fields.join(","));
+    csv.push_str("\r\n");
+}
+
+/// Quote a text field when it contains a delimiter, quote or line break,
+/// doubling embedded quotes; absent values are empty
+fn csv_field(value: Option<&str>) -> String {
+    match value {
+        Some(value) if value.contains([',', '"', '\r', '\n']) => {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        }
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
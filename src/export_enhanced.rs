@@ -34,6 +34,7 @@ fn calculate_allocation_percentiles(allocations: &[AllocationInfo]) -> (usize, u
 
     (median, p95)
 }
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
@@ -124,7 +125,7 @@ pub fn enhance_type_information(
 }
 
 /// Enhanced type analysis with detailed subcategory detection
-fn analyze_type_with_detailed_subcategory(type_name: &str) -> (String, String, String) {
+pub(crate) fn analyze_type_with_detailed_subcategory(type_name: &str) -> (String, String, String) {
     let clean_type = type_name.trim();
 
     // Handle empty or explicitly unknown types first
@@ -486,7 +487,7 @@ pub fn categorize_enhanced_allocations(
 }
 
 /// Enhanced type information with variable names and categorization
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EnhancedTypeInfo {
     /// Simplified type name for display
     pub simplified_name: String,
@@ -527,7 +528,7 @@ pub fn export_enhanced_svg<P: AsRef<Path>>(tracker: &MemoryTracker, path: P) ->
     }
 
     let active_allocations = tracker.get_active_allocations()?;
-    let memory_by_type = tracker.get_memory_by_type()?;
+    let memory_by_type = crate::tracker::aggregate_memory_by_type(&active_allocations);
     let stats = tracker.get_stats()?;
 
     // Filter out unknown types and enhance type information
@@ -2044,7 +2045,7 @@ pub fn add_memory_timeline(
             // If sizes are equal, sort by lifecycle duration (timestamp difference as proxy)
             let a_duration = a.timestamp_dealloc.unwrap_or(a.timestamp_alloc + 1000) - a.timestamp_alloc;
             let b_duration = b.timestamp_dealloc.unwrap_or(b.timestamp_alloc + 1000) - b.timestamp_alloc;
-            b_duration.cmp(&a_duration)
+            b_duration.cmp(&a_duration).then(a.seq.cmp(&b.seq))
         } else {
             size_cmp
         }
@@ -2795,7 +2796,7 @@ pub fn add_enhanced_timeline_dashboard(
             // If sizes are equal, sort by lifecycle duration (timestamp difference as proxy)
             let a_duration = a.timestamp_dealloc.unwrap_or(a.timestamp_alloc + 1000) - a.timestamp_alloc;
             let b_duration = b.timestamp_dealloc.unwrap_or(b.timestamp_alloc + 1000) - b.timestamp_alloc;
-            b_duration.cmp(&a_duration)
+            b_duration.cmp(&a_duration).then(a.seq.cmp(&b.seq))
         } else {
             size_cmp
         }
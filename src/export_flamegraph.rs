@@ -0,0 +1,73 @@
+//! Folded-stack export of live memory for flamegraph.pl and inferno
+
+use crate::tracker::MemoryTracker;
+use crate::types::{AllocationInfo, TrackingError, TrackingResult};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Root frame of allocations tracked without a backtrace
+const NO_BACKTRACE_FRAME: &str = "[no-backtrace]";
+
+/// Render the active allocations as folded stacks, one
+/// `outermost;...;innermost <bytes>` line per distinct call stack.
+///
+/// Bytes of allocations sharing a stack are summed. Frames are function
+/// names without their source locations, so one function is one frame.
+/// Allocations without a captured backtrace (see
+/// `MemoryTracker::with_backtraces`) are kept under a `[no-backtrace]` root
+/// frame, split by type name.
+pub fn render_flamegraph_folded(tracker: &MemoryTracker) -> TrackingResult<String> {
+    let mut stacks: BTreeMap<String, usize> = BTreeMap::new();
+    for allocation in tracker.get_active_allocations()? {
+        let bytes = stacks.entry(folded_stack(&allocation)).or_insert(0);
+        *bytes = bytes.saturating_add(allocation.size);
+    }
+
+    let mut folded = String::new();
+    for (stack, bytes) in stacks {
+        folded.push_str(&stack);
+        folded.push(' ');
+        folded.push_str(&bytes.to_string());
+        folded.push('\n');
+    }
+    Ok(folded)
+}
+
+/// Write the folded stacks from `render_flamegraph_folded` to `path`.
+pub fn export_flamegraph_folded<P: AsRef<Path>>(
+    tracker: &MemoryTracker,
+    path: P,
+) -> TrackingResult<()> {
+    let folded = render_flamegraph_folded(tracker)?;
+    let write = || -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path.as_ref())?);
+        writer.write_all(folded.as_bytes())?;
+        writer.flush()
+    };
+    write().map_err(|e| TrackingError::SerializationError(format!("Flamegraph export failed: {e}")))
+}
+
+/// The allocation's stack, outermost frame first
+fn folded_stack(allocation: &AllocationInfo) -> String {
+    match allocation.backtrace.as_deref() {
+        Some(frames) if !frames.is_empty() => frames
+            .iter()
+            .rev()
+            .map(|frame| folded_frame(frame))
+            .collect::<Vec<_>>()
+            .join(";"),
+        _ => format!(
+            "{NO_BACKTRACE_FRAME};{}",
+            folded_frame(allocation.type_name.as_deref().unwrap_or("Unknown"))
+        ),
+    }
+}
+
+/// A frame's function name, with the characters the format reserves replaced:
+/// `;` separates frames and newlines separate stacks
+fn folded_frame(frame: &str) -> String {
+    let symbol = frame.split_once(" at ").map_or(frame, |(symbol, _)| symbol);
+    symbol.replace(';', ":").replace(['\n', '\r'], " ")
+}
@@ -0,0 +1,75 @@
+//! InfluxDB line protocol export of tracker statistics
+
+use crate::tracker::MemoryTracker;
+use crate::types::{InfluxExportOptions, TrackingResult};
+use std::fmt::Write;
+
+/// Render current statistics and per-type usage as InfluxDB line protocol.
+///
+/// Produces one line with the overall statistics and one line per type,
+/// tagged with `type_name`, all sharing the same nanosecond timestamp.
+pub fn export_influx_line(
+    tracker: &MemoryTracker,
+    options: &InfluxExportOptions,
+) -> TrackingResult<String> {
+    let stats = tracker.get_stats()?;
+    let memory_by_type = tracker.get_memory_by_type()?;
+    let timestamp_ns = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    let mut series = escape_measurement(&options.measurement);
+    for (key, value) in &options.tags {
+        let _ = write!(series, ",{}={}", escape_tag(key), escape_tag(value));
+    }
+
+    let mut output = String::new();
+    let _ = writeln!(
+        output,
+        "{series} active_allocations={}i,active_memory={}i,peak_allocations={}i,peak_memory={}i,\
+         total_allocated={}i,total_allocations={}i,total_deallocated={}i,total_deallocations={}i \
+         {timestamp_ns}",
+        stats.active_allocations,
+        stats.active_memory,
+        stats.peak_allocations,
+        stats.peak_memory,
+        stats.total_allocated,
+        stats.total_allocations,
+        stats.total_deallocated,
+        stats.total_deallocations,
+    );
+
+    for usage in &memory_by_type {
+        let _ = writeln!(
+            output,
+            "{series},type_name={} allocation_count={}i,total_size={}i {timestamp_ns}",
+            escape_tag(&usage.type_name),
+            usage.allocation_count,
+            usage.total_size,
+        );
+    }
+
+    Ok(output)
+}
+
+/// Escape a measurement name: commas and spaces are significant
+fn escape_measurement(name: &str) -> String {
+    escape(name, &[',', ' '])
+}
+
+/// Escape a tag key or value: commas, equals signs and spaces are significant
+fn escape_tag(tag: &str) -> String {
+    escape(tag, &[',', '=', ' '])
+}
+
+fn escape(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
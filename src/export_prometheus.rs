@@ -0,0 +1,127 @@
+//! Prometheus text exposition format export of tracker statistics
+
+use crate::tracker::MemoryTracker;
+use crate::types::{PrometheusExportOptions, TypeMemoryUsage};
+use std::fmt::Write;
+
+/// Render current statistics as Prometheus gauges and counters.
+///
+/// Every metric is preceded by its `# HELP` and `# TYPE` lines; counters
+/// carry the `_total` suffix. With `include_types`, per-type gauges labelled
+/// with `type` follow. Statistics that cannot be read are rendered as zero.
+pub fn render_prometheus(tracker: &MemoryTracker, options: &PrometheusExportOptions) -> String {
+    let stats = tracker.get_stats().unwrap_or_default();
+    let mut output = String::new();
+
+    let gauges = [
+        (
+            "memtrack_active_memory_bytes",
+            "Bytes in active tracked allocations",
+            stats.active_memory,
+        ),
+        (
+            "memtrack_active_allocations",
+            "Number of active tracked allocations",
+            stats.active_allocations,
+        ),
+        (
+            "memtrack_peak_memory_bytes",
+            "Highest number of bytes active at once",
+            stats.peak_memory,
+        ),
+        (
+            "memtrack_peak_allocations",
+            "Highest number of allocations active at once",
+            stats.peak_allocations,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        write_metric(&mut output, name, help, "gauge", value);
+    }
+
+    let counters = [
+        (
+            "memtrack_total_allocations_total",
+            "Allocations tracked since the tracker started",
+            stats.total_allocations,
+        ),
+        (
+            "memtrack_total_deallocations_total",
+            "Deallocations tracked since the tracker started",
+            stats.total_deallocations,
+        ),
+        (
+            "memtrack_total_allocated_bytes_total",
+            "Bytes allocated since the tracker started",
+            stats.total_allocated,
+        ),
+        (
+            "memtrack_total_deallocated_bytes_total",
+            "Bytes deallocated since the tracker started",
+            stats.total_deallocated,
+        ),
+    ];
+    for (name, help, value) in counters {
+        write_metric(&mut output, name, help, "counter", value);
+    }
+
+    if options.include_types {
+        let memory_by_type = tracker.get_memory_by_type().unwrap_or_default();
+        write_type_metric(
+            &mut output,
+            "memtrack_type_active_memory_bytes",
+            "Bytes in active allocations of each type",
+            &memory_by_type,
+            |usage| usage.total_size,
+        );
+        write_type_metric(
+            &mut output,
+            "memtrack_type_active_allocations",
+            "Number of active allocations of each type",
+            &memory_by_type,
+            |usage| usage.allocation_count,
+        );
+    }
+
+    output
+}
+
+fn write_metric(output: &mut String, name: &str, help: &str, kind: &str, value: usize) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} {kind}");
+    let _ = writeln!(output, "{name} {value}");
+}
+
+/// Write a gauge with one sample per type, labelled `type`
+fn write_type_metric(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    memory_by_type: &[TypeMemoryUsage],
+    value: impl Fn(&TypeMemoryUsage) -> usize,
+) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} gauge");
+    for usage in memory_by_type {
+        let _ = writeln!(
+            output,
+            "{name}{{type=\"{}\"}} {}",
+            escape_label_value(&usage.type_name),
+            value(usage)
+        );
+    }
+}
+
+/// Escape a label value: backslashes, double quotes and newlines are significant
+fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,84 @@
+//! Plain-text SQL dump of the allocation history
+
+use crate::tracker::MemoryTracker;
+use crate::types::{AllocationInfo, TrackingResult};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Write the allocation history and current statistics to `writer` as a SQL
+/// dump, one row at a time.
+///
+/// The dump is a text script, not a database file: it creates an
+/// `allocations` table with one row per history entry and a single-row
+/// `stats` table inside one transaction, using only portable SQL. Replay it
+/// into any database, e.g. `sqlite3 memory.db < dump.sql`.
+pub fn write_sql_dump<W: Write>(tracker: &MemoryTracker, writer: &mut W) -> TrackingResult<()> {
+    let history = tracker.get_allocation_history()?;
+    let stats = tracker.get_stats()?;
+
+    writer.write_all(b"BEGIN TRANSACTION;\n")?;
+    writer.write_all(
+        b"CREATE TABLE allocations (ptr INTEGER, size INTEGER, type_name TEXT, var_name TEXT, \
+          timestamp_alloc INTEGER, timestamp_dealloc INTEGER, thread_id TEXT);\n",
+    )?;
+    writer.write_all(
+        b"CREATE TABLE stats (total_allocations INTEGER, total_deallocations INTEGER, \
+          total_allocated INTEGER, total_deallocated INTEGER, active_allocations INTEGER, \
+          active_memory INTEGER, peak_allocations INTEGER, peak_memory INTEGER);\n",
+    )?;
+
+    for allocation in &history {
+        write_allocation_row(writer, allocation)?;
+    }
+    writeln!(
+        writer,
+        "INSERT INTO stats VALUES ({}, {}, {}, {}, {}, {}, {}, {});",
+        stats.total_allocations,
+        stats.total_deallocations,
+        stats.total_allocated,
+        stats.total_deallocated,
+        stats.active_allocations,
+        stats.active_memory,
+        stats.peak_allocations,
+        stats.peak_memory,
+    )?;
+    writer.write_all(b"COMMIT;\n")?;
+    Ok(())
+}
+
+/// Write the SQL dump from `write_sql_dump` to `path`.
+pub fn export_sql_dump<P: AsRef<Path>>(tracker: &MemoryTracker, path: P) -> TrackingResult<()> {
+    let mut writer = BufWriter::new(File::create(path.as_ref())?);
+    write_sql_dump(tracker, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_allocation_row<W: Write>(
+    writer: &mut W,
+    allocation: &AllocationInfo,
+) -> TrackingResult<()> {
+    writeln!(
+        writer,
+        "INSERT INTO allocations VALUES ({}, {}, {}, {}, {}, {}, {});",
+        allocation.ptr,
+        allocation.size,
+        text_literal(allocation.type_name.as_deref()),
+        text_literal(allocation.var_name.as_deref()),
+        allocation.timestamp_alloc,
+        allocation
+            .timestamp_dealloc
+            .map_or_else(|| "NULL".to_string(), |t| t.to_string()),
+        text_literal(Some(&allocation.thread_id)),
+    )?;
+    Ok(())
+}
+
+/// Quote a string as a SQL literal, or `NULL` when absent
+fn text_literal(value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("'{}'", value.replace('\'', "''")),
+        None => "NULL".to_string(),
+    }
+}
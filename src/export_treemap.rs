@@ -0,0 +1,67 @@
+//! Treemap JSON export of the category → subcategory → type hierarchy
+
+use crate::export_enhanced::analyze_type_with_detailed_subcategory;
+use crate::tracker::MemoryTracker;
+use crate::types::{TrackingError, TrackingResult, TreemapNode};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Name of the root node of an exported treemap
+const TREEMAP_ROOT_NAME: &str = "memory";
+
+/// Build the treemap hierarchy of active memory.
+///
+/// Types are categorized the same way as in `enhance_type_information`, but
+/// only top-level types become leaves: inner types (the `i32` in `Vec<i32>`)
+/// are already counted by their container, and treemap renderers sum leaf
+/// values, so including them would count those bytes twice.
+pub fn build_treemap(tracker: &MemoryTracker) -> TrackingResult<TreemapNode> {
+    let mut hierarchy: BTreeMap<String, BTreeMap<String, BTreeMap<String, usize>>> =
+        BTreeMap::new();
+
+    for usage in tracker.get_memory_by_type()? {
+        if usage.type_name == "Unknown" {
+            continue;
+        }
+        let (simplified_name, category, subcategory) =
+            analyze_type_with_detailed_subcategory(&usage.type_name);
+        *hierarchy
+            .entry(category)
+            .or_default()
+            .entry(subcategory)
+            .or_default()
+            .entry(simplified_name)
+            .or_insert(0) += usage.total_size;
+    }
+
+    let categories = hierarchy
+        .into_iter()
+        .map(|(category, subcategories)| {
+            let subcategories = subcategories
+                .into_iter()
+                .map(|(subcategory, types)| {
+                    let types = types
+                        .into_iter()
+                        .map(|(type_name, bytes)| TreemapNode::leaf(type_name, bytes))
+                        .collect();
+                    TreemapNode::branch(subcategory, types)
+                })
+                .collect();
+            TreemapNode::branch(category, subcategories)
+        })
+        .collect();
+
+    Ok(TreemapNode::branch(
+        TREEMAP_ROOT_NAME.to_string(),
+        categories,
+    ))
+}
+
+/// Write the treemap hierarchy of active memory to a JSON file
+pub fn export_treemap_json<P: AsRef<Path>>(tracker: &MemoryTracker, path: P) -> TrackingResult<()> {
+    let treemap = build_treemap(tracker)?;
+    let file = File::create(path.as_ref())?;
+    serde_json::to_writer_pretty(file, &treemap)
+        .map_err(|e| TrackingError::SerializationError(format!("Treemap export failed: {e}")))
+}
@@ -7,20 +7,50 @@
 
 #![warn(missing_docs)]
 
-pub mod allocator;
+pub mod aggregate;
+pub mod alloc;
+mod callsite;
+pub mod events;
+pub mod export_binary;
+pub mod export_chrome_trace;
+pub mod export_csv;
 pub mod export_enhanced;
+pub mod export_flamegraph;
+pub mod export_influx;
+pub mod export_prometheus;
+pub mod export_sql;
+pub mod export_treemap;
+mod pool;
+mod recent_frees;
+pub mod report;
+mod retained;
+pub mod scope;
+mod shards;
+#[cfg(all(unix, feature = "socket-server"))]
+mod socket_server;
+pub mod state;
+pub mod test_support;
+mod thresholds;
+pub mod timeline_delta;
 pub mod tracker;
 pub mod types;
 pub mod utils;
 pub mod visualization;
 
 // Re-export main types for easier use
-pub use allocator::TrackingAllocator;
-pub use tracker::{get_global_tracker, MemoryTracker};
+pub use aggregate::AggregateOnlyTracker;
+pub use alloc::TrackingAllocator;
+pub use report::AnalyzedReport;
+pub use tracker::{get_global_tracker, init_global_tracker, MemoryTracker, MemoryTrackerBuilder};
 pub use types::{AllocationInfo, TrackingError, TrackingResult};
 pub use utils::{format_bytes, get_simple_type, simplify_type_name};
 pub use visualization::{export_lifecycle_timeline, export_memory_analysis};
 
+/// Former home of the tracking allocator, which now lives in [`alloc`].
+pub mod allocator {
+    pub use crate::alloc::TrackingAllocator;
+}
+
 // Set up the global allocator when the tracking-allocator feature is enabled
 #[cfg(feature = "tracking-allocator")]
 #[global_allocator]
@@ -142,10 +172,32 @@ pub fn _track_var_impl<T: Trackable>(var: &T, var_name: &str) -> TrackingResult<
     }
 }
 
+/// Macro to track an allocation and attribute it to the calling crate.
+///
+/// The crate name is taken from `CARGO_PKG_NAME` at the call site, so in a
+/// workspace each member's allocations are reported under its own name by
+/// `MemoryTracker::get_memory_by_crate`.
+///
+/// # Example
+/// ```rust
+/// use memscope_rs::track_crate;
+///
+/// let buffer = vec![0u8; 1024];
+/// track_crate!(buffer.as_ptr() as usize, buffer.capacity()).unwrap();
+/// ```
+#[macro_export]
+macro_rules! track_crate {
+    ($ptr:expr, $size:expr) => {
+        $crate::get_global_tracker().track_crate_allocation($ptr, $size, env!("CARGO_PKG_NAME"))
+    };
+}
+
 /// Initialize the memory tracking system.
 ///
 /// This function sets up the tracing subscriber and prepares the global tracker.
-/// Call this early in your application, typically in main().
+/// Call this early in your application, typically in main(): the tracking
+/// allocator records allocations from the moment the global tracker exists.
+/// To use a configured tracker, call `init_global_tracker` first.
 ///
 /// # Example
 /// ```rust
@@ -162,6 +214,7 @@ pub fn init() {
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
+    get_global_tracker();
 
     tracing::info!("memscope-rs initialized");
 }
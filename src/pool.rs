@@ -0,0 +1,83 @@
+//! Per-pool allocation stacks, used to detect frees out of LIFO order.
+
+use crate::types::{TrackingError, TrackingResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Live allocations of each arena/pool, in allocation order
+#[derive(Default)]
+pub(crate) struct PoolStacks {
+    pools: Mutex<HashMap<u64, PoolState>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct PoolState {
+    /// `(ptr, size)` of the live allocations, most recent last
+    stack: Vec<(usize, usize)>,
+    /// Frees that did not target the top of the stack
+    violations: usize,
+}
+
+impl PoolStacks {
+    /// Pools restored from `snapshot`
+    pub(crate) fn from_pools(pools: HashMap<u64, PoolState>) -> Self {
+        Self {
+            pools: Mutex::new(pools),
+        }
+    }
+
+    /// Copy of every pool's live stack and violation count
+    pub(crate) fn snapshot(&self) -> HashMap<u64, PoolState> {
+        match self.pools.lock() {
+            Ok(pools) => pools.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Push a new allocation onto its pool's stack
+    pub(crate) fn push(&self, pool_id: u64, ptr: usize, size: usize) {
+        let mut pools = match self.pools.lock() {
+            Ok(pools) => pools,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        pools.entry(pool_id).or_default().stack.push((ptr, size));
+    }
+
+    /// Remove a freed allocation from its pool's stack.
+    ///
+    /// Returns the pointer that should have been freed instead when `ptr` was
+    /// not the top of the stack, which counts as a violation.
+    pub(crate) fn pop(&self, pool_id: u64, ptr: usize) -> TrackingResult<Option<usize>> {
+        let mut pools = match self.pools.lock() {
+            Ok(pools) => pools,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let pool = pools
+            .get_mut(&pool_id)
+            .ok_or(TrackingError::InvalidPointer { ptr })?;
+        let index = pool
+            .stack
+            .iter()
+            .rposition(|&(live, _)| live == ptr)
+            .ok_or(TrackingError::InvalidPointer { ptr })?;
+
+        let top = pool.stack.len() - 1;
+        let expected = pool.stack[top].0;
+        pool.stack.remove(index);
+        if index == top {
+            return Ok(None);
+        }
+        pool.violations += 1;
+        Ok(Some(expected))
+    }
+
+    /// Number of out-of-order frees seen in a pool
+    pub(crate) fn violations(&self, pool_id: u64) -> usize {
+        let pools = match self.pools.lock() {
+            Ok(pools) => pools,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        pools.get(&pool_id).map_or(0, |pool| pool.violations)
+    }
+}
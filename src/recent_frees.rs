@@ -0,0 +1,101 @@
+//! Window of recently freed pointers, used to classify double frees.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// The last few freed pointers, and the suspected double frees seen so far
+#[derive(Default)]
+pub(crate) struct RecentFrees {
+    /// Pointers remembered per window (0 = detection off), read without the lock
+    window: AtomicUsize,
+    state: Mutex<RecentFreesState>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct RecentFreesState {
+    /// Freed pointers, most recent last
+    freed: VecDeque<usize>,
+    /// Pointers freed again while still in `freed`, in detection order
+    suspected: Vec<usize>,
+}
+
+impl RecentFrees {
+    /// A window of `window` pointers restored from `snapshot`
+    pub(crate) fn from_state(window: usize, state: RecentFreesState) -> Self {
+        Self {
+            window: AtomicUsize::new(window),
+            state: Mutex::new(state),
+        }
+    }
+
+    /// The window size, and a copy of the remembered and suspected frees
+    pub(crate) fn snapshot(&self) -> (usize, RecentFreesState) {
+        let state = self.lock();
+        (self.window.load(Ordering::Relaxed), state.clone())
+    }
+
+    /// Remember up to `window` freed pointers, or stop detecting with 0
+    pub(crate) fn set_window(&self, window: usize) {
+        let mut state = self.lock();
+        self.window.store(window, Ordering::Relaxed);
+        while state.freed.len() > window {
+            state.freed.pop_front();
+        }
+    }
+
+    /// Remember a successfully tracked free
+    pub(crate) fn record_free(&self, ptr: usize) {
+        let window = self.window.load(Ordering::Relaxed);
+        if window == 0 {
+            return;
+        }
+        let mut state = self.lock();
+        if state.freed.len() >= window {
+            state.freed.pop_front();
+        }
+        state.freed.push_back(ptr);
+    }
+
+    /// Forget a pointer the allocator handed out again, so freeing it is legitimate
+    pub(crate) fn record_reuse(&self, ptr: usize) {
+        if self.window.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let mut state = self.lock();
+        if let Some(index) = state.freed.iter().rposition(|&freed| freed == ptr) {
+            state.freed.remove(index);
+        }
+    }
+
+    /// Classify a free of an unknown pointer; returns true if it was freed recently
+    pub(crate) fn check_unknown_free(&self, ptr: usize) -> bool {
+        if self.window.load(Ordering::Relaxed) == 0 {
+            return false;
+        }
+        let mut state = self.lock();
+        let double_free = state.freed.contains(&ptr);
+        if double_free {
+            state.suspected.push(ptr);
+        }
+        double_free
+    }
+
+    /// Suspected double frees, in detection order
+    pub(crate) fn suspected(&self) -> Vec<usize> {
+        self.lock().suspected.clone()
+    }
+
+    /// Forget every remembered free and suspected double free
+    pub(crate) fn clear(&self) {
+        *self.lock() = RecentFreesState::default();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RecentFreesState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! Immutable analysis reports produced from a memory tracker.
+
+use crate::tracker::MemoryTracker;
+use crate::types::{
+    AllocationInfo, ExportRoot, MemoryStats, ReportOptions, TrackingResult, TypeMemoryUsage,
+};
+use crate::utils::format_bytes;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Allocations alive longer than this are reported as leak candidates (milliseconds)
+const LEAK_CANDIDATE_AGE_MS: u128 = 10_000;
+
+/// Largest leak candidates listed individually in the text report
+const TEXT_REPORT_MAX_LEAKS: usize = 10;
+
+/// Allocation size percentiles in bytes
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SizePercentiles {
+    /// 50th percentile (median)
+    pub p50: usize,
+    /// 90th percentile
+    pub p90: usize,
+    /// 95th percentile
+    pub p95: usize,
+    /// 99th percentile
+    pub p99: usize,
+}
+
+/// Immutable snapshot of a fully analyzed tracker state.
+///
+/// Created by `MemoryTracker::finalize`. All values are computed once, so
+/// querying the report never touches the live tracker or its locks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzedReport {
+    generated_at: u128,
+    stats: MemoryStats,
+    memory_by_type: Vec<TypeMemoryUsage>,
+    hierarchy: ExportRoot,
+    leak_candidates: Vec<AllocationInfo>,
+    size_percentiles: SizePercentiles,
+}
+
+impl AnalyzedReport {
+    pub(crate) fn new(
+        stats: MemoryStats,
+        memory_by_type: Vec<TypeMemoryUsage>,
+        hierarchy: ExportRoot,
+        active_allocations: &[AllocationInfo],
+    ) -> Self {
+        let generated_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let mut leak_candidates: Vec<AllocationInfo> = active_allocations
+            .iter()
+            .filter(|a| {
+                !a.warmup
+                    && !a.expected_persistent
+                    && generated_at.saturating_sub(a.timestamp_alloc) > LEAK_CANDIDATE_AGE_MS
+            })
+            .cloned()
+            .collect();
+        leak_candidates.sort_by_key(|a| std::cmp::Reverse(a.size));
+
+        Self {
+            generated_at,
+            stats,
+            memory_by_type,
+            hierarchy,
+            leak_candidates,
+            size_percentiles: calculate_size_percentiles(active_allocations),
+        }
+    }
+
+    /// Time the report was generated (milliseconds since UNIX_EPOCH)
+    pub fn generated_at(&self) -> u128 {
+        self.generated_at
+    }
+
+    /// Memory statistics at the time of the report
+    pub fn stats(&self) -> &MemoryStats {
+        &self.stats
+    }
+
+    /// Memory usage grouped by type, sorted by total size descending
+    pub fn memory_by_type(&self) -> &[TypeMemoryUsage] {
+        &self.memory_by_type
+    }
+
+    /// Category/subcategory hierarchy, in the same shape as the JSON export
+    pub fn hierarchy(&self) -> &ExportRoot {
+        &self.hierarchy
+    }
+
+    /// Active allocations older than 10 seconds, sorted by size descending.
+    ///
+    /// Allocations made during a warmup window or marked as expected
+    /// persistent are excluded.
+    pub fn leak_candidates(&self) -> &[AllocationInfo] {
+        &self.leak_candidates
+    }
+
+    /// Total bytes held by leak candidates
+    pub fn leak_candidate_bytes(&self) -> usize {
+        self.leak_candidates.iter().map(|a| a.size).sum()
+    }
+
+    /// Percentiles of active allocation sizes
+    pub fn size_percentiles(&self) -> &SizePercentiles {
+        &self.size_percentiles
+    }
+
+    /// The type holding the most memory, if any
+    pub fn largest_type(&self) -> Option<&TypeMemoryUsage> {
+        self.memory_by_type.first()
+    }
+}
+
+/// Render the plain-text report of `MemoryTracker::text_report`
+pub(crate) fn render_text_report(
+    tracker: &MemoryTracker,
+    options: &ReportOptions,
+) -> TrackingResult<String> {
+    let stats = tracker.get_stats()?;
+    let mut out = String::new();
+
+    let _ = writeln!(out, "Memory report");
+    let _ = writeln!(
+        out,
+        "  Active: {} allocations, {} (peak {})",
+        stats.active_allocations,
+        format_bytes(stats.active_memory),
+        format_bytes(stats.peak_memory)
+    );
+    let _ = writeln!(
+        out,
+        "  Total: {} allocations ({}), {} deallocations ({})",
+        stats.total_allocations,
+        format_bytes(stats.total_allocated),
+        stats.total_deallocations,
+        format_bytes(stats.total_deallocated)
+    );
+
+    if options.top_types > 0 {
+        let memory_by_type = tracker.get_memory_by_type()?;
+        let _ = writeln!(out, "\nTop types");
+        if memory_by_type.is_empty() {
+            let _ = writeln!(out, "  (no typed allocations)");
+        }
+        for (rank, usage) in memory_by_type.iter().take(options.top_types).enumerate() {
+            let _ = writeln!(
+                out,
+                "  {}. {} {} in {} allocations",
+                rank + 1,
+                usage.type_name,
+                format_bytes(usage.total_size),
+                usage.allocation_count
+            );
+        }
+    }
+
+    if options.include_leaks {
+        let leaks = tracker.detect_leaks(std::time::Duration::from_millis(options.leak_age_ms))?;
+        let leak_bytes: usize = leaks.iter().map(|a| a.size).sum();
+        let _ = writeln!(
+            out,
+            "\nLeak candidates (older than {}ms)\n  {} allocations, {}",
+            options.leak_age_ms,
+            leaks.len(),
+            format_bytes(leak_bytes)
+        );
+        for leak in leaks.iter().take(TEXT_REPORT_MAX_LEAKS) {
+            let _ = writeln!(out, "  {leak}");
+        }
+    }
+
+    if options.include_health {
+        let consistency = tracker.verify_consistency()?;
+        let _ = writeln!(out, "\nHealth");
+        if consistency.is_consistent() {
+            let _ = writeln!(out, "  Statistics consistent with active allocations");
+        } else {
+            let _ = writeln!(
+                out,
+                "  Statistics drift: {} allocations, {} bytes (see reconcile)",
+                consistency.allocation_drift(),
+                consistency.memory_drift()
+            );
+        }
+        let _ = writeln!(
+            out,
+            "  Pointer reuse collisions: {}",
+            stats.ptr_reuse_collisions
+        );
+        let overhead_ratio = tracker.overhead_ratio()?;
+        if overhead_ratio.is_finite() {
+            let _ = writeln!(
+                out,
+                "  Tracker overhead: {overhead_ratio:.2} bytes per live byte"
+            );
+        }
+    }
+
+    if options.include_percentiles {
+        let percentiles = calculate_size_percentiles(&tracker.get_active_allocations()?);
+        let _ = writeln!(
+            out,
+            "\nSize percentiles\n  p50={} p90={} p95={} p99={}",
+            format_bytes(percentiles.p50),
+            format_bytes(percentiles.p90),
+            format_bytes(percentiles.p95),
+            format_bytes(percentiles.p99)
+        );
+    }
+
+    Ok(out)
+}
+
+/// Nearest-rank percentiles over allocation sizes
+fn calculate_size_percentiles(allocations: &[AllocationInfo]) -> SizePercentiles {
+    if allocations.is_empty() {
+        return SizePercentiles::default();
+    }
+
+    let mut sizes: Vec<usize> = allocations.iter().map(|a| a.size).collect();
+    sizes.sort_unstable();
+
+    let percentile = |p: f64| {
+        let rank = ((p / 100.0) * sizes.len() as f64).ceil() as usize;
+        sizes[rank.clamp(1, sizes.len()) - 1]
+    };
+
+    SizePercentiles {
+        p50: percentile(50.0),
+        p90: percentile(90.0),
+        p95: percentile(95.0),
+        p99: percentile(99.0),
+    }
+}
@@ -0,0 +1,99 @@
+//! Parent/child links between allocations, used for retained-size queries.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Directed "parent owns child" edges between allocation pointers
+#[derive(Default)]
+pub(crate) struct ChildLinks {
+    edges: Mutex<HashMap<usize, Vec<usize>>>,
+    /// Set once any link is added, so deallocation skips the lock until then
+    populated: AtomicBool,
+}
+
+impl ChildLinks {
+    /// Links restored from `snapshot`
+    pub(crate) fn from_edges(edges: HashMap<usize, Vec<usize>>) -> Self {
+        let populated = AtomicBool::new(!edges.is_empty());
+        Self {
+            edges: Mutex::new(edges),
+            populated,
+        }
+    }
+
+    /// Copy of every link, parent -> children
+    pub(crate) fn snapshot(&self) -> HashMap<usize, Vec<usize>> {
+        match self.edges.lock() {
+            Ok(edges) => edges.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Record that the allocation at `parent` owns the allocation at `child`
+    pub(crate) fn link(&self, parent: usize, child: usize) {
+        let mut edges = match self.edges.lock() {
+            Ok(edges) => edges,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let children = edges.entry(parent).or_default();
+        if !children.contains(&child) {
+            children.push(child);
+        }
+        self.populated.store(true, Ordering::Release);
+    }
+
+    /// Drop the outgoing links of a freed allocation so a reused address starts clean
+    pub(crate) fn forget(&self, ptr: usize) {
+        if !self.populated.load(Ordering::Acquire) {
+            return;
+        }
+        // Skip if busy, like the other optional bookkeeping on the deallocation path
+        if let Ok(mut edges) = self.edges.try_lock() {
+            edges.remove(&ptr);
+        }
+    }
+
+    /// Pointers that own at least one child
+    pub(crate) fn parents(&self) -> Vec<usize> {
+        if !self.populated.load(Ordering::Acquire) {
+            return Vec::new();
+        }
+        let edges = match self.edges.lock() {
+            Ok(edges) => edges,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        edges.keys().copied().collect()
+    }
+
+    /// Drop every link
+    pub(crate) fn clear(&self) {
+        let mut edges = match self.edges.lock() {
+            Ok(edges) => edges,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        edges.clear();
+        self.populated.store(false, Ordering::Release);
+    }
+
+    /// Every pointer reachable from `root` through child links, including `root`.
+    ///
+    /// Cycles are followed once.
+    pub(crate) fn reachable_from(&self, root: usize) -> HashSet<usize> {
+        let edges = match self.edges.lock() {
+            Ok(edges) => edges,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let mut reachable = HashSet::from([root]);
+        let mut pending = vec![root];
+        while let Some(ptr) = pending.pop() {
+            for &child in edges.get(&ptr).into_iter().flatten() {
+                if reachable.insert(child) {
+                    pending.push(child);
+                }
+            }
+        }
+        reachable
+    }
+}
@@ -0,0 +1,252 @@
+//! Nested scope measurements backed by a per-thread scope stack.
+
+use crate::tracker::MemoryTracker;
+use crate::types::{ScopeAttribution, ScopeOptions, ScopeReport};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Number of open scopes across all threads, so tracking skips the
+/// thread-local lookup entirely when nothing is being measured
+static OPEN_SCOPES: AtomicUsize = AtomicUsize::new(0);
+
+/// `(ptr, seq)` of an allocation made during a scope
+type ScopeAllocation = (usize, u64);
+
+static NEXT_SCOPE_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static SCOPE_STACK: RefCell<Vec<ScopeFrame>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Byte and count totals collected for one scope
+#[derive(Debug, Clone, Copy, Default)]
+struct ScopeTotals {
+    allocated_bytes: usize,
+    allocation_count: usize,
+    deallocated_bytes: usize,
+    deallocation_count: usize,
+}
+
+impl ScopeTotals {
+    fn add(&mut self, other: &ScopeTotals) {
+        self.allocated_bytes += other.allocated_bytes;
+        self.allocation_count += other.allocation_count;
+        self.deallocated_bytes += other.deallocated_bytes;
+        self.deallocation_count += other.deallocation_count;
+    }
+}
+
+struct ScopeFrame {
+    id: u64,
+    /// Address of the tracker the scope was opened on
+    tracker: usize,
+    name: String,
+    attribution: ScopeAttribution,
+    started: std::time::Instant,
+    /// Activity recorded while this scope was the innermost one
+    own: ScopeTotals,
+    /// Inclusive activity of child scopes that have already finished
+    children: ScopeTotals,
+    /// Allocations made while this scope was innermost, when escapes are tracked
+    own_allocations: Option<Vec<ScopeAllocation>>,
+    /// Allocations made by finished child scopes, when escapes are tracked
+    child_allocations: Option<Vec<ScopeAllocation>>,
+}
+
+/// An open scope measurement, created by `MemoryTracker::begin_scope` or
+/// `MemoryTracker::scope`.
+///
+/// Call `finish` to close the scope and get its report. A guard dropped
+/// without `finish` still closes the scope; a `begin_scope` guard discards the
+/// report, while a `scope` guard logs it and stores it on the tracker. Guards
+/// are tied to the thread that opened them.
+#[must_use = "a scope is closed as soon as its guard is dropped"]
+pub struct ScopeGuard<'a> {
+    tracker: &'a MemoryTracker,
+    id: u64,
+    finished: bool,
+    /// Log and store the report on drop (guards from `MemoryTracker::scope`)
+    record_on_drop: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a> ScopeGuard<'a> {
+    pub(crate) fn begin(tracker: &'a MemoryTracker, name: &str, options: &ScopeOptions) -> Self {
+        let id = NEXT_SCOPE_ID.fetch_add(1, Ordering::Relaxed);
+        let frame = ScopeFrame {
+            id,
+            tracker: tracker.scope_key(),
+            name: name.to_string(),
+            attribution: options.attribution,
+            started: std::time::Instant::now(),
+            own: ScopeTotals::default(),
+            children: ScopeTotals::default(),
+            own_allocations: options.track_escapes.then(Vec::new),
+            child_allocations: options.track_escapes.then(Vec::new),
+        };
+        SCOPE_STACK.with(|stack| stack.borrow_mut().push(frame));
+        OPEN_SCOPES.fetch_add(1, Ordering::Relaxed);
+
+        Self {
+            tracker,
+            id,
+            finished: false,
+            record_on_drop: false,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Open a scope whose report is logged and stored on the tracker when the
+    /// guard is dropped
+    pub(crate) fn begin_recorded(tracker: &'a MemoryTracker, name: &str) -> Self {
+        let mut guard = Self::begin(tracker, name, &ScopeOptions::default());
+        guard.record_on_drop = true;
+        guard
+    }
+
+    /// Close the scope and report the memory activity attributed to it.
+    ///
+    /// When the scope was opened with `track_escapes`, the report also lists
+    /// the allocations made during the scope that are still alive: memory
+    /// the measured code returned, stored elsewhere or leaked.
+    pub fn finish(mut self) -> ScopeReport {
+        self.finished = true;
+        // The frame always exists: it is only removed by `finish` or `drop`
+        let Some((mut report, allocations)) = pop_frame(self.id) else {
+            return ScopeReport {
+                name: String::new(),
+                depth: 0,
+                attribution: ScopeAttribution::default(),
+                allocated_bytes: 0,
+                allocation_count: 0,
+                deallocated_bytes: 0,
+                deallocation_count: 0,
+                duration_ms: 0,
+                escaped_ptrs: None,
+            };
+        };
+        report.escaped_ptrs =
+            allocations.map(|allocations| self.tracker.still_active(&allocations));
+        report
+    }
+}
+
+impl Drop for ScopeGuard<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let popped = pop_frame(self.id);
+        if let (true, Some((report, _))) = (self.record_on_drop, popped) {
+            tracing::info!(
+                "Scope '{}': {:+} bytes, {:+} allocations ({} allocated, {} freed) in {} ms",
+                report.name,
+                report.net_bytes(),
+                report.net_allocations(),
+                report.allocated_bytes,
+                report.deallocated_bytes,
+                report.duration_ms
+            );
+            self.tracker.record_scope_report(report);
+        }
+    }
+}
+
+/// Remove a frame from the current thread's stack and build its report.
+///
+/// The frame's inclusive totals are handed to the nearest enclosing scope on
+/// the same tracker, so parents see their children without counting them twice.
+fn pop_frame(id: u64) -> Option<(ScopeReport, Option<Vec<ScopeAllocation>>)> {
+    let frame = SCOPE_STACK
+        .try_with(|stack| {
+            let mut stack = stack.borrow_mut();
+            let index = stack.iter().rposition(|frame| frame.id == id)?;
+            let frame = stack.remove(index);
+
+            let mut inclusive = frame.own;
+            inclusive.add(&frame.children);
+            if let Some(parent) = stack[..index]
+                .iter_mut()
+                .rev()
+                .find(|parent| parent.tracker == frame.tracker)
+            {
+                parent.children.add(&inclusive);
+                if let Some(parent_allocations) = &mut parent.child_allocations {
+                    parent_allocations.extend(frame.own_allocations.iter().flatten());
+                    parent_allocations.extend(frame.child_allocations.iter().flatten());
+                }
+            }
+
+            let depth = stack[..index]
+                .iter()
+                .filter(|parent| parent.tracker == frame.tracker)
+                .count();
+            Some((frame, inclusive, depth))
+        })
+        .ok()
+        .flatten();
+    OPEN_SCOPES.fetch_sub(1, Ordering::Relaxed);
+
+    let (frame, inclusive, depth) = frame?;
+    let (totals, allocations) = match frame.attribution {
+        ScopeAttribution::Exclusive => (frame.own, frame.own_allocations),
+        ScopeAttribution::Inclusive => (
+            inclusive,
+            frame.own_allocations.map(|mut own| {
+                own.extend(frame.child_allocations.into_iter().flatten());
+                own
+            }),
+        ),
+    };
+    let report = ScopeReport {
+        name: frame.name,
+        depth,
+        attribution: frame.attribution,
+        allocated_bytes: totals.allocated_bytes,
+        allocation_count: totals.allocation_count,
+        deallocated_bytes: totals.deallocated_bytes,
+        deallocation_count: totals.deallocation_count,
+        duration_ms: frame.started.elapsed().as_millis() as u64,
+        escaped_ptrs: None,
+    };
+    Some((report, allocations))
+}
+
+/// Attribute an allocation to the innermost open scope of `tracker` on this thread
+pub(crate) fn record_allocation(tracker: usize, ptr: usize, seq: u64, size: usize) {
+    with_innermost(tracker, |frame| {
+        frame.own.allocated_bytes += size;
+        frame.own.allocation_count += 1;
+        if let Some(allocations) = &mut frame.own_allocations {
+            allocations.push((ptr, seq));
+        }
+    });
+}
+
+/// Attribute a deallocation to the innermost open scope of `tracker` on this thread
+pub(crate) fn record_deallocation(tracker: usize, size: usize) {
+    with_innermost(tracker, |frame| {
+        frame.own.deallocated_bytes += size;
+        frame.own.deallocation_count += 1;
+    });
+}
+
+fn with_innermost(tracker: usize, update: impl FnOnce(&mut ScopeFrame)) {
+    if OPEN_SCOPES.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    // The stack may be borrowed when the allocator is re-entered while a
+    // frame is being pushed; that allocation is bookkeeping and is skipped
+    let _ = SCOPE_STACK.try_with(|stack| {
+        if let Ok(mut stack) = stack.try_borrow_mut() {
+            if let Some(frame) = stack
+                .iter_mut()
+                .rev()
+                .find(|frame| frame.tracker == tracker)
+            {
+                update(frame);
+            }
+        }
+    });
+}
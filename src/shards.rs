@@ -0,0 +1,194 @@
+//! Active allocation map split across independently locked shards.
+
+use crate::alloc::UntrackedScope;
+use crate::types::AllocationInfo;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+/// Shards used when none are configured
+pub const DEFAULT_SHARD_COUNT: usize = 16;
+
+/// Fibonacci hashing multiplier (2^64 / golden ratio). Allocator pointers
+/// are aligned, so their low bits are mostly zero and a plain `ptr % N`
+/// would leave most shards unused.
+const SHARD_HASH_MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// One shard of the active allocation map (ptr -> allocation info)
+pub(crate) type AllocationMap = HashMap<usize, AllocationInfo>;
+
+/// Active allocations spread over `N` mutex-protected maps, so concurrent
+/// tracking of different pointers rarely contends on the same lock
+pub(crate) struct ShardedAllocations {
+    shards: Box<[Mutex<AllocationMap>]>,
+}
+
+impl ShardedAllocations {
+    /// Create `shard_count` empty shards (at least one)
+    pub(crate) fn new(shard_count: usize) -> Self {
+        let shards = (0..shard_count.max(1))
+            .map(|_| Mutex::new(AllocationMap::new()))
+            .collect();
+        Self { shards }
+    }
+
+    /// Distribute existing allocations over `shard_count` shards
+    pub(crate) fn from_allocations(
+        allocations: impl IntoIterator<Item = AllocationInfo>,
+        shard_count: usize,
+    ) -> Self {
+        let sharded = Self::new(shard_count);
+        {
+            let mut all = sharded.lock_all();
+            for allocation in allocations {
+                all.insert(allocation.ptr, allocation);
+            }
+        }
+        sharded
+    }
+
+    pub(crate) fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The shard holding `ptr`
+    pub(crate) fn shard(&self, ptr: usize) -> &Mutex<AllocationMap> {
+        &self.shards[shard_index(ptr, self.shards.len())]
+    }
+
+    /// Lock every shard, blocking, in index order.
+    ///
+    /// Always taking them in the same order keeps two whole-map operations
+    /// from deadlocking; the tracking fast path only ever holds one shard.
+    /// This thread's allocations are untracked while the shards are held.
+    pub(crate) fn lock_all(&self) -> AllShards<'_> {
+        let untracked = UntrackedScope::enter();
+        let guards = self
+            .shards
+            .iter()
+            .map(|shard| match shard.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => poisoned.into_inner(),
+            })
+            .collect();
+        AllShards {
+            guards,
+            _untracked: untracked,
+        }
+    }
+
+    /// Lock every shard in index order without waiting, or `None` if any is
+    /// busy (the shards taken so far are released again).
+    pub(crate) fn try_lock_all(&self) -> Option<AllShards<'_>> {
+        let untracked = UntrackedScope::enter();
+        let guards = self
+            .shards
+            .iter()
+            .map(|shard| match shard.try_lock() {
+                Ok(guard) => Some(guard),
+                Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+                Err(TryLockError::WouldBlock) => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(AllShards {
+            guards,
+            _untracked: untracked,
+        })
+    }
+
+    /// Copy the allocations matching `keep`, locking one shard at a time.
+    ///
+    /// Not a consistent cut across shards, but tracking on the other shards
+    /// is never blocked while copying. The copies are made untracked, or they
+    /// would land in the shards not yet visited.
+    pub(crate) fn cloned_matching(
+        &self,
+        keep: impl Fn(&AllocationInfo) -> bool,
+    ) -> Vec<AllocationInfo> {
+        let _untracked = UntrackedScope::enter();
+        let mut values = Vec::new();
+        for shard in self.shards.iter() {
+            let shard = match shard.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            values.extend(shard.values().filter(|a| keep(a)).cloned());
+        }
+        values
+    }
+
+    /// Remove and return every allocation
+    pub(crate) fn into_allocations(self) -> impl Iterator<Item = AllocationInfo> {
+        self.shards.into_vec().into_iter().flat_map(|shard| {
+            match shard.into_inner() {
+                Ok(map) => map,
+                Err(poisoned) => poisoned.into_inner(),
+            }
+            .into_values()
+        })
+    }
+}
+
+fn shard_index(ptr: usize, shard_count: usize) -> usize {
+    (((ptr as u64).wrapping_mul(SHARD_HASH_MULTIPLIER) >> 32) as usize) % shard_count
+}
+
+/// Every shard locked at once, presenting the shards as a single map
+pub(crate) struct AllShards<'a> {
+    guards: Vec<MutexGuard<'a, AllocationMap>>,
+    /// Dropped after the guards, so tracking resumes once the shards are free
+    _untracked: UntrackedScope,
+}
+
+impl<'a> AllShards<'a> {
+    fn shard(&self, ptr: usize) -> &AllocationMap {
+        &self.guards[shard_index(ptr, self.guards.len())]
+    }
+
+    fn shard_mut(&mut self, ptr: usize) -> &mut AllocationMap {
+        let index = shard_index(ptr, self.guards.len());
+        &mut self.guards[index]
+    }
+
+    pub(crate) fn get(&self, ptr: &usize) -> Option<&AllocationInfo> {
+        self.shard(*ptr).get(ptr)
+    }
+
+    pub(crate) fn get_mut(&mut self, ptr: &usize) -> Option<&mut AllocationInfo> {
+        self.shard_mut(*ptr).get_mut(ptr)
+    }
+
+    pub(crate) fn contains_key(&self, ptr: &usize) -> bool {
+        self.shard(*ptr).contains_key(ptr)
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        ptr: usize,
+        allocation: AllocationInfo,
+    ) -> Option<AllocationInfo> {
+        self.shard_mut(ptr).insert(ptr, allocation)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.guards.iter().map(|shard| shard.len()).sum()
+    }
+
+    /// Reserved entries across all shards
+    pub(crate) fn capacity(&self) -> usize {
+        self.guards.iter().map(|shard| shard.capacity()).sum()
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &AllocationInfo> {
+        self.guards.iter().flat_map(|shard| shard.values())
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut AllocationInfo> + use<'_, 'a> {
+        self.guards.iter_mut().flat_map(|shard| shard.values_mut())
+    }
+
+    pub(crate) fn clear(&mut self) {
+        for shard in &mut self.guards {
+            shard.clear();
+        }
+    }
+}
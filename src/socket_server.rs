@@ -0,0 +1,98 @@
+//! Live streaming of tracking events to clients of a Unix domain socket.
+
+use crate::events::{EventHook, LoggedEvent};
+use crate::types::TrackingResult;
+use std::io::Write;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+/// Event lines queued for the clients before new events are dropped
+const SOCKET_QUEUE_CAPACITY: usize = 4096;
+
+/// How long a client may stall a write before it is disconnected
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A listening socket streaming newline-delimited `LoggedEvent` JSON
+pub(crate) struct SocketServer {
+    path: PathBuf,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl SocketServer {
+    /// Bind `path` and start accepting clients.
+    ///
+    /// Returns the server and the hook that feeds it. Tracking only serializes
+    /// the event and queues it without blocking; a background thread writes
+    /// it to every client, dropping clients that disconnect or stall. Events
+    /// are dropped while the queue is full. The broadcast thread exits once
+    /// the hook is removed and dropped.
+    pub(crate) fn start(path: &Path) -> TrackingResult<(Self, EventHook)> {
+        let listener = UnixListener::bind(path)?;
+        let clients: Arc<Mutex<Vec<UnixStream>>> = Arc::default();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::sync_channel::<String>(SOCKET_QUEUE_CAPACITY);
+
+        let accept_clients = Arc::clone(&clients);
+        let accept_shutdown = Arc::clone(&shutdown);
+        std::thread::Builder::new()
+            .name("memscope-socket-accept".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if accept_shutdown.load(Ordering::Acquire) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    if stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT)).is_ok() {
+                        lock_clients(&accept_clients).push(stream);
+                    }
+                }
+            })?;
+
+        std::thread::Builder::new()
+            .name("memscope-socket-broadcast".to_string())
+            .spawn(move || {
+                for line in receiver {
+                    lock_clients(&clients)
+                        .retain_mut(|client| client.write_all(line.as_bytes()).is_ok());
+                }
+            })?;
+
+        let hook: EventHook = Arc::new(move |event| {
+            if let Ok(mut line) = serde_json::to_string(&LoggedEvent::now(event)) {
+                line.push('\n');
+                // Never block the tracked program on slow clients
+                let _ = sender.try_send(line);
+            }
+        });
+
+        let server = Self {
+            path: path.to_path_buf(),
+            shutdown,
+        };
+        Ok((server, hook))
+    }
+
+    /// Stop accepting clients and remove the socket file.
+    ///
+    /// Connected clients are closed when the broadcast thread exits, after the
+    /// server's hook has been removed.
+    pub(crate) fn stop(self) -> TrackingResult<()> {
+        self.shutdown.store(true, Ordering::Release);
+        // Wake the accept thread so it sees the shutdown flag
+        let _ = UnixStream::connect(&self.path);
+        std::fs::remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+fn lock_clients(clients: &Mutex<Vec<UnixStream>>) -> std::sync::MutexGuard<'_, Vec<UnixStream>> {
+    match clients.lock() {
+        Ok(clients) => clients,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
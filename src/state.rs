@@ -0,0 +1,204 @@
+//! Versioned on-disk checkpoints of the complete tracker state.
+
+use crate::pool::PoolState;
+use crate::recent_frees::RecentFreesState;
+use crate::shards::DEFAULT_SHARD_COUNT;
+use crate::tracker::ResourceLedger;
+use crate::types::{
+    AllocationInfo, DuplicatePolicy, ExportRoot, MemoryStats, ResourceUnit, ScopeReport,
+    TrackingError, TrackingResult,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Version of the checkpoint format written by `save_state`
+pub const STATE_FORMAT_VERSION: u32 = 3;
+
+/// Everything needed to rebuild a `MemoryTracker`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TrackerState {
+    pub(crate) format_version: u32,
+    pub(crate) active_allocations: Vec<AllocationInfo>,
+    pub(crate) history: Vec<AllocationInfo>,
+    pub(crate) stats: MemoryStats,
+    pub(crate) stack_allocations: HashMap<usize, AllocationInfo>,
+    pub(crate) stack_stats: MemoryStats,
+    pub(crate) metadata: HashMap<String, String>,
+    pub(crate) duplicate_policy: DuplicatePolicy,
+    #[serde(default)]
+    pub(crate) resources: Vec<(ResourceUnit, ResourceLedger)>,
+    pub(crate) config: TrackerConfig,
+    /// History entries evicted or never recorded because of the history limit
+    pub(crate) history_evicted: usize,
+    /// Highest bytes each type has held at once
+    pub(crate) type_peaks: HashMap<String, usize>,
+    pub(crate) dropped_allocations: u64,
+    pub(crate) dropped_deallocations: u64,
+    pub(crate) dropped_type_updates: u64,
+    /// Generation new allocations are stamped with
+    pub(crate) generation: u32,
+    /// `link_child` links, parent -> children
+    pub(crate) child_links: HashMap<usize, Vec<usize>>,
+    /// Live stacks and violation counts of the registered pools
+    pub(crate) pools: HashMap<u64, PoolState>,
+    /// Recently freed pointers and suspected double frees
+    pub(crate) recent_frees: RecentFreesState,
+    /// Snapshots taken with `record_type_sample`, oldest first
+    pub(crate) type_samples: Vec<HashMap<String, usize>>,
+    /// Reports of dropped `scope` guards, oldest first
+    pub(crate) scope_reports: Vec<ScopeReport>,
+}
+
+/// Behaviour configured on the tracker, restored with its data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TrackerConfig {
+    pub(crate) history_limit: Option<usize>,
+    pub(crate) blocking_mode: bool,
+    pub(crate) shard_count: usize,
+    pub(crate) capture_backtraces: bool,
+    pub(crate) coalesce_window_ms: Option<u64>,
+    pub(crate) budget: Option<usize>,
+    pub(crate) auto_reconcile_every: Option<usize>,
+    pub(crate) double_free_window: Option<usize>,
+}
+
+impl Default for TrackerConfig {
+    /// The configuration of `MemoryTracker::new`
+    fn default() -> Self {
+        Self {
+            history_limit: None,
+            blocking_mode: false,
+            shard_count: DEFAULT_SHARD_COUNT,
+            capture_backtraces: false,
+            coalesce_window_ms: None,
+            budget: None,
+            auto_reconcile_every: None,
+            double_free_window: None,
+        }
+    }
+}
+
+/// Only the version, read first so a mismatch is reported before parsing the rest
+#[derive(Deserialize)]
+struct StateHeader {
+    format_version: u32,
+}
+
+impl TrackerState {
+    pub(crate) fn write(&self, path: &Path) -> TrackingResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self)
+            .map_err(|e| TrackingError::SerializationError(format!("State save failed: {e}")))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub(crate) fn read(path: &Path) -> TrackingResult<Self> {
+        let contents = std::io::read_to_string(BufReader::new(File::open(path)?))?;
+        Self::parse(&contents)
+    }
+
+    pub(crate) fn parse(contents: &str) -> TrackingResult<Self> {
+        let header: StateHeader = serde_json::from_str(contents).map_err(|e| {
+            TrackingError::SerializationError(format!("Not a tracker state file: {e}"))
+        })?;
+        if header.format_version != STATE_FORMAT_VERSION {
+            return Err(TrackingError::StateVersionMismatch {
+                found: header.format_version,
+                expected: STATE_FORMAT_VERSION,
+            });
+        }
+
+        serde_json::from_str(contents)
+            .map_err(|e| TrackingError::SerializationError(format!("State load failed: {e}")))
+    }
+
+    /// Rebuild as much state as a hierarchical JSON export holds.
+    ///
+    /// The flat `allocations` array is used when the export has one. Otherwise
+    /// allocations come from the per-type entries, which carry no pointer or
+    /// untyped allocations: they get sequential placeholder pointers from 1,
+    /// and deduplicated entries are expanded to `count` allocations. Active
+    /// statistics and peaks come from the summary; cumulative byte totals are
+    /// not exported and stay zero.
+    pub(crate) fn from_export(root: ExportRoot) -> Self {
+        let allocations = match root.allocations {
+            Some(allocations) => allocations,
+            None => {
+                let mut allocations = Vec::new();
+                let entries = root
+                    .memory_hierarchy
+                    .values()
+                    .flat_map(|category| category.subcategories.values())
+                    .flat_map(|subcategory| &subcategory.types)
+                    .flat_map(|type_detail| {
+                        type_detail
+                            .allocations
+                            .iter()
+                            .map(move |entry| (type_detail, entry))
+                    });
+                for (type_detail, entry) in entries {
+                    for _ in 0..entry.count.unwrap_or(1) {
+                        let mut allocation =
+                            AllocationInfo::new(allocations.len() + 1, entry.size_bytes);
+                        allocation.seq = entry.alloc_id;
+                        allocation.timestamp_alloc = entry.allocation_time;
+                        allocation.var_name.clone_from(&entry.variable_name);
+                        allocation.type_name = entry
+                            .type_name
+                            .clone()
+                            .or_else(|| Some(type_detail.type_name.clone()));
+                        allocation.source_crate.clone_from(&entry.source_crate);
+                        allocations.push(allocation);
+                    }
+                }
+                allocations.sort_by_key(|a| a.seq);
+                allocations
+            }
+        };
+
+        // Continue from the latest exported generation
+        let allocations_generation = allocations.iter().map(|a| a.generation).max().unwrap_or(0);
+        let summary = &root.summary;
+        let stats = MemoryStats {
+            total_allocations: summary.total_allocations,
+            total_deallocations: summary
+                .total_allocations
+                .saturating_sub(summary.active_allocations),
+            active_allocations: summary.active_allocations,
+            active_memory: summary.active_memory_bytes,
+            peak_allocations: summary.active_allocations,
+            peak_memory: summary.peak_memory_bytes,
+            peak_memory_timestamp: summary.peak_memory_timestamp,
+            peak_allocations_timestamp: summary.peak_allocations_timestamp,
+            ..Default::default()
+        };
+
+        Self {
+            format_version: STATE_FORMAT_VERSION,
+            history: allocations.clone(),
+            active_allocations: allocations,
+            stats,
+            stack_allocations: HashMap::new(),
+            stack_stats: MemoryStats::default(),
+            metadata: root.metadata.custom.into_iter().collect(),
+            duplicate_policy: DuplicatePolicy::default(),
+            resources: Vec::new(),
+            config: TrackerConfig::default(),
+            history_evicted: 0,
+            type_peaks: HashMap::new(),
+            dropped_allocations: 0,
+            dropped_deallocations: 0,
+            dropped_type_updates: 0,
+            generation: allocations_generation,
+            child_links: HashMap::new(),
+            pools: HashMap::new(),
+            recent_frees: RecentFreesState::default(),
+            type_samples: Vec::new(),
+            scope_reports: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Support for replaying synthetic tracking events in tests.
+//!
+//! `EventGenerator` produces deterministic, always-valid sequences of
+//! allocations and deallocations from a seed, and `MemoryTracker::apply_events`
+//! replays them, so accounting invariants can be checked over many sequences.
+//! The `aggregate_by_type_*` functions expose both aggregation paths of
+//! `get_memory_by_type` for benchmarking.
+
+use crate::tracker::{fold_type_usage, sorted_type_usage, MemoryTracker};
+use crate::types::{TrackEvent, TrackingResult, TypeMemoryUsage};
+
+impl MemoryTracker {
+    /// Replay a sequence of tracking events in order.
+    pub fn apply_events(&self, events: &[TrackEvent]) -> TrackingResult<()> {
+        for event in events {
+            match *event {
+                TrackEvent::Alloc { ptr, size } => self.track_allocation(ptr, size)?,
+                TrackEvent::Dealloc { ptr } => self.track_deallocation(ptr)?,
+                TrackEvent::Realloc {
+                    old_ptr,
+                    new_ptr,
+                    size,
+                } => self.track_reallocation(old_ptr, new_ptr, size)?,
+                // Derived from pool tracking; there is nothing to replay
+                TrackEvent::PoolViolation { .. } => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Deterministic generator of valid allocation/deallocation sequences.
+///
+/// Deallocations only ever target live pointers, and freed addresses are
+/// reused by later allocations the way a real allocator would.
+pub struct EventGenerator {
+    state: u64,
+    next_ptr: usize,
+    live: Vec<usize>,
+    freed: Vec<usize>,
+    max_size: usize,
+}
+
+impl EventGenerator {
+    /// Create a generator from a seed. The same seed always yields the same events.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            // xorshift must never be seeded with zero
+            state: seed ^ 0x9E37_79B9_7F4A_7C15,
+            next_ptr: 0x1000,
+            live: Vec::new(),
+            freed: Vec::new(),
+            max_size: 4096,
+        }
+    }
+
+    /// Set the maximum allocation size produced (inclusive).
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Generate the next `count` events.
+    pub fn generate(&mut self, count: usize) -> Vec<TrackEvent> {
+        (0..count).map(|_| self.next_event()).collect()
+    }
+
+    /// Pointers allocated by the generated events and not yet freed.
+    pub fn live_ptrs(&self) -> &[usize] {
+        &self.live
+    }
+
+    fn next_event(&mut self) -> TrackEvent {
+        let allocate = self.live.is_empty() || self.next_u64() % 10 < 6;
+
+        if allocate {
+            let ptr = if !self.freed.is_empty() && self.next_u64() & 1 == 0 {
+                let index = (self.next_u64() as usize) % self.freed.len();
+                self.freed.swap_remove(index)
+            } else {
+                let ptr = self.next_ptr;
+                self.next_ptr += 0x10;
+                ptr
+            };
+            let size = (self.next_u64() as usize) % (self.max_size + 1);
+            self.live.push(ptr);
+            TrackEvent::Alloc { ptr, size }
+        } else {
+            let index = (self.next_u64() as usize) % self.live.len();
+            let ptr = self.live.swap_remove(index);
+            self.freed.push(ptr);
+            TrackEvent::Dealloc { ptr }
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        // xorshift64
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+}
+
+/// Group `(type_name, size)` entries by type on the current thread, as
+/// `get_memory_by_type` does below the parallel threshold.
+pub fn aggregate_by_type_serial(entries: &[(Option<String>, usize)]) -> Vec<TypeMemoryUsage> {
+    sorted_type_usage(fold_type_usage(entries))
+}
+
+/// Group `(type_name, size)` entries by type on the rayon pool, as
+/// `get_memory_by_type` does for large active sets, whatever their size.
+#[cfg(feature = "parallel")]
+pub fn aggregate_by_type_parallel(entries: &[(Option<String>, usize)]) -> Vec<TypeMemoryUsage> {
+    sorted_type_usage(crate::tracker::fold_type_usage_parallel(entries))
+}
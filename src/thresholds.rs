@@ -0,0 +1,79 @@
+//! Active-memory watermarks whose callbacks fire when they are crossed.
+
+use crate::types::MemoryStats;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Callback invoked with the statistics at the moment a threshold is crossed
+type ThresholdCallback = Arc<dyn Fn(&MemoryStats) + Send + Sync>;
+
+struct Threshold {
+    bytes: usize,
+    callback: ThresholdCallback,
+    /// Cleared when the callback fires, set again once usage drops back to `bytes`
+    armed: AtomicBool,
+}
+
+/// Registered thresholds, checked after every change to active memory
+#[derive(Default)]
+pub(crate) struct Thresholds {
+    thresholds: Mutex<Vec<Threshold>>,
+    /// Fast-path check so tracking pays nothing when no thresholds are set
+    active: AtomicBool,
+}
+
+impl Thresholds {
+    pub(crate) fn add(&self, bytes: usize, callback: Box<dyn Fn(&MemoryStats) + Send + Sync>) {
+        let mut thresholds = self.lock();
+        thresholds.push(Threshold {
+            bytes,
+            callback: Arc::from(callback),
+            armed: AtomicBool::new(true),
+        });
+        self.active.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn clear(&self) {
+        self.lock().clear();
+        self.active.store(false, Ordering::Release);
+    }
+
+    /// Whether any threshold is registered, without taking the lock
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    /// Re-arm thresholds that `active_memory` is at or below, and fire those
+    /// it is above for the first time.
+    ///
+    /// Callbacks run after the registry lock is released, so they may
+    /// allocate, track or register further thresholds.
+    pub(crate) fn check(&self, stats: &MemoryStats) {
+        let to_fire: Vec<ThresholdCallback> = {
+            let thresholds = self.lock();
+            thresholds
+                .iter()
+                .filter_map(|threshold| {
+                    if stats.active_memory <= threshold.bytes {
+                        threshold.armed.store(true, Ordering::Relaxed);
+                        None
+                    } else if threshold.armed.swap(false, Ordering::Relaxed) {
+                        Some(Arc::clone(&threshold.callback))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+        for callback in to_fire {
+            callback(stats);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Threshold>> {
+        match self.thresholds.lock() {
+            Ok(thresholds) => thresholds,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
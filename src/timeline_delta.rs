@@ -0,0 +1,99 @@
+//! Compact delta-encoded storage of the memory timeline
+
+use crate::types::{TrackingError, TrackingResult};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// A memory timeline stored as a base point followed by run-length-encoded deltas.
+///
+/// Each run is `(dt_ms, delta_bytes, repeat)`: the next `repeat` points each
+/// advance the timestamp by `dt_ms` and the live bytes by `delta_bytes`.
+/// Stable stretches of a timeline repeat the same step and collapse into a
+/// single run, while every original point can still be restored exactly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeltaTimeline {
+    /// Number of points in the decoded timeline
+    pub point_count: usize,
+    /// Timestamp of the first point (milliseconds since UNIX_EPOCH)
+    pub base_timestamp: u128,
+    /// Live bytes at the first point
+    pub base_bytes: usize,
+    /// `(dt_ms, delta_bytes, repeat)` runs producing the remaining points
+    pub runs: Vec<(u64, i64, usize)>,
+}
+
+impl DeltaTimeline {
+    /// Delta-encode `(timestamp_ms, live_bytes)` points, as returned by
+    /// `MemoryTracker::memory_timeline`
+    pub fn encode(timeline: &[(u128, usize)]) -> Self {
+        let Some(&(base_timestamp, base_bytes)) = timeline.first() else {
+            return Self {
+                point_count: 0,
+                base_timestamp: 0,
+                base_bytes: 0,
+                runs: Vec::new(),
+            };
+        };
+
+        let mut runs: Vec<(u64, i64, usize)> = Vec::new();
+        for pair in timeline.windows(2) {
+            let ((prev_time, prev_bytes), (time, bytes)) = (pair[0], pair[1]);
+            let dt = time.saturating_sub(prev_time) as u64;
+            let delta = bytes as i64 - prev_bytes as i64;
+            match runs.last_mut() {
+                Some((run_dt, run_delta, repeat)) if *run_dt == dt && *run_delta == delta => {
+                    *repeat += 1;
+                }
+                _ => runs.push((dt, delta, 1)),
+            }
+        }
+
+        Self {
+            point_count: timeline.len(),
+            base_timestamp,
+            base_bytes,
+            runs,
+        }
+    }
+
+    /// Restore the original `(timestamp_ms, live_bytes)` points
+    pub fn decode(&self) -> Vec<(u128, usize)> {
+        if self.point_count == 0 {
+            return Vec::new();
+        }
+
+        let mut points = Vec::with_capacity(self.point_count);
+        let (mut time, mut bytes) = (self.base_timestamp, self.base_bytes as i64);
+        points.push((time, bytes as usize));
+        for &(dt, delta, repeat) in &self.runs {
+            for _ in 0..repeat {
+                time += u128::from(dt);
+                bytes += delta;
+                points.push((time, bytes.max(0) as usize));
+            }
+        }
+        points
+    }
+}
+
+/// Write a delta-encoded timeline to a JSON file
+pub fn write_timeline_delta(timeline: &[(u128, usize)], path: &Path) -> TrackingResult<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, &DeltaTimeline::encode(timeline)).map_err(|e| {
+        TrackingError::SerializationError(format!("Timeline delta export failed: {e}"))
+    })?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a file written by `MemoryTracker::export_timeline_delta` back into
+/// `(timestamp_ms, live_bytes)` points
+pub fn decode_timeline_delta<P: AsRef<Path>>(path: P) -> TrackingResult<Vec<(u128, usize)>> {
+    let reader = BufReader::new(File::open(path.as_ref())?);
+    let encoded: DeltaTimeline = serde_json::from_reader(reader).map_err(|e| {
+        TrackingError::SerializationError(format!("Timeline delta read failed: {e}"))
+    })?;
+    Ok(encoded.decode())
+}
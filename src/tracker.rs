@@ -1,8 +1,64 @@
 //! Memory allocation tracking functionality.
 
-use crate::types::{AllocationInfo, MemoryStats, TrackingResult, TypeMemoryUsage};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, OnceLock};
+use crate::alloc::UntrackedScope;
+use crate::events::{
+    ActiveEventLog, EventHook, EventLogWriter, HookId, HookRegistry, ShutdownHook,
+};
+use crate::pool::PoolStacks;
+use crate::recent_frees::RecentFrees;
+use crate::report::AnalyzedReport;
+use crate::retained::ChildLinks;
+use crate::scope::ScopeGuard;
+use crate::shards::{AllShards, ShardedAllocations, DEFAULT_SHARD_COUNT};
+use crate::state::{TrackerConfig, TrackerState, STATE_FORMAT_VERSION};
+use crate::thresholds::Thresholds;
+use crate::types::{
+    AllocationBalance, AllocationInfo, Baseline, BurstEvent, CategoryNode, CategorySummary,
+    ConsistencyReport, CrateMemoryUsage, DuplicatePolicy, ExportMetadata, ExportRoot,
+    ExportSummary, ExportWarning, FragmentationReport, InfluxExportOptions, JsonExportOptions,
+    LeakReport, LeakedAllocation, LegacySvgOptions, LegacySvgStyle, MemorySnapshot, MemoryStats,
+    PingPongSite, PrometheusExportOptions, RateSample, ReportOptions, ResourceUnit, RssReport,
+    ScopeOptions, ScopeReport, SizeBucket, SizeHistogram, SizeStat, StatsDelta, SubcategoryNode,
+    SubcategorySummary, ThreadMemoryUsage, TrackEvent, TrackingError, TrackingFidelity,
+    TrackingResult, TypeAllocationEntry, TypeDetail, TypeLifetimeStats, TypeMemoryUsage,
+    TypePeakUsage, ValueMeasurement,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+/// Lifetime histogram bucket bounds used when none are given (milliseconds)
+const DEFAULT_LIFETIME_BUCKETS_MS: [u64; 6] = [1, 10, 100, 1_000, 10_000, 100_000];
+
+/// Freed allocations living at most this long count as a ping-pong cycle
+/// (milliseconds). Timestamps have millisecond resolution, so this means freed
+/// within the same or the next clock tick: an immediate free, not a short-lived buffer.
+const PING_PONG_MAX_LIFETIME_MS: u128 = 1;
+
+/// Minimum number of short-lived cycles before a site is reported as ping-pong
+const PING_PONG_MIN_CYCLES: usize = 10;
+
+/// Upper bound of the smallest default `size_histogram` bucket (bytes)
+const MIN_HISTOGRAM_BUCKET: usize = 8;
+
+/// `coalesce_window_ms` value meaning coalescing is off
+const COALESCE_DISABLED: u64 = u64::MAX;
+
+/// `budget` value meaning no budget is enforced
+const NO_BUDGET: usize = usize::MAX;
+
+/// Active bytes per type name at one `record_type_sample` call
+type TypeSample = HashMap<String, usize>;
+
+/// A type breakdown with the time it was computed
+type CachedTypeBreakdown = Option<(std::time::Instant, Vec<TypeMemoryUsage>)>;
+
+/// Counters behind the last `rate_sample` call, and the rates it returned
+type LastRateSample = Option<(Baseline, RateSample)>;
+
+/// Next allocation sequence number, shared by all trackers so `seq` orders every allocation
+static NEXT_ALLOCATION_SEQ: AtomicU64 = AtomicU64::new(1);
 
 /// Global memory tracker instance
 static GLOBAL_TRACKER: OnceLock<Arc<MemoryTracker>> = OnceLock::new();
@@ -17,239 +73,3893 @@ pub fn get_global_tracker() -> Arc<MemoryTracker> {
         .clone()
 }
 
+/// Install a global tracker built from `builder`, instead of the zero-config
+/// one `get_global_tracker` creates on first use.
+///
+/// Call it at the start of `main`, before anything calls
+/// `get_global_tracker`. Fails with `TrackingError::AlreadyInitialized` if the
+/// global tracker already exists, whether created by an earlier call to this
+/// function or by `get_global_tracker`.
+pub fn init_global_tracker(builder: MemoryTrackerBuilder) -> TrackingResult<Arc<MemoryTracker>> {
+    let mut installed = false;
+    let tracker = GLOBAL_TRACKER.get_or_init(|| {
+        installed = true;
+        Arc::new(builder.build())
+    });
+    if installed {
+        Ok(tracker.clone())
+    } else {
+        Err(TrackingError::AlreadyInitialized)
+    }
+}
+
+/// The global tracker if it has been created, without creating it.
+///
+/// Used by the tracking allocator, so that allocations made before the
+/// program initializes the global tracker do not install a default one.
+pub(crate) fn global_tracker_if_initialized() -> Option<&'static MemoryTracker> {
+    GLOBAL_TRACKER.get().map(|tracker| &**tracker)
+}
+
+/// Lock-free counters backing `MemoryStats` on the tracking fast path.
+///
+/// All counters use `Relaxed` ordering: each value is individually exact, but a
+/// snapshot taken while tracking is in progress may mix values from slightly
+/// different moments.
+#[derive(Default)]
+pub(crate) struct AtomicStats {
+    total_allocations: AtomicUsize,
+    total_deallocations: AtomicUsize,
+    total_allocated: AtomicUsize,
+    total_deallocated: AtomicUsize,
+    active_allocations: AtomicUsize,
+    active_memory: AtomicUsize,
+    peak_allocations: AtomicUsize,
+    peak_memory: AtomicUsize,
+    /// Milliseconds since the Unix epoch when each peak was reached (0 = never)
+    peak_allocations_timestamp: AtomicU64,
+    peak_memory_timestamp: AtomicU64,
+    ptr_reuse_collisions: AtomicUsize,
+    estimated_deallocations: AtomicUsize,
+    coalesced: AtomicUsize,
+    unknown_deallocations: AtomicUsize,
+}
+
+impl AtomicStats {
+    /// Record an allocation and update the peaks
+    pub(crate) fn record_allocation(&self, size: usize) {
+        self.total_allocations.fetch_add(1, Ordering::Relaxed);
+        self.total_allocated.fetch_add(size, Ordering::Relaxed);
+        let active_allocations = self.active_allocations.fetch_add(1, Ordering::Relaxed) + 1;
+        let active_memory = self
+            .active_memory
+            .fetch_add(size, Ordering::Relaxed)
+            .saturating_add(size);
+
+        // fetch_max is a compare-and-swap loop that only ever raises the peak
+        raise_peak(
+            &self.peak_allocations,
+            &self.peak_allocations_timestamp,
+            active_allocations,
+        );
+        raise_peak(
+            &self.peak_memory,
+            &self.peak_memory_timestamp,
+            active_memory,
+        );
+    }
+
+    /// Record bytes added onto an existing active allocation
+    fn record_accumulation(&self, size: usize) {
+        self.total_allocations.fetch_add(1, Ordering::Relaxed);
+        self.total_allocated.fetch_add(size, Ordering::Relaxed);
+        let active_memory = self
+            .active_memory
+            .fetch_add(size, Ordering::Relaxed)
+            .saturating_add(size);
+        raise_peak(
+            &self.peak_memory,
+            &self.peak_memory_timestamp,
+            active_memory,
+        );
+    }
+
+    /// Record an active allocation resized by a reallocation: only the byte
+    /// difference counts, as allocated or deallocated bytes
+    fn record_resize(&self, old_size: usize, new_size: usize) {
+        if new_size >= old_size {
+            let grown = new_size - old_size;
+            self.total_allocated.fetch_add(grown, Ordering::Relaxed);
+            let active_memory = self
+                .active_memory
+                .fetch_add(grown, Ordering::Relaxed)
+                .saturating_add(grown);
+            raise_peak(
+                &self.peak_memory,
+                &self.peak_memory_timestamp,
+                active_memory,
+            );
+        } else {
+            let shrunk = old_size - new_size;
+            self.total_deallocated.fetch_add(shrunk, Ordering::Relaxed);
+            saturating_sub(&self.active_memory, shrunk);
+        }
+    }
+
+    /// Record a deallocation of a previously tracked allocation
+    pub(crate) fn record_deallocation(&self, size: usize) {
+        self.total_deallocations.fetch_add(1, Ordering::Relaxed);
+        self.total_deallocated.fetch_add(size, Ordering::Relaxed);
+        saturating_sub(&self.active_allocations, 1);
+        saturating_sub(&self.active_memory, size);
+    }
+
+    /// Record the free of a synthetic entry, taking `counted_size` out of the
+    /// active counters if its estimate was folded into them
+    fn record_estimated_deallocation(&self, counted_size: Option<usize>) {
+        self.estimated_deallocations.fetch_add(1, Ordering::Relaxed);
+        if let Some(size) = counted_size {
+            saturating_sub(&self.active_allocations, 1);
+            saturating_sub(&self.active_memory, size);
+        }
+    }
+
+    /// Counters initialised from previously captured statistics
+    pub(crate) fn from_snapshot(stats: &MemoryStats) -> Self {
+        Self {
+            total_allocations: AtomicUsize::new(stats.total_allocations),
+            total_deallocations: AtomicUsize::new(stats.total_deallocations),
+            total_allocated: AtomicUsize::new(stats.total_allocated),
+            total_deallocated: AtomicUsize::new(stats.total_deallocated),
+            active_allocations: AtomicUsize::new(stats.active_allocations),
+            active_memory: AtomicUsize::new(stats.active_memory),
+            peak_allocations: AtomicUsize::new(stats.peak_allocations),
+            peak_memory: AtomicUsize::new(stats.peak_memory),
+            peak_allocations_timestamp: AtomicU64::new(timestamp_to_atomic(
+                stats.peak_allocations_timestamp,
+            )),
+            peak_memory_timestamp: AtomicU64::new(timestamp_to_atomic(stats.peak_memory_timestamp)),
+            ptr_reuse_collisions: AtomicUsize::new(stats.ptr_reuse_collisions),
+            estimated_deallocations: AtomicUsize::new(stats.estimated_deallocations),
+            coalesced: AtomicUsize::new(stats.coalesced),
+            unknown_deallocations: AtomicUsize::new(stats.unknown_deallocations),
+        }
+    }
+
+    /// Overwrite every counter with previously captured statistics
+    fn restore(&self, stats: &MemoryStats) {
+        let counters = [
+            (&self.total_allocations, stats.total_allocations),
+            (&self.total_deallocations, stats.total_deallocations),
+            (&self.total_allocated, stats.total_allocated),
+            (&self.total_deallocated, stats.total_deallocated),
+            (&self.active_allocations, stats.active_allocations),
+            (&self.active_memory, stats.active_memory),
+            (&self.peak_allocations, stats.peak_allocations),
+            (&self.peak_memory, stats.peak_memory),
+            (&self.ptr_reuse_collisions, stats.ptr_reuse_collisions),
+            (&self.estimated_deallocations, stats.estimated_deallocations),
+            (&self.coalesced, stats.coalesced),
+            (&self.unknown_deallocations, stats.unknown_deallocations),
+        ];
+        for (counter, value) in counters {
+            counter.store(value, Ordering::Relaxed);
+        }
+        self.peak_allocations_timestamp.store(
+            timestamp_to_atomic(stats.peak_allocations_timestamp),
+            Ordering::Relaxed,
+        );
+        self.peak_memory_timestamp.store(
+            timestamp_to_atomic(stats.peak_memory_timestamp),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Overwrite the active counters with exact values, raising the peaks if needed
+    fn reset_active(&self, active_allocations: usize, active_memory: usize) {
+        self.active_allocations
+            .store(active_allocations, Ordering::Relaxed);
+        self.active_memory.store(active_memory, Ordering::Relaxed);
+        raise_peak(
+            &self.peak_allocations,
+            &self.peak_allocations_timestamp,
+            active_allocations,
+        );
+        raise_peak(
+            &self.peak_memory,
+            &self.peak_memory_timestamp,
+            active_memory,
+        );
+    }
+
+    /// Read the counters into a `MemoryStats` value
+    pub(crate) fn snapshot(&self) -> MemoryStats {
+        MemoryStats {
+            total_allocations: self.total_allocations.load(Ordering::Relaxed),
+            total_deallocations: self.total_deallocations.load(Ordering::Relaxed),
+            total_allocated: self.total_allocated.load(Ordering::Relaxed),
+            total_deallocated: self.total_deallocated.load(Ordering::Relaxed),
+            active_allocations: self.active_allocations.load(Ordering::Relaxed),
+            active_memory: self.active_memory.load(Ordering::Relaxed),
+            peak_allocations: self.peak_allocations.load(Ordering::Relaxed),
+            peak_memory: self.peak_memory.load(Ordering::Relaxed),
+            ptr_reuse_collisions: self.ptr_reuse_collisions.load(Ordering::Relaxed),
+            estimated_deallocations: self.estimated_deallocations.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            unknown_deallocations: self.unknown_deallocations.load(Ordering::Relaxed),
+            peak_memory_timestamp: timestamp_from_atomic(&self.peak_memory_timestamp),
+            peak_allocations_timestamp: timestamp_from_atomic(&self.peak_allocations_timestamp),
+            ..Default::default()
+        }
+    }
+}
+
+/// Subtract from an atomic counter without wrapping below zero
+fn saturating_sub(counter: &AtomicUsize, amount: usize) {
+    let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+        Some(current.saturating_sub(amount))
+    });
+}
+
+/// Current time in milliseconds since the Unix epoch, the clock of `timestamp_alloc`
+fn unix_time_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Raise `peak` to `value`, stamping `timestamp` with the current time when it rises
+fn raise_peak(peak: &AtomicUsize, timestamp: &AtomicU64, value: usize) {
+    if peak.fetch_max(value, Ordering::Relaxed) < value {
+        timestamp.store(unix_time_ms(), Ordering::Relaxed);
+    }
+}
+
+/// Atomic form of a peak timestamp, where 0 means no peak yet
+fn timestamp_to_atomic(timestamp: Option<u128>) -> u64 {
+    timestamp.map_or(0, |t| u64::try_from(t).unwrap_or(u64::MAX))
+}
+
+fn timestamp_from_atomic(timestamp: &AtomicU64) -> Option<u128> {
+    match timestamp.load(Ordering::Relaxed) {
+        0 => None,
+        t => Some(u128::from(t)),
+    }
+}
+
+/// Allocation history, including freed allocations with their deallocation time.
+#[derive(Default)]
+struct AllocationHistory {
+    /// Recorded allocations, in allocation order, oldest evicted first
+    entries: VecDeque<AllocationInfo>,
+    /// ptr -> absolute index (`evicted` + position in `entries`) for
+    /// allocations that have not been freed yet
+    live_index: HashMap<usize, usize>,
+    /// Most entries kept before the oldest are evicted (`None` = unbounded)
+    capacity: Option<usize>,
+    /// Entries evicted or never recorded because of `capacity`
+    evicted: usize,
+}
+
+impl AllocationHistory {
+    /// Rebuild a history, including the index of still-live entries
+    fn from_entries(entries: Vec<AllocationInfo>, capacity: Option<usize>, evicted: usize) -> Self {
+        let live_index = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.is_active())
+            .map(|(index, entry)| (entry.ptr, evicted + index))
+            .collect();
+        Self {
+            entries: entries.into(),
+            live_index,
+            capacity,
+            evicted,
+        }
+    }
+
+    /// Append a new allocation, evicting the oldest entries beyond `capacity`
+    fn record(&mut self, allocation: AllocationInfo) {
+        if self.capacity == Some(0) {
+            self.evicted += 1;
+            return;
+        }
+        self.live_index
+            .insert(allocation.ptr, self.evicted + self.entries.len());
+        self.entries.push_back(allocation);
+        self.evict_over_capacity();
+    }
+
+    fn evict_over_capacity(&mut self) {
+        let Some(capacity) = self.capacity else {
+            return;
+        };
+        while self.entries.len() > capacity {
+            if let Some(oldest) = self.entries.pop_front() {
+                if self.live_index.get(&oldest.ptr) == Some(&self.evicted) {
+                    self.live_index.remove(&oldest.ptr);
+                }
+            }
+            self.evicted += 1;
+        }
+    }
+
+    /// Copy the final state of a freed allocation onto its history entry
+    fn record_deallocation(&mut self, freed: &AllocationInfo) {
+        if let Some(entry) = self.live_entry(freed.ptr) {
+            entry.timestamp_dealloc = freed.timestamp_dealloc;
+            entry.var_name.clone_from(&freed.var_name);
+            entry.type_name.clone_from(&freed.type_name);
+            entry.source_crate.clone_from(&freed.source_crate);
+        }
+        self.live_index.remove(&freed.ptr);
+    }
+
+    /// Move the entry of a live allocation to its address after a reallocation
+    fn relocate(&mut self, old_ptr: usize, moved: &AllocationInfo) {
+        let Some(index) = self.live_index.remove(&old_ptr) else {
+            return;
+        };
+        let Some(entry) = index
+            .checked_sub(self.evicted)
+            .and_then(|position| self.entries.get_mut(position))
+            .filter(|entry| entry.ptr == old_ptr && entry.is_active())
+        else {
+            return;
+        };
+        entry.ptr = moved.ptr;
+        entry.size = moved.size;
+        entry.growth_events = moved.growth_events;
+        entry.peak_size = moved.peak_size;
+        entry.efficiency_score = moved.efficiency_score;
+        self.live_index.insert(moved.ptr, index);
+    }
+
+    /// Update the variable association of a live allocation
+    fn associate(&mut self, ptr: usize, var_name: &str, type_name: &str) {
+        if let Some(entry) = self.live_entry(ptr) {
+            entry.var_name = Some(var_name.to_string());
+            entry.type_name = Some(type_name.to_string());
+        }
+    }
+
+    /// Update the crate attribution of a live allocation
+    fn set_source_crate(&mut self, ptr: usize, source_crate: &str) {
+        if let Some(entry) = self.live_entry(ptr) {
+            entry.source_crate = Some(source_crate.to_string());
+        }
+    }
+
+    /// Remove the entry of a live allocation entirely, as if it was never recorded.
+    ///
+    /// Entries after it shift down by one; only their live indexes need fixing,
+    /// and a coalesced entry is recent, so few entries follow it.
+    fn coalesce(&mut self, ptr: usize) -> bool {
+        if self.live_entry(ptr).is_none() {
+            return false;
+        }
+        let index = self.live_index[&ptr];
+        self.live_index.remove(&ptr);
+        self.entries.remove(index - self.evicted);
+        for (offset, entry) in self.entries.range(index - self.evicted..).enumerate() {
+            if let Some(live) = self.live_index.get_mut(&entry.ptr) {
+                if *live == index + offset + 1 {
+                    *live = index + offset;
+                }
+            }
+        }
+        true
+    }
+
+    fn live_entry(&mut self, ptr: usize) -> Option<&mut AllocationInfo> {
+        let index = *self.live_index.get(&ptr)?;
+        self.entries
+            .get_mut(index.checked_sub(self.evicted)?)
+            // Guard against a stale index left behind by a skipped history update
+            .filter(|entry| entry.ptr == ptr && entry.is_active())
+    }
+}
+
+/// Active allocations and statistics for one non-byte resource unit
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct ResourceLedger {
+    allocations: HashMap<usize, AllocationInfo>,
+    stats: MemoryStats,
+}
+
+impl ResourceLedger {
+    fn record_allocation(&mut self, allocation: AllocationInfo) {
+        let amount = allocation.size;
+        let stats = &mut self.stats;
+        if let Some(previous) = self.allocations.insert(allocation.ptr, allocation) {
+            // Re-tracking the same id replaces the previous record
+            stats.active_allocations = stats.active_allocations.saturating_sub(1);
+            stats.active_memory = stats.active_memory.saturating_sub(previous.size);
+        }
+
+        stats.total_allocations = stats.total_allocations.saturating_add(1);
+        stats.total_allocated = stats.total_allocated.saturating_add(amount);
+        stats.active_allocations = stats.active_allocations.saturating_add(1);
+        stats.active_memory = stats.active_memory.saturating_add(amount);
+        stats.peak_allocations = stats.peak_allocations.max(stats.active_allocations);
+        stats.peak_memory = stats.peak_memory.max(stats.active_memory);
+    }
+
+    fn record_deallocation(&mut self, id: usize) {
+        if let Some(allocation) = self.allocations.remove(&id) {
+            let stats = &mut self.stats;
+            stats.total_deallocations = stats.total_deallocations.saturating_add(1);
+            stats.total_deallocated = stats.total_deallocated.saturating_add(allocation.size);
+            stats.active_allocations = stats.active_allocations.saturating_sub(1);
+            stats.active_memory = stats.active_memory.saturating_sub(allocation.size);
+        }
+    }
+}
+
+/// Running active bytes per type name, with the highest value each reached
+#[derive(Debug, Default)]
+struct TypeTotals {
+    /// type name -> (current bytes, peak bytes)
+    totals: HashMap<String, (usize, usize)>,
+}
+
+impl TypeTotals {
+    /// Totals of the typed allocations in an active map, each at its peak
+    fn from_active(active: &AllShards) -> Self {
+        let mut totals = Self::default();
+        for allocation in active.values() {
+            if let Some(type_name) = &allocation.type_name {
+                totals.add(type_name, allocation.size);
+            }
+        }
+        totals
+    }
+
+    fn add(&mut self, type_name: &str, size: usize) {
+        let (current, peak) = match self.totals.get_mut(type_name) {
+            Some(entry) => entry,
+            None => self.totals.entry(type_name.to_string()).or_default(),
+        };
+        *current = current.saturating_add(size);
+        *peak = (*peak).max(*current);
+    }
+
+    fn remove(&mut self, type_name: &str, size: usize) {
+        if let Some((current, _)) = self.totals.get_mut(type_name) {
+            *current = current.saturating_sub(size);
+        }
+    }
+}
+
 /// Core memory tracking functionality.
 ///
 /// The MemoryTracker maintains records of all memory allocations and deallocations,
 /// provides statistics, and supports exporting data in various formats.
 pub struct MemoryTracker {
     /// Active allocations (ptr -> allocation info)
-    active_allocations: Mutex<HashMap<usize, AllocationInfo>>,
+    active_allocations: ShardedAllocations,
     /// Complete allocation history (for analysis)
-    allocation_history: Mutex<Vec<AllocationInfo>>,
-    /// Memory usage statistics
-    stats: Mutex<MemoryStats>,
+    allocation_history: Mutex<AllocationHistory>,
+    /// Memory usage statistics, updated without taking any lock
+    stats: AtomicStats,
+    /// Explicitly flagged stack allocations (id -> allocation info), kept apart from the heap
+    stack_allocations: Mutex<HashMap<usize, AllocationInfo>>,
+    /// Statistics for flagged stack allocations
+    stack_stats: Mutex<MemoryStats>,
+    /// User-supplied key-value metadata emitted with every export
+    metadata: Mutex<HashMap<String, String>>,
+    /// Policy for allocations tracked at an already-active pointer
+    duplicate_policy: Mutex<DuplicatePolicy>,
+    /// Allocations counted in units other than heap bytes, kept apart per unit
+    resources: Mutex<HashMap<ResourceUnit, ResourceLedger>>,
+    /// Whether a warmup window is open; allocations made meanwhile are never leaks
+    warmup: AtomicBool,
+    /// Whether `track_allocation` captures a backtrace for each allocation
+    capture_backtraces: AtomicBool,
+    /// Whether tracking calls wait for busy locks instead of dropping the event
+    blocking_mode: AtomicBool,
+    /// Generation stamped on new allocations, bumped by `advance_generation`
+    generation: AtomicU32,
+    /// Parent/child ownership links used for retained-size queries
+    child_links: ChildLinks,
+    /// Per-pool allocation stacks for `track_pool_allocation`
+    pools: PoolStacks,
+    /// Recently freed pointers, for `suspected_double_frees`
+    recent_frees: RecentFrees,
+    /// Active-memory watermarks registered with `set_threshold`
+    thresholds: Thresholds,
+    /// Active-memory budget in bytes enforced by `set_budget` (`NO_BUDGET` = none)
+    budget: AtomicUsize,
+    /// Reports of dropped `scope` guards, oldest first
+    scope_reports: Mutex<Vec<ScopeReport>>,
+    /// Previous `rate_sample` call, the starting point of the next one
+    last_rate_sample: Mutex<LastRateSample>,
+    /// Reconcile statistics automatically every this many tracking operations (0 = never)
+    auto_reconcile_every: AtomicUsize,
+    /// Tracking operations since the last automatic reconciliation
+    ops_since_reconcile: AtomicUsize,
+    /// Allocations skipped because their active map shard was busy
+    dropped_allocations: AtomicU64,
+    /// Deallocations skipped because their active map shard was busy
+    dropped_deallocations: AtomicU64,
+    /// Per-type total updates skipped because the totals were busy
+    dropped_type_updates: AtomicU64,
+    /// Freed allocations that lived at most this long are dropped from history
+    /// (`COALESCE_DISABLED` = never)
+    coalesce_window_ms: AtomicU64,
+    /// Callbacks invoked for every tracked allocation and deallocation
+    event_hooks: HookRegistry,
+    /// JSONL event log started with `start_event_log`, if any
+    event_log: Mutex<Option<ActiveEventLog>>,
+    /// Unix socket server started with `start_socket_server`, and its hook
+    #[cfg(all(unix, feature = "socket-server"))]
+    socket_server: Mutex<Option<(HookId, crate::socket_server::SocketServer)>>,
+    /// Finalizers run once by `shutdown` or when the tracker is dropped
+    shutdown_hooks: Mutex<Vec<ShutdownHook>>,
+    /// Per-type running totals and peaks; only locked while holding an `active_allocations` shard
+    type_totals: Mutex<TypeTotals>,
+    /// Per-type active bytes captured by `record_type_sample`, oldest first
+    type_samples: Mutex<Vec<TypeSample>>,
+    /// Last `get_memory_by_type_cached` result and when it was computed
+    type_breakdown_cache: Mutex<CachedTypeBreakdown>,
+}
+
+/// Configures a `MemoryTracker` in one place, builder style.
+///
+/// Every option defaults to what `MemoryTracker::new` uses, so
+/// `MemoryTrackerBuilder::new().build()` is equivalent to `new()`.
+#[derive(Debug, Clone)]
+pub struct MemoryTrackerBuilder {
+    history_limit: Option<usize>,
+    blocking_mode: bool,
+    capture_backtraces: bool,
+    shard_count: usize,
+    budget: Option<usize>,
+}
+
+impl MemoryTrackerBuilder {
+    /// Start from the default configuration.
+    pub fn new() -> Self {
+        Self {
+            history_limit: None,
+            blocking_mode: false,
+            capture_backtraces: false,
+            shard_count: DEFAULT_SHARD_COUNT,
+            budget: None,
+        }
+    }
+
+    /// Keep at most `limit` allocations in the history; see
+    /// `MemoryTracker::with_history_limit`.
+    pub fn history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = Some(limit);
+        self
+    }
+
+    /// Wait for busy locks instead of dropping events; see
+    /// `MemoryTracker::with_blocking_mode`.
+    pub fn blocking_mode(mut self, enabled: bool) -> Self {
+        self.blocking_mode = enabled;
+        self
+    }
+
+    /// Capture a backtrace for every tracked allocation; see
+    /// `MemoryTracker::with_backtraces`.
+    pub fn capture_backtraces(mut self, enabled: bool) -> Self {
+        self.capture_backtraces = enabled;
+        self
+    }
+
+    /// Split the active allocation map into `shards` shards; see
+    /// `MemoryTracker::with_shard_count`.
+    pub fn shard_count(mut self, shards: usize) -> Self {
+        self.shard_count = shards;
+        self
+    }
+
+    /// Report allocations that take active memory past `budget` bytes; see
+    /// `MemoryTracker::set_budget`.
+    pub fn budget(mut self, budget: Option<usize>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Create a tracker with this configuration.
+    pub fn build(self) -> MemoryTracker {
+        let tracker = match self.history_limit {
+            Some(limit) => MemoryTracker::with_history_limit(limit),
+            None => MemoryTracker::new(),
+        }
+        .with_shard_count(self.shard_count)
+        .with_blocking_mode(self.blocking_mode)
+        .with_backtraces(self.capture_backtraces);
+        tracker.set_budget(self.budget);
+        tracker
+    }
+}
+
+impl Default for MemoryTrackerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MemoryTracker {
     /// Create a new memory tracker.
     pub fn new() -> Self {
         Self {
-            active_allocations: Mutex::new(HashMap::new()),
-            allocation_history: Mutex::new(Vec::new()),
-            stats: Mutex::new(MemoryStats::default()),
+            active_allocations: ShardedAllocations::new(DEFAULT_SHARD_COUNT),
+            allocation_history: Mutex::new(AllocationHistory::default()),
+            stats: AtomicStats::default(),
+            stack_allocations: Mutex::new(HashMap::new()),
+            stack_stats: Mutex::new(MemoryStats::default()),
+            metadata: Mutex::new(HashMap::new()),
+            duplicate_policy: Mutex::new(DuplicatePolicy::default()),
+            resources: Mutex::new(HashMap::new()),
+            warmup: AtomicBool::new(false),
+            capture_backtraces: AtomicBool::new(false),
+            blocking_mode: AtomicBool::new(false),
+            generation: AtomicU32::new(0),
+            child_links: ChildLinks::default(),
+            pools: PoolStacks::default(),
+            recent_frees: RecentFrees::default(),
+            thresholds: Thresholds::default(),
+            budget: AtomicUsize::new(NO_BUDGET),
+            scope_reports: Mutex::new(Vec::new()),
+            last_rate_sample: Mutex::new(None),
+            auto_reconcile_every: AtomicUsize::new(0),
+            ops_since_reconcile: AtomicUsize::new(0),
+            dropped_allocations: AtomicU64::new(0),
+            dropped_deallocations: AtomicU64::new(0),
+            dropped_type_updates: AtomicU64::new(0),
+            coalesce_window_ms: AtomicU64::new(COALESCE_DISABLED),
+            event_hooks: HookRegistry::default(),
+            event_log: Mutex::new(None),
+            #[cfg(all(unix, feature = "socket-server"))]
+            socket_server: Mutex::new(None),
+            shutdown_hooks: Mutex::new(Vec::new()),
+            type_totals: Mutex::new(TypeTotals::default()),
+            type_samples: Mutex::new(Vec::new()),
+            type_breakdown_cache: Mutex::new(None),
+        }
+    }
+
+    /// Start configuring a tracker; see `MemoryTrackerBuilder`.
+    pub fn builder() -> MemoryTrackerBuilder {
+        MemoryTrackerBuilder::new()
+    }
+
+    /// Set how allocations tracked at an already-active pointer are handled.
+    ///
+    /// Every such collision is counted in `MemoryStats::ptr_reuse_collisions`,
+    /// whatever the policy.
+    pub fn set_duplicate_policy(&self, policy: DuplicatePolicy) {
+        match self.duplicate_policy.lock() {
+            Ok(mut current) => *current = policy,
+            Err(poisoned) => *poisoned.into_inner() = policy,
+        }
+    }
+
+    /// Get the current duplicate-pointer policy.
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        match self.duplicate_policy.lock() {
+            Ok(policy) => *policy,
+            Err(poisoned) => *poisoned.into_inner(),
         }
     }
 
     /// Track a new memory allocation.
+    ///
+    /// With the `tracing` feature, the allocation is tagged with the id of the
+    /// current span, and with backtraces enabled (see `with_backtraces`) the
+    /// call stack is captured. The tracking allocator does neither: it can run
+    /// while the subscriber or the backtrace machinery is itself allocating.
     pub fn track_allocation(&self, ptr: usize, size: usize) -> TrackingResult<()> {
         // Create allocation info first (no locks needed)
-        let allocation = AllocationInfo::new(ptr, size);
+        let mut allocation = AllocationInfo::new(ptr, size);
+        if self.capture_backtraces.load(Ordering::Relaxed) {
+            allocation.backtrace = Some(crate::callsite::capture_frames());
+        }
+        #[cfg(feature = "tracing")]
+        {
+            allocation.span_id = current_span_id();
+        }
+        self.record_allocation(allocation)
+    }
 
-        // Use try_lock to avoid blocking during high allocation activity
-        match (self.active_allocations.try_lock(), self.stats.try_lock()) {
-            (Ok(mut active), Ok(mut stats)) => {
-                // Add to active allocations
-                active.insert(ptr, allocation.clone());
+    /// Create a tracker whose history keeps at most `limit` allocations.
+    ///
+    /// Once full, recording an allocation evicts the oldest history entry,
+    /// live or freed. A limit of 0 records no history at all. Statistics and
+    /// the active allocations are unaffected, but queries built on the history
+    /// (such as `survival_by_generation` or `export_sql_dump`) only see what
+    /// it still holds; see `history_evicted_count`.
+    pub fn with_history_limit(limit: usize) -> Self {
+        let mut tracker = Self::new();
+        match tracker.allocation_history.get_mut() {
+            Ok(history) => history.capacity = Some(limit),
+            Err(poisoned) => poisoned.into_inner().capacity = Some(limit),
+        }
+        tracker
+    }
+
+    /// Maximum number of history entries kept, or `None` when unbounded.
+    pub fn history_capacity(&self) -> Option<usize> {
+        match self.allocation_history.lock() {
+            Ok(history) => history.capacity,
+            Err(poisoned) => poisoned.into_inner().capacity,
+        }
+    }
+
+    /// Number of allocations evicted from (or never recorded in) the history
+    /// because of the limit set with `with_history_limit`.
+    pub fn history_evicted_count(&self) -> usize {
+        match self.allocation_history.lock() {
+            Ok(history) => history.evicted,
+            Err(poisoned) => poisoned.into_inner().evicted,
+        }
+    }
+
+    /// Split the active allocation map into `shards` independently locked
+    /// shards (at least one), builder style; the default is 16.
+    ///
+    /// Tracking only locks the shard of the pointer involved, so threads
+    /// working on different pointers rarely contend and fewer events are
+    /// dropped under load. Whole-map queries lock every shard. Already tracked
+    /// allocations are redistributed.
+    pub fn with_shard_count(mut self, shards: usize) -> Self {
+        let current = std::mem::replace(
+            &mut self.active_allocations,
+            ShardedAllocations::new(shards),
+        );
+        self.active_allocations =
+            ShardedAllocations::from_allocations(current.into_allocations(), shards);
+        self
+    }
+
+    /// Number of shards the active allocation map is split into.
+    pub fn shard_count(&self) -> usize {
+        self.active_allocations.shard_count()
+    }
+
+    /// Enable or disable blocking mode, builder style.
+    ///
+    /// By default tracking calls (`track_allocation`, `track_deallocation`,
+    /// `associate_var` and their variants) never wait: when a lock is busy the
+    /// event is dropped and counted in `tracking_fidelity`. In blocking mode
+    /// they wait for the lock instead, so every event is recorded and the
+    /// statistics are exact; meant for tests that assert on counts.
+    ///
+    /// Do not enable it on a tracker fed from a custom global allocator: an
+    /// allocation made while the tracker holds one of its locks re-enters the
+    /// tracker on the same thread and deadlocks waiting for that lock.
+    pub fn with_blocking_mode(self, enabled: bool) -> Self {
+        self.blocking_mode.store(enabled, Ordering::Relaxed);
+        self
+    }
 
-                // Update statistics with overflow protection
-                stats.total_allocations = stats.total_allocations.saturating_add(1);
-                stats.total_allocated = stats.total_allocated.saturating_add(size);
-                stats.active_allocations = stats.active_allocations.saturating_add(1);
-                stats.active_memory = stats.active_memory.saturating_add(size);
+    /// Whether tracking calls wait for busy locks; see `with_blocking_mode`.
+    pub fn is_blocking_mode(&self) -> bool {
+        self.blocking_mode.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable backtrace capture in `track_allocation`, builder style.
+    ///
+    /// Capturing and resolving a backtrace costs far more than tracking
+    /// itself, so it is off by default; use it to find where unexpected
+    /// allocations come from (see `get_allocations_by_callsite`).
+    pub fn with_backtraces(self, enabled: bool) -> Self {
+        self.set_backtraces(enabled);
+        self
+    }
+
+    /// Enable or disable backtrace capture on an existing tracker, such as the
+    /// global one; see `with_backtraces`.
+    pub fn set_backtraces(&self, enabled: bool) {
+        self.capture_backtraces.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Track a new memory allocation made at `timestamp` (milliseconds since
+    /// UNIX_EPOCH) instead of now.
+    ///
+    /// For importing or replaying recorded events, and for testing
+    /// time-dependent analysis deterministically. `track_allocation` is the
+    /// same call stamped with the current time.
+    pub fn track_allocation_at(
+        &self,
+        ptr: usize,
+        size: usize,
+        timestamp: u128,
+    ) -> TrackingResult<()> {
+        let mut allocation = AllocationInfo::new(ptr, size);
+        allocation.timestamp_alloc = timestamp;
+        self.record_allocation(allocation)
+    }
+
+    /// Track a new memory allocation made with a known alignment.
+    ///
+    /// The tracking allocator records every allocation with its alignment,
+    /// which feeds `alignment_waste_report`.
+    pub fn track_aligned_allocation(
+        &self,
+        ptr: usize,
+        size: usize,
+        align: usize,
+    ) -> TrackingResult<()> {
+        self.track_allocator_allocation(ptr, size, align, None)
+    }
+
+    /// Track a new memory allocation for which the allocator reserved
+    /// `usable` bytes to satisfy a request of `requested` bytes.
+    ///
+    /// The gap feeds `overallocation_report`. A `usable` size below
+    /// `requested` is treated as equal to it.
+    pub fn track_allocation_with_usable(
+        &self,
+        ptr: usize,
+        requested: usize,
+        usable: usize,
+    ) -> TrackingResult<()> {
+        let mut allocation = AllocationInfo::new(ptr, requested);
+        allocation.usable_size = Some(usable.max(requested));
+        self.record_allocation(allocation)
+    }
+
+    /// Track an allocation made by the tracking allocator
+    pub(crate) fn track_allocator_allocation(
+        &self,
+        ptr: usize,
+        size: usize,
+        align: usize,
+        usable_size: Option<usize>,
+    ) -> TrackingResult<()> {
+        let mut allocation = AllocationInfo::new(ptr, size);
+        allocation.align = Some(align);
+        allocation.usable_size = usable_size.map(|usable| usable.max(size));
+        self.record_allocation(allocation)
+    }
+
+    /// Track a reallocation made by the tracking allocator
+    pub(crate) fn track_allocator_reallocation(
+        &self,
+        old_ptr: usize,
+        new_ptr: usize,
+        new_size: usize,
+        align: usize,
+        usable_size: Option<usize>,
+    ) -> TrackingResult<()> {
+        self.record_reallocation(old_ptr, new_ptr, new_size, usable_size, || {
+            self.track_allocator_allocation(new_ptr, new_size, align, usable_size)
+        })
+    }
+
+    /// Track an allocation and attribute it to the workspace crate that made it.
+    ///
+    /// Usually called through the `track_crate!` macro, which captures the
+    /// caller's `CARGO_PKG_NAME`. If the pointer is already tracked (e.g. by the
+    /// tracking allocator) only the attribution is added.
+    pub fn track_crate_allocation(
+        &self,
+        ptr: usize,
+        size: usize,
+        source_crate: &str,
+    ) -> TrackingResult<()> {
+        if let Some(mut active) = self.tracking_lock(self.active_allocations.shard(ptr)) {
+            if let Some(existing) = active.get_mut(&ptr) {
+                existing.source_crate = Some(source_crate.to_string());
+                drop(active);
+
+                // Keep the history entry in sync (optional, skip if busy)
+                if let Some(mut history) = self.tracking_lock(&self.allocation_history) {
+                    history.set_source_crate(ptr, source_crate);
+                }
+                return Ok(());
+            }
+        }
+
+        let mut allocation = AllocationInfo::new(ptr, size);
+        allocation.source_crate = Some(source_crate.to_string());
+        self.record_allocation(allocation)
+    }
+
+    fn record_allocation(&self, mut allocation: AllocationInfo) -> TrackingResult<()> {
+        let (ptr, size) = (allocation.ptr, allocation.size);
+        allocation.seq = NEXT_ALLOCATION_SEQ.fetch_add(1, Ordering::Relaxed);
+        allocation.warmup = self.warmup.load(Ordering::Relaxed);
+        allocation.generation = self.generation.load(Ordering::Relaxed);
+        let budget_result = self.check_budget(size);
 
-                // Update peaks
-                if stats.active_allocations > stats.peak_allocations {
-                    stats.peak_allocations = stats.active_allocations;
+        // Unless in blocking mode, don't wait during high allocation activity
+        match self.tracking_lock(self.active_allocations.shard(ptr)) {
+            Some(mut active) => {
+                if let Some(existing) = active.get_mut(&ptr) {
+                    self.stats
+                        .ptr_reuse_collisions
+                        .fetch_add(1, Ordering::Relaxed);
+
+                    match self.duplicate_policy() {
+                        DuplicatePolicy::Overwrite => {
+                            if let Some(type_name) = &existing.type_name {
+                                self.update_type_totals(|totals| {
+                                    totals.remove(type_name, existing.size)
+                                });
+                            }
+                        }
+                        DuplicatePolicy::Reject => {
+                            return Err(TrackingError::DuplicatePointer { ptr });
+                        }
+                        DuplicatePolicy::Sum => {
+                            existing.size = existing.size.saturating_add(size);
+                            if let Some(type_name) = &existing.type_name {
+                                self.update_type_totals(|totals| totals.add(type_name, size));
+                            }
+                            drop(active);
+                            self.stats.record_accumulation(size);
+                            self.check_thresholds();
+                            return budget_result;
+                        }
+                    }
                 }
-                if stats.active_memory > stats.peak_memory {
-                    stats.peak_memory = stats.active_memory;
+
+                // Add to active allocations
+                if let Some(type_name) = &allocation.type_name {
+                    self.update_type_totals(|totals| totals.add(type_name, size));
                 }
+                active.insert(ptr, allocation.clone());
 
-                // Release locks before adding to history
-                drop(stats);
+                // Release the lock before updating statistics and history
                 drop(active);
 
-                // Add to history with separate try_lock (optional, skip if busy)
-                if let Ok(mut history) = self.allocation_history.try_lock() {
-                    history.push(allocation);
+                // Statistics are atomic and never contend with the active map
+                self.stats.record_allocation(size);
+                self.recent_frees.record_reuse(ptr);
+                crate::scope::record_allocation(self.scope_key(), ptr, allocation.seq, size);
+
+                // Add to history with a separate lock (optional, skip if busy)
+                if let Some(mut history) = self.tracking_lock(&self.allocation_history) {
+                    history.record(allocation);
                 }
 
-                Ok(())
+                self.check_thresholds();
+                self.event_hooks.emit(&TrackEvent::Alloc { ptr, size });
+                self.maybe_auto_reconcile();
+                budget_result
             }
-            _ => {
-                // If we can't get locks immediately, skip tracking to avoid deadlock
+            None => {
+                // If we can't get the lock immediately, skip tracking to avoid deadlock
                 // This is acceptable as we prioritize program stability over complete tracking
-                Ok(())
+                self.dropped_allocations.fetch_add(1, Ordering::Relaxed);
+                budget_result
             }
         }
     }
 
-    /// Track a memory deallocation.
-    pub fn track_deallocation(&self, ptr: usize) -> TrackingResult<()> {
-        // Use try_lock to avoid blocking during high deallocation activity
-        match (self.active_allocations.try_lock(), self.stats.try_lock()) {
-            (Ok(mut active), Ok(mut stats)) => {
-                if let Some(allocation) = active.remove(&ptr) {
-                    // Update statistics with overflow protection
-                    stats.total_deallocations = stats.total_deallocations.saturating_add(1);
-                    stats.total_deallocated =
-                        stats.total_deallocated.saturating_add(allocation.size);
-                    stats.active_allocations = stats.active_allocations.saturating_sub(1);
-                    stats.active_memory = stats.active_memory.saturating_sub(allocation.size);
-                }
-                Ok(())
-            }
-            _ => {
-                // If we can't get locks immediately, skip tracking to avoid deadlock
-                Ok(())
+    /// `BudgetExceeded` if adding `size` bytes would take active memory past
+    /// the budget
+    fn check_budget(&self, size: usize) -> TrackingResult<()> {
+        let limit = self.budget.load(Ordering::Relaxed);
+        if limit == NO_BUDGET {
+            return Ok(());
+        }
+        let attempted = self
+            .stats
+            .active_memory
+            .load(Ordering::Relaxed)
+            .saturating_add(size);
+        if attempted > limit {
+            return Err(TrackingError::BudgetExceeded { limit, attempted });
+        }
+        Ok(())
+    }
+
+    /// Lock `mutex` on the tracking path: waits in blocking mode, otherwise
+    /// gives up at once (returning `None`) when it is busy
+    fn tracking_lock<'a, T>(&self, mutex: &'a Mutex<T>) -> Option<MutexGuard<'a, T>> {
+        if self.blocking_mode.load(Ordering::Relaxed) {
+            Some(match mutex.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => poisoned.into_inner(),
+            })
+        } else {
+            mutex.try_lock().ok()
+        }
+    }
+
+    /// Apply a change to the per-type totals. Callers hold the active map lock,
+    /// so the totals change in the same order as the map. Like the map itself,
+    /// the totals are only waited for in blocking mode; a skipped update is
+    /// counted in `TrackingFidelity::dropped_type_updates`.
+    fn update_type_totals(&self, update: impl FnOnce(&mut TypeTotals)) {
+        match self.tracking_lock(&self.type_totals) {
+            Some(mut totals) => update(&mut totals),
+            None => {
+                self.dropped_type_updates.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Highest number of bytes each type has held at once.
+    ///
+    /// Peaks are maintained incrementally as allocations gain a type (usually
+    /// through `associate_var`) and are freed. A peak far above the type's
+    /// current usage points at a transient spike worth investigating.
+    pub fn get_peak_by_type(&self) -> TrackingResult<HashMap<String, usize>> {
+        let totals = match self.type_totals.lock() {
+            Ok(totals) => totals,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        Ok(totals
+            .totals
+            .iter()
+            .map(|(type_name, &(_, peak))| (type_name.clone(), peak))
+            .collect())
+    }
+
+    /// Current and peak active bytes per type, largest peak first.
+    ///
+    /// Same running totals as `get_peak_by_type`: an allocation typed after it
+    /// was tracked (through `associate_var`) counts towards its type from then
+    /// on, and retyping moves its bytes to the new type.
+    pub fn get_peak_memory_by_type(&self) -> TrackingResult<Vec<TypePeakUsage>> {
+        let mut usage: Vec<TypePeakUsage> = {
+            let totals = match self.type_totals.lock() {
+                Ok(totals) => totals,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            totals
+                .totals
+                .iter()
+                .map(|(type_name, &(current_size, peak_size))| TypePeakUsage {
+                    type_name: type_name.clone(),
+                    current_size,
+                    peak_size,
+                })
+                .collect()
+        };
+        usage.sort_by(|a, b| {
+            b.peak_size
+                .cmp(&a.peak_size)
+                .then_with(|| a.type_name.cmp(&b.type_name))
+        });
+        Ok(usage)
+    }
+
+    /// Open a nested scope measurement on the current thread.
+    ///
+    /// Scopes form a per-thread stack: allocations and deallocations made
+    /// through this tracker are attributed to the innermost open scope, and
+    /// `ScopeGuard::finish` closes the scope and reports its own activity
+    /// without the activity of scopes nested inside it.
+    pub fn begin_scope(&self, name: &str) -> ScopeGuard<'_> {
+        self.begin_scope_with_options(name, &ScopeOptions::default())
+    }
+
+    /// Measure a block until the returned guard is dropped.
+    ///
+    /// Typical use is `let _scope = tracker.scope("parse_request");` at the
+    /// top of a function. When the guard drops, the scope's own activity
+    /// (nested scopes excluded, as with `begin_scope`) is logged through
+    /// `tracing` and appended to `scope_reports`. The guard only borrows the
+    /// tracker and keeps running totals; no allocations are copied.
+    pub fn scope(&self, label: &str) -> ScopeGuard<'_> {
+        ScopeGuard::begin_recorded(self, label)
+    }
+
+    /// Reports of the `scope` guards dropped so far, in the order they closed
+    pub fn scope_reports(&self) -> Vec<ScopeReport> {
+        match self.scope_reports.lock() {
+            Ok(reports) => reports.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Remove and return the stored `scope` reports
+    pub fn take_scope_reports(&self) -> Vec<ScopeReport> {
+        match self.scope_reports.lock() {
+            Ok(mut reports) => std::mem::take(&mut *reports),
+            Err(poisoned) => std::mem::take(&mut *poisoned.into_inner()),
+        }
+    }
+
+    pub(crate) fn record_scope_report(&self, report: ScopeReport) {
+        match self.scope_reports.lock() {
+            Ok(mut reports) => reports.push(report),
+            Err(poisoned) => poisoned.into_inner().push(report),
+        }
+    }
+
+    /// Open a nested scope measurement with explicit options
+    pub fn begin_scope_with_options(&self, name: &str, options: &ScopeOptions) -> ScopeGuard<'_> {
+        ScopeGuard::begin(self, name, options)
+    }
+
+    /// Identifies this tracker's frames on the shared thread-local scope stack
+    pub(crate) fn scope_key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Pointers of the given `(ptr, seq)` allocations that are still active.
+    ///
+    /// The sequence number guards against a freed address having been reused
+    /// by an unrelated allocation.
+    pub(crate) fn still_active(&self, allocations: &[(usize, u64)]) -> Vec<usize> {
+        let active = self.active_allocations.lock_all();
+        let mut alive: Vec<(usize, u64)> = allocations
+            .iter()
+            .copied()
+            .filter(|(ptr, seq)| active.get(ptr).is_some_and(|a| a.seq == *seq))
+            .collect();
+        alive.sort_by_key(|&(_, seq)| seq);
+        alive.into_iter().map(|(ptr, _)| ptr).collect()
+    }
+
+    /// Track a memory deallocation.
+    pub fn track_deallocation(&self, ptr: usize) -> TrackingResult<()> {
+        self.record_deallocation(ptr, None)
+    }
+
+    /// Track a memory deallocation made at `timestamp` (milliseconds since
+    /// UNIX_EPOCH) instead of now; see `track_allocation_at`.
+    ///
+    /// A timestamp earlier than the allocation's is clamped to it, so the
+    /// recorded lifetime is never negative.
+    pub fn track_deallocation_at(&self, ptr: usize, timestamp: u128) -> TrackingResult<()> {
+        self.record_deallocation(ptr, Some(timestamp))
+    }
+
+    /// Remove an active allocation, stamping it with `timestamp` or the current time
+    fn record_deallocation(&self, ptr: usize, timestamp: Option<u128>) -> TrackingResult<()> {
+        // Unless in blocking mode, don't wait during high deallocation activity
+        match self.tracking_lock(self.active_allocations.shard(ptr)) {
+            Some(mut active) => {
+                let removed = active.remove(&ptr);
+                if let Some(allocation) = &removed {
+                    if let Some(type_name) = &allocation.type_name {
+                        self.update_type_totals(|totals| totals.remove(type_name, allocation.size));
+                    }
+                }
+                drop(active);
+
+                if let Some(mut allocation) = removed {
+                    if allocation.size_estimated {
+                        // A synthetic entry's estimated size is only in the statistics
+                        // if a reconciliation folded it in; otherwise nothing is subtracted
+                        let counted_size = allocation.estimate_counted.then_some(allocation.size);
+                        self.stats.record_estimated_deallocation(counted_size);
+                        if let Some(size) = counted_size {
+                            crate::scope::record_deallocation(self.scope_key(), size);
+                        }
+                        tracing::debug!(
+                            "Freed synthetic allocation at {:x} (estimated size {}, counted: {})",
+                            ptr,
+                            allocation.size,
+                            allocation.estimate_counted
+                        );
+                    } else {
+                        self.stats.record_deallocation(allocation.size);
+                        crate::scope::record_deallocation(self.scope_key(), allocation.size);
+                    }
+                    self.child_links.forget(ptr);
+
+                    // Retain the freed allocation in history (optional, skip if busy)
+                    match timestamp {
+                        Some(timestamp) => {
+                            allocation.timestamp_dealloc =
+                                Some(timestamp.max(allocation.timestamp_alloc));
+                        }
+                        None => allocation.mark_deallocated(),
+                    }
+                    if let Some(mut history) = self.tracking_lock(&self.allocation_history) {
+                        if self.within_coalesce_window(&allocation) && history.coalesce(ptr) {
+                            self.stats.coalesced.fetch_add(1, Ordering::Relaxed);
+                        } else {
+                            history.record_deallocation(&allocation);
+                        }
+                    }
+
+                    self.recent_frees.record_free(ptr);
+                    self.check_thresholds();
+                    self.event_hooks.emit(&TrackEvent::Dealloc { ptr });
+                    self.maybe_auto_reconcile();
+                } else {
+                    self.stats
+                        .unknown_deallocations
+                        .fetch_add(1, Ordering::Relaxed);
+                    if self.recent_frees.check_unknown_free(ptr) {
+                        tracing::warn!("Suspected double free at {:x}", ptr);
+                    }
+                }
+                Ok(())
+            }
+            None => {
+                // If we can't get the lock immediately, skip tracking to avoid deadlock
+                self.dropped_deallocations.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+        }
+    }
+
+    /// Track a reallocation that moved the allocation at `old_ptr` to
+    /// `new_ptr` with `new_size` bytes, as when a `Vec` grows.
+    ///
+    /// The entry keeps its variable and type association, allocation time and
+    /// sequence number, and counts a growth event. Statistics change by the
+    /// byte difference only: no allocation or deallocation is counted. If
+    /// `old_ptr` is not tracked, `new_ptr` is tracked as a fresh allocation.
+    pub fn track_reallocation(
+        &self,
+        old_ptr: usize,
+        new_ptr: usize,
+        new_size: usize,
+    ) -> TrackingResult<()> {
+        self.record_reallocation(old_ptr, new_ptr, new_size, None, || {
+            self.track_allocation(new_ptr, new_size)
+        })
+    }
+
+    /// Move an active allocation to `new_ptr`, or call `untracked` if
+    /// `old_ptr` is not tracked
+    fn record_reallocation(
+        &self,
+        old_ptr: usize,
+        new_ptr: usize,
+        new_size: usize,
+        usable_size: Option<usize>,
+        untracked: impl FnOnce() -> TrackingResult<()>,
+    ) -> TrackingResult<()> {
+        // Unless in blocking mode, don't wait during high allocation activity
+        let removed = match self.tracking_lock(self.active_allocations.shard(old_ptr)) {
+            Some(mut active) => {
+                let removed = active.remove(&old_ptr);
+                if let Some(allocation) = &removed {
+                    if let Some(type_name) = &allocation.type_name {
+                        self.update_type_totals(|totals| totals.remove(type_name, allocation.size));
+                    }
+                }
+                removed
+            }
+            None => {
+                self.dropped_allocations.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
+            }
+        };
+        let Some(mut allocation) = removed else {
+            return untracked();
+        };
+
+        // A synthetic entry's estimated size is not in the statistics unless
+        // a reconciliation folded it in
+        let old_size =
+            (!allocation.size_estimated || allocation.estimate_counted).then_some(allocation.size);
+        let budget_result = self.check_budget(new_size.saturating_sub(old_size.unwrap_or(0)));
+        allocation.ptr = new_ptr;
+        allocation.size = new_size;
+        allocation.size_estimated = false;
+        allocation.estimate_counted = false;
+        allocation.usable_size = usable_size.map(|usable| usable.max(new_size));
+        allocation.record_growth(new_size);
+
+        match self.tracking_lock(self.active_allocations.shard(new_ptr)) {
+            Some(mut active) => {
+                if let Some(type_name) = &allocation.type_name {
+                    self.update_type_totals(|totals| totals.add(type_name, new_size));
+                }
+                if let Some(replaced) = active.insert(new_ptr, allocation.clone()) {
+                    // A free of the new address was missed; like `DuplicatePolicy::Overwrite`
+                    self.stats
+                        .ptr_reuse_collisions
+                        .fetch_add(1, Ordering::Relaxed);
+                    if let Some(type_name) = &replaced.type_name {
+                        self.update_type_totals(|totals| totals.remove(type_name, replaced.size));
+                    }
+                }
+            }
+            None => {
+                // The allocation is lost; account for it as freed
+                if let Some(old_size) = old_size {
+                    self.stats.record_deallocation(old_size);
+                }
+                self.dropped_allocations.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
+            }
+        }
+
+        match old_size {
+            Some(old_size) => self.stats.record_resize(old_size, new_size),
+            None => self.stats.record_allocation(new_size),
+        }
+        self.child_links.forget(old_ptr);
+        self.recent_frees.record_reuse(new_ptr);
+
+        // Keep the history entry in sync (optional, skip if busy)
+        if let Some(mut history) = self.tracking_lock(&self.allocation_history) {
+            history.relocate(old_ptr, &allocation);
+        }
+
+        self.check_thresholds();
+        self.event_hooks.emit(&TrackEvent::Realloc {
+            old_ptr,
+            new_ptr,
+            size: new_size,
+        });
+        self.maybe_auto_reconcile();
+        budget_result
+    }
+
+    /// Track an explicitly flagged stack allocation (e.g. a large `[u8; N]` local).
+    ///
+    /// Stack allocations are recorded separately from heap allocations and never
+    /// contribute to the heap statistics returned by `get_stats`.
+    pub fn track_stack_allocation(
+        &self,
+        id: usize,
+        size: usize,
+        var_name: String,
+    ) -> TrackingResult<()> {
+        let mut allocation = AllocationInfo::new(id, size);
+        allocation.var_name = Some(var_name);
+        allocation.scope_name = Some("stack".to_string());
+
+        let mut stack = match self.stack_allocations.lock() {
+            Ok(stack) => stack,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut stats = match self.stack_stats.lock() {
+            Ok(stats) => stats,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if let Some(previous) = stack.insert(id, allocation) {
+            // Re-flagging the same id replaces the previous record
+            stats.active_allocations = stats.active_allocations.saturating_sub(1);
+            stats.active_memory = stats.active_memory.saturating_sub(previous.size);
+        }
+
+        stats.total_allocations = stats.total_allocations.saturating_add(1);
+        stats.total_allocated = stats.total_allocated.saturating_add(size);
+        stats.active_allocations = stats.active_allocations.saturating_add(1);
+        stats.active_memory = stats.active_memory.saturating_add(size);
+        if stats.active_allocations > stats.peak_allocations {
+            stats.peak_allocations = stats.active_allocations;
+        }
+        if stats.active_memory > stats.peak_memory {
+            stats.peak_memory = stats.active_memory;
+        }
+
+        Ok(())
+    }
+
+    /// Mark a flagged stack allocation as released (e.g. when its frame returns).
+    pub fn track_stack_deallocation(&self, id: usize) -> TrackingResult<()> {
+        let mut stack = match self.stack_allocations.lock() {
+            Ok(stack) => stack,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut stats = match self.stack_stats.lock() {
+            Ok(stats) => stats,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if let Some(allocation) = stack.remove(&id) {
+            stats.total_deallocations = stats.total_deallocations.saturating_add(1);
+            stats.total_deallocated = stats.total_deallocated.saturating_add(allocation.size);
+            stats.active_allocations = stats.active_allocations.saturating_sub(1);
+            stats.active_memory = stats.active_memory.saturating_sub(allocation.size);
+        }
+        Ok(())
+    }
+
+    /// Get statistics for explicitly flagged stack allocations.
+    pub fn get_stack_stats(&self) -> TrackingResult<MemoryStats> {
+        match self.stack_stats.lock() {
+            Ok(stats) => Ok(stats.clone()),
+            Err(poisoned) => {
+                // Handle poisoned lock by recovering the data
+                let stats = poisoned.into_inner();
+                Ok(stats.clone())
+            }
+        }
+    }
+
+    /// Get all currently flagged stack allocations.
+    pub fn get_stack_allocations(&self) -> TrackingResult<Vec<AllocationInfo>> {
+        match self.stack_allocations.lock() {
+            Ok(stack) => Ok(stack.values().cloned().collect()),
+            Err(poisoned) => {
+                // Handle poisoned lock by recovering the data
+                let stack = poisoned.into_inner();
+                Ok(stack.values().cloned().collect())
+            }
+        }
+    }
+
+    /// Track a resource counted in `unit` rather than heap bytes (e.g. GPU memory or file handles).
+    ///
+    /// Each unit keeps its own allocations and statistics, so other resources are
+    /// never summed with heap bytes. `ResourceUnit::Bytes` is the heap itself and
+    /// behaves exactly like `track_allocation`.
+    pub fn track_resource_allocation(
+        &self,
+        id: usize,
+        amount: usize,
+        unit: ResourceUnit,
+    ) -> TrackingResult<()> {
+        if unit == ResourceUnit::Bytes {
+            return self.track_allocation(id, amount);
+        }
+
+        let mut allocation = AllocationInfo::new(id, amount);
+        allocation.seq = NEXT_ALLOCATION_SEQ.fetch_add(1, Ordering::Relaxed);
+        allocation.unit = unit.clone();
+
+        let mut resources = match self.resources.lock() {
+            Ok(resources) => resources,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        resources
+            .entry(unit)
+            .or_default()
+            .record_allocation(allocation);
+        Ok(())
+    }
+
+    /// Release a resource tracked with `track_resource_allocation`.
+    pub fn track_resource_deallocation(
+        &self,
+        id: usize,
+        unit: &ResourceUnit,
+    ) -> TrackingResult<()> {
+        if *unit == ResourceUnit::Bytes {
+            return self.track_deallocation(id);
+        }
+
+        let mut resources = match self.resources.lock() {
+            Ok(resources) => resources,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(ledger) = resources.get_mut(unit) {
+            ledger.record_deallocation(id);
+        }
+        Ok(())
+    }
+
+    /// Get statistics for a single resource unit; `ResourceUnit::Bytes` gives `get_stats`.
+    pub fn get_stats_for_unit(&self, unit: &ResourceUnit) -> TrackingResult<MemoryStats> {
+        if *unit == ResourceUnit::Bytes {
+            return self.get_stats();
+        }
+
+        let resources = match self.resources.lock() {
+            Ok(resources) => resources,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        Ok(resources
+            .get(unit)
+            .map(|ledger| ledger.stats.clone())
+            .unwrap_or_default())
+    }
+
+    /// Get the active allocations of a single resource unit.
+    pub fn get_active_allocations_for_unit(
+        &self,
+        unit: &ResourceUnit,
+    ) -> TrackingResult<Vec<AllocationInfo>> {
+        if *unit == ResourceUnit::Bytes {
+            return self.get_active_allocations();
+        }
+
+        let resources = match self.resources.lock() {
+            Ok(resources) => resources,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        Ok(resources
+            .get(unit)
+            .map(|ledger| ledger.allocations.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// Associate a variable name and type with an allocation.
+    ///
+    /// An untracked pointer gets a synthetic entry whose size is guessed
+    /// from the type name; prefer `associate_var_typed` when the type is known.
+    pub fn associate_var(
+        &self,
+        ptr: usize,
+        var_name: String,
+        type_name: String,
+    ) -> TrackingResult<()> {
+        self.associate_var_with_size(ptr, var_name, type_name, None)
+    }
+
+    /// Associate a variable of type `T` with an allocation.
+    ///
+    /// Like `associate_var`, but the type name comes from
+    /// `std::any::type_name::<T>()` and a synthetic entry for an untracked
+    /// pointer is sized `size_of::<T>()` plus `heap_bytes`, which callers can
+    /// supply for containers whose `size_of` only covers the header. The size
+    /// of an already tracked allocation is left as tracked.
+    pub fn associate_var_typed<T>(
+        &self,
+        ptr: usize,
+        var_name: String,
+        heap_bytes: Option<usize>,
+    ) -> TrackingResult<()> {
+        let size = std::mem::size_of::<T>().saturating_add(heap_bytes.unwrap_or(0));
+        self.associate_var_with_size(
+            ptr,
+            var_name,
+            std::any::type_name::<T>().to_string(),
+            Some(size),
+        )
+    }
+
+    /// Shared body of `associate_var` and `associate_var_typed`; a synthetic
+    /// entry uses `synthetic_size`, or an estimate from the type name when `None`
+    fn associate_var_with_size(
+        &self,
+        ptr: usize,
+        var_name: String,
+        type_name: String,
+        synthetic_size: Option<usize>,
+    ) -> TrackingResult<()> {
+        // Unless in blocking mode, don't wait if the allocator is currently tracking
+        match self.tracking_lock(self.active_allocations.shard(ptr)) {
+            Some(mut active) => {
+                if let Some(allocation) = active.get_mut(&ptr) {
+                    allocation.var_name = Some(var_name.clone());
+                    let previous_type = allocation.type_name.replace(type_name.clone());
+                    let size = allocation.size;
+                    self.update_type_totals(|totals| {
+                        if let Some(previous_type) = &previous_type {
+                            totals.remove(previous_type, size);
+                        }
+                        totals.add(&type_name, size);
+                    });
+                    drop(active);
+
+                    // Keep the history entry in sync (optional, skip if busy)
+                    if let Some(mut history) = self.tracking_lock(&self.allocation_history) {
+                        history.associate(ptr, &var_name, &type_name);
+                    }
+                    tracing::debug!(
+                        "Associated variable '{}' with existing allocation at {:x}",
+                        var_name,
+                        ptr
+                    );
+                    Ok(())
+                } else {
+                    // For smart pointers and other complex types, create a synthetic allocation entry
+                    // This ensures we can track variables even when the exact pointer isn't in our allocator
+                    let mut synthetic_allocation = AllocationInfo::new(ptr, 0); // Size will be estimated
+                    synthetic_allocation.var_name = Some(var_name.clone());
+                    synthetic_allocation.type_name = Some(type_name.clone());
+
+                    // Estimate size based on type unless the caller knows it
+                    let estimated_size =
+                        synthetic_size.unwrap_or_else(|| estimate_type_size(&type_name));
+                    synthetic_allocation.size = estimated_size;
+                    synthetic_allocation.size_estimated = true;
+
+                    // Add to active allocations for tracking
+                    self.update_type_totals(|totals| totals.add(&type_name, estimated_size));
+                    active.insert(ptr, synthetic_allocation);
+                    tracing::debug!("Created synthetic allocation for variable '{}' at {:x} (estimated size: {})", 
+                                   var_name, ptr, estimated_size);
+                    Ok(())
+                }
+            }
+            None => {
+                // If we can't get the lock immediately, it's likely the allocator is busy
+                // We'll just skip the association to avoid deadlock
+                // tracing::warn!("Failed to associate variable '{}' - tracker busy", var_name);
+                Ok(())
+            }
+        }
+    }
+
+    /// Register a callback invoked for every tracked allocation and deallocation.
+    ///
+    /// Hooks run on the tracking thread after the tracker's locks are released,
+    /// so they may call back into the tracker. Keep them cheap: they sit on the
+    /// allocation path.
+    pub fn add_event_hook<F>(&self, hook: F) -> HookId
+    where
+        F: Fn(&TrackEvent) + Send + Sync + 'static,
+    {
+        let hook: EventHook = Arc::new(hook);
+        self.event_hooks.add(hook)
+    }
+
+    /// Remove a previously registered hook. Returns false if it was not registered.
+    pub fn remove_event_hook(&self, id: HookId) -> bool {
+        self.event_hooks.remove(id)
+    }
+
+    /// Start appending every tracked event to a JSONL file at `path`.
+    ///
+    /// Each line is a `LoggedEvent`. The file is flushed every few events, so a
+    /// crash loses at most the last unflushed handful. Starting a new log stops
+    /// any log already running.
+    pub fn start_event_log<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        self.stop_event_log()?;
+
+        let writer = Arc::new(Mutex::new(EventLogWriter::create(path.as_ref())?));
+        let hook_writer = Arc::clone(&writer);
+        let hook_id = self.add_event_hook(move |event| {
+            let mut writer = match hook_writer.lock() {
+                Ok(writer) => writer,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            // A failed write must not disturb the tracked program
+            let _ = writer.append(event);
+        });
+
+        let mut event_log = match self.event_log.lock() {
+            Ok(event_log) => event_log,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        *event_log = Some(ActiveEventLog { hook_id, writer });
+        Ok(())
+    }
+
+    /// Stream every tracked event to clients of a Unix domain socket at `path`.
+    ///
+    /// Each connected client receives newline-delimited `LoggedEvent` JSON in
+    /// real time, and any number of clients may attach. Tracking never waits
+    /// for clients: events are queued, clients that disconnect or stall are
+    /// dropped, and events are discarded while the queue is full. Starting a
+    /// new server stops any server already running. Fails if `path` exists.
+    #[cfg(all(unix, feature = "socket-server"))]
+    pub fn start_socket_server<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        self.stop_socket_server()?;
+
+        let (server, hook) = crate::socket_server::SocketServer::start(path.as_ref())?;
+        let hook_id = self.event_hooks.add(hook);
+
+        let mut socket_server = match self.socket_server.lock() {
+            Ok(socket_server) => socket_server,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        *socket_server = Some((hook_id, server));
+        Ok(())
+    }
+
+    /// Stop the server started with `start_socket_server`, disconnecting its
+    /// clients and removing the socket file.
+    ///
+    /// Does nothing if no server is running.
+    #[cfg(all(unix, feature = "socket-server"))]
+    pub fn stop_socket_server(&self) -> TrackingResult<()> {
+        let active = match self.socket_server.lock() {
+            Ok(mut socket_server) => socket_server.take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        let Some((hook_id, server)) = active else {
+            return Ok(());
+        };
+
+        self.event_hooks.remove(hook_id);
+        server.stop()
+    }
+
+    /// Stop the event log started with `start_event_log` and flush it to disk.
+    ///
+    /// Does nothing if no log is running.
+    pub fn stop_event_log(&self) -> TrackingResult<()> {
+        let active = match self.event_log.lock() {
+            Ok(mut event_log) => event_log.take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        let Some(active) = active else {
+            return Ok(());
+        };
+
+        self.event_hooks.remove(active.hook_id);
+        let mut writer = match active.writer.lock() {
+            Ok(writer) => writer,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        writer.flush()
+    }
+
+    /// Register a finalizer to run on `shutdown`, e.g. to write a final report.
+    ///
+    /// Finalizers run once, in registration order. They also run when the
+    /// tracker is dropped, but the global tracker is never dropped, so call
+    /// `shutdown` explicitly at the end of the program.
+    pub fn on_shutdown(&self, finalizer: ShutdownHook) {
+        let mut hooks = match self.shutdown_hooks.lock() {
+            Ok(hooks) => hooks,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hooks.push(finalizer);
+    }
+
+    /// Run the registered finalizers, then stop and flush any running event log.
+    ///
+    /// Finalizers are removed as they run, so calling `shutdown` again only
+    /// runs finalizers registered since. A finalizer may use the tracker,
+    /// including registering further finalizers, which run in the same call.
+    pub fn shutdown(&self) -> TrackingResult<()> {
+        loop {
+            let hooks = match self.shutdown_hooks.lock() {
+                Ok(mut hooks) => std::mem::take(&mut *hooks),
+                Err(poisoned) => std::mem::take(&mut *poisoned.into_inner()),
+            };
+            if hooks.is_empty() {
+                break;
+            }
+            for finalizer in hooks {
+                finalizer();
+            }
+        }
+        #[cfg(all(unix, feature = "socket-server"))]
+        self.stop_socket_server()?;
+        self.stop_event_log()
+    }
+
+    /// Checkpoint the complete tracker state to `path`.
+    ///
+    /// Unlike the JSON export this keeps all tracked data: active allocations,
+    /// history, statistics, per-type peaks and samples, dropped-event counts,
+    /// stack, resource and pool allocations, `link_child` links, the current
+    /// generation, recent frees and suspected double frees, scope reports,
+    /// metadata and the configuration (duplicate policy, history limit,
+    /// blocking mode, shard count, backtrace capture, coalesce window, budget,
+    /// auto-reconcile interval and double-free window) are all written, in a
+    /// versioned JSON document. Runtime attachments are not part of the state:
+    /// event hooks, thresholds, a running event log, shutdown finalizers, an
+    /// open warmup window and the `rate_sample` starting point.
+    pub fn save_state<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        self.capture_state()?.write(path.as_ref())
+    }
+
+    fn capture_state(&self) -> TrackingResult<TrackerState> {
+        let active_allocations = self.get_active_allocations()?;
+        let history = self.get_allocation_history()?;
+        let stack_allocations = match self.stack_allocations.lock() {
+            Ok(stack) => stack.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        let stack_stats = self.get_stack_stats()?;
+        let metadata = match self.metadata.lock() {
+            Ok(metadata) => metadata.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        let resources = match self.resources.lock() {
+            Ok(resources) => resources.clone().into_iter().collect(),
+            Err(poisoned) => poisoned.into_inner().clone().into_iter().collect(),
+        };
+        let type_peaks = self.get_peak_by_type()?;
+        let coalesce_window_ms = self.coalesce_window_ms.load(Ordering::Relaxed);
+        let budget = self.budget.load(Ordering::Relaxed);
+        let auto_reconcile_every = self.auto_reconcile_every.load(Ordering::Relaxed);
+        let (double_free_window, recent_frees) = self.recent_frees.snapshot();
+        let type_samples = match self.type_samples.lock() {
+            Ok(samples) => samples.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        let scope_reports = match self.scope_reports.lock() {
+            Ok(reports) => reports.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        let config = TrackerConfig {
+            history_limit: self.history_capacity(),
+            blocking_mode: self.is_blocking_mode(),
+            shard_count: self.shard_count(),
+            capture_backtraces: self.capture_backtraces.load(Ordering::Relaxed),
+            coalesce_window_ms: (coalesce_window_ms != COALESCE_DISABLED)
+                .then_some(coalesce_window_ms),
+            budget: (budget != NO_BUDGET).then_some(budget),
+            auto_reconcile_every: (auto_reconcile_every != 0).then_some(auto_reconcile_every),
+            double_free_window: (double_free_window != 0).then_some(double_free_window),
+        };
+
+        Ok(TrackerState {
+            format_version: STATE_FORMAT_VERSION,
+            active_allocations,
+            history,
+            stats: self.stats.snapshot(),
+            stack_allocations,
+            stack_stats,
+            metadata,
+            duplicate_policy: self.duplicate_policy(),
+            resources,
+            config,
+            history_evicted: self.history_evicted_count(),
+            type_peaks,
+            dropped_allocations: self.dropped_allocations.load(Ordering::Relaxed),
+            dropped_deallocations: self.dropped_deallocations.load(Ordering::Relaxed),
+            dropped_type_updates: self.dropped_type_updates.load(Ordering::Relaxed),
+            generation: self.generation.load(Ordering::Relaxed),
+            child_links: self.child_links.snapshot(),
+            pools: self.pools.snapshot(),
+            recent_frees,
+            type_samples,
+            scope_reports,
+        })
+    }
+
+    /// Restore a tracker from a checkpoint written by `save_state`.
+    ///
+    /// Fails with `TrackingError::StateVersionMismatch` if the file was written
+    /// by an incompatible format version.
+    pub fn load_state<P: AsRef<std::path::Path>>(path: P) -> TrackingResult<MemoryTracker> {
+        Ok(Self::from_state(TrackerState::read(path.as_ref())?))
+    }
+
+    /// Export the complete tracker state as flat JSON built to round-trip
+    /// through `import_from_json`.
+    ///
+    /// This is the complete document written by `save_state`; use it instead
+    /// of the hierarchical `export_to_json` when the data will be re-analysed.
+    pub fn export_raw_json<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        self.save_state(path)
+    }
+
+    /// Rebuild a tracker from a JSON file for offline analysis.
+    ///
+    /// Accepts both `export_raw_json` (or `save_state`) output, which is
+    /// restored exactly, and the hierarchical `export_to_json` output, which is
+    /// lossy: allocations are rebuilt from the flat `allocations` array when it
+    /// was included, else from the per-type entries with placeholder pointers,
+    /// and only the summary statistics are restored. Parse errors are reported
+    /// as `TrackingError::SerializationError` naming the file.
+    pub fn import_from_json<P: AsRef<std::path::Path>>(path: P) -> TrackingResult<MemoryTracker> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        let document: serde_json::Value = serde_json::from_str(&contents).map_err(|e| {
+            TrackingError::SerializationError(format!("{}: invalid JSON: {e}", path.display()))
+        })?;
+
+        let state = if document.get("format_version").is_some() {
+            TrackerState::parse(&contents).map_err(|error| match error {
+                TrackingError::SerializationError(message) => {
+                    TrackingError::SerializationError(format!("{}: {message}", path.display()))
+                }
+                other => other,
+            })?
+        } else {
+            let root: ExportRoot = serde_json::from_value(document).map_err(|e| {
+                TrackingError::SerializationError(format!(
+                    "{}: not a memscope JSON export: {e}",
+                    path.display()
+                ))
+            })?;
+            TrackerState::from_export(root)
+        };
+        Ok(Self::from_state(state))
+    }
+
+    /// Write the complete tracker state as a compact binary dump.
+    ///
+    /// Holds exactly what `save_state` writes, in a fraction of the space and
+    /// time of the JSON: see `export_binary` for the layout. Read it back with
+    /// `import_from_binary`.
+    pub fn export_to_binary<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        crate::export_binary::write_binary(&self.capture_state()?, path.as_ref())
+    }
+
+    /// Rebuild a tracker from a dump written by `export_to_binary`.
+    ///
+    /// Fails with `TrackingError::SerializationError` if the file does not
+    /// start with the binary magic bytes or was written by another binary
+    /// format version.
+    pub fn import_from_binary<P: AsRef<std::path::Path>>(path: P) -> TrackingResult<MemoryTracker> {
+        Ok(Self::from_state(crate::export_binary::read_binary(
+            path.as_ref(),
+        )?))
+    }
+
+    fn from_state(state: TrackerState) -> Self {
+        // Ids are never reused within a process, so new allocations follow the restored ones
+        let max_seq = state
+            .history
+            .iter()
+            .chain(state.active_allocations.iter())
+            .map(|a| a.seq)
+            .max();
+        if let Some(max_seq) = max_seq {
+            NEXT_ALLOCATION_SEQ.fetch_max(max_seq.saturating_add(1), Ordering::Relaxed);
+        }
+        let config = state.config;
+        let active =
+            ShardedAllocations::from_allocations(state.active_allocations, config.shard_count);
+        let mut type_totals = TypeTotals::from_active(&active.lock_all());
+        for (type_name, peak) in state.type_peaks {
+            let (_, restored_peak) = type_totals.totals.entry(type_name).or_default();
+            *restored_peak = (*restored_peak).max(peak);
+        }
+        Self {
+            active_allocations: active,
+            allocation_history: Mutex::new(AllocationHistory::from_entries(
+                state.history,
+                config.history_limit,
+                state.history_evicted,
+            )),
+            stats: AtomicStats::from_snapshot(&state.stats),
+            stack_allocations: Mutex::new(state.stack_allocations),
+            stack_stats: Mutex::new(state.stack_stats),
+            metadata: Mutex::new(state.metadata),
+            duplicate_policy: Mutex::new(state.duplicate_policy),
+            resources: Mutex::new(state.resources.into_iter().collect()),
+            warmup: AtomicBool::new(false),
+            capture_backtraces: AtomicBool::new(config.capture_backtraces),
+            blocking_mode: AtomicBool::new(config.blocking_mode),
+            generation: AtomicU32::new(state.generation),
+            child_links: ChildLinks::from_edges(state.child_links),
+            pools: PoolStacks::from_pools(state.pools),
+            recent_frees: RecentFrees::from_state(
+                config.double_free_window.unwrap_or(0),
+                state.recent_frees,
+            ),
+            thresholds: Thresholds::default(),
+            budget: AtomicUsize::new(config.budget.unwrap_or(NO_BUDGET)),
+            scope_reports: Mutex::new(state.scope_reports),
+            last_rate_sample: Mutex::new(None),
+            auto_reconcile_every: AtomicUsize::new(config.auto_reconcile_every.unwrap_or(0)),
+            ops_since_reconcile: AtomicUsize::new(0),
+            dropped_allocations: AtomicU64::new(state.dropped_allocations),
+            dropped_deallocations: AtomicU64::new(state.dropped_deallocations),
+            dropped_type_updates: AtomicU64::new(state.dropped_type_updates),
+            coalesce_window_ms: AtomicU64::new(
+                config.coalesce_window_ms.unwrap_or(COALESCE_DISABLED),
+            ),
+            event_hooks: HookRegistry::default(),
+            event_log: Mutex::new(None),
+            #[cfg(all(unix, feature = "socket-server"))]
+            socket_server: Mutex::new(None),
+            shutdown_hooks: Mutex::new(Vec::new()),
+            type_totals: Mutex::new(type_totals),
+            type_samples: Mutex::new(state.type_samples),
+            type_breakdown_cache: Mutex::new(None),
+        }
+    }
+
+    /// Attach a key-value pair (e.g. git commit, hostname, workload name) to this tracker.
+    ///
+    /// Metadata is emitted in the `metadata` block of exports so that runs can be
+    /// told apart later. Setting an existing key replaces its value.
+    pub fn set_metadata(&self, key: &str, value: String) {
+        match self.metadata.lock() {
+            Ok(mut metadata) => {
+                metadata.insert(key.to_string(), value);
+            }
+            Err(poisoned) => {
+                poisoned.into_inner().insert(key.to_string(), value);
+            }
+        }
+    }
+
+    /// Get all metadata attached to this tracker, sorted by key.
+    pub fn get_metadata(&self) -> BTreeMap<String, String> {
+        match self.metadata.lock() {
+            Ok(metadata) => metadata.clone().into_iter().collect(),
+            Err(poisoned) => poisoned.into_inner().clone().into_iter().collect(),
+        }
+    }
+
+    /// Get current memory usage statistics.
+    pub fn get_stats(&self) -> TrackingResult<MemoryStats> {
+        Ok(self.stats.snapshot())
+    }
+
+    /// Snapshot the current statistics as a baseline for `delta_since`.
+    ///
+    /// Baselines are plain values: any number can coexist and each can be
+    /// queried repeatedly, unlike a scope guard.
+    pub fn mark_baseline(&self) -> Baseline {
+        Baseline {
+            stats: self.stats.snapshot(),
+            taken_at: std::time::Instant::now(),
+        }
+    }
+
+    /// Capture the statistics and the set of live allocations, to compare
+    /// with a later snapshot using `MemorySnapshot::diff`.
+    ///
+    /// Only `(ptr, seq, size)` is copied while the active map is locked;
+    /// sorting happens after it is released, so tracking is stalled for
+    /// little more than a memcpy of the map.
+    pub fn snapshot(&self) -> MemorySnapshot {
+        let live: Vec<(usize, u64, usize)> = {
+            let active = self.active_allocations.lock_all();
+            active
+                .values()
+                .map(|allocation| (allocation.ptr, allocation.seq, allocation.size))
+                .collect()
+        };
+        MemorySnapshot::new(self.stats.snapshot(), live)
+    }
+
+    /// Change in the statistics since `baseline` was marked
+    pub fn delta_since(&self, baseline: &Baseline) -> TrackingResult<StatsDelta> {
+        let now = self.stats.snapshot();
+        let then = &baseline.stats;
+
+        Ok(StatsDelta {
+            allocations: now.total_allocations.saturating_sub(then.total_allocations),
+            deallocations: now
+                .total_deallocations
+                .saturating_sub(then.total_deallocations),
+            allocated_bytes: now.total_allocated.saturating_sub(then.total_allocated),
+            deallocated_bytes: now.total_deallocated.saturating_sub(then.total_deallocated),
+            active_allocations_change: now.active_allocations as isize
+                - then.active_allocations as isize,
+            active_memory_change: now.active_memory as isize - then.active_memory as isize,
+            elapsed_ms: baseline.taken_at.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Allocation, deallocation and allocated-byte rates per second since the
+    /// previous call.
+    ///
+    /// The tracker remembers each sample, so calling this periodically (e.g.
+    /// every second from a background thread) yields a churn time series. The
+    /// first call only starts the series and returns zeros. Time is measured
+    /// with a monotonic clock; if no time has passed since the previous call,
+    /// its rates are returned again instead of dividing by zero.
+    pub fn rate_sample(&self) -> RateSample {
+        let now = self.mark_baseline();
+        let mut last = match self.last_rate_sample.lock() {
+            Ok(last) => last,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let sample = match last.as_ref() {
+            None => RateSample::default(),
+            Some((previous, previous_sample)) => {
+                let interval = now.taken_at.saturating_duration_since(previous.taken_at);
+                if interval.is_zero() {
+                    return *previous_sample;
+                }
+                let per_sec = |now: usize, then: usize| {
+                    now.saturating_sub(then) as f64 / interval.as_secs_f64()
+                };
+                RateSample {
+                    allocations_per_sec: per_sec(
+                        now.stats.total_allocations,
+                        previous.stats.total_allocations,
+                    ),
+                    deallocations_per_sec: per_sec(
+                        now.stats.total_deallocations,
+                        previous.stats.total_deallocations,
+                    ),
+                    bytes_allocated_per_sec: per_sec(
+                        now.stats.total_allocated,
+                        previous.stats.total_allocated,
+                    ),
+                    interval,
+                }
+            }
+        };
+        *last = Some((now, sample));
+        sample
+    }
+
+    /// Estimate the memory the tracker itself uses for bookkeeping.
+    ///
+    /// Counts the reserved capacity of the active map, the history and its
+    /// index, and the stack map, plus the heap strings held by their entries.
+    /// Allocator rounding and hash-table control bytes beyond one per slot are
+    /// not included, so the true figure is somewhat higher.
+    pub fn self_overhead_bytes(&self) -> TrackingResult<usize> {
+        let slot_bytes = std::mem::size_of::<(usize, AllocationInfo)>() + 1;
+        let map_bytes = |map: &HashMap<usize, AllocationInfo>| {
+            map.capacity() * slot_bytes + map.values().map(allocation_heap_bytes).sum::<usize>()
+        };
+
+        let active = {
+            let active = self.active_allocations.lock_all();
+            active.capacity() * slot_bytes
+                + active.values().map(allocation_heap_bytes).sum::<usize>()
+        };
+        let history = {
+            let history = match self.allocation_history.lock() {
+                Ok(history) => history,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            history.entries.capacity() * std::mem::size_of::<AllocationInfo>()
+                + history
+                    .entries
+                    .iter()
+                    .map(allocation_heap_bytes)
+                    .sum::<usize>()
+                + history.live_index.capacity() * (std::mem::size_of::<(usize, usize)>() + 1)
+        };
+        let stack = match self.stack_allocations.lock() {
+            Ok(stack) => map_bytes(&stack),
+            Err(poisoned) => map_bytes(&poisoned.into_inner()),
+        };
+
+        Ok(std::mem::size_of::<Self>() + active + history + stack)
+    }
+
+    /// Tracker bookkeeping bytes per tracked live byte.
+    ///
+    /// `self_overhead_bytes() / active_memory`. A ratio near or above 1.0 means
+    /// the tracking metadata rivals the data being tracked (typical of many tiny
+    /// allocations). Returns `f64::INFINITY` when no memory is live.
+    pub fn overhead_ratio(&self) -> TrackingResult<f64> {
+        let overhead = self.self_overhead_bytes()?;
+        let active_memory = self.get_stats()?.active_memory;
+
+        if active_memory == 0 {
+            return Ok(f64::INFINITY);
+        }
+        Ok(overhead as f64 / active_memory as f64)
+    }
+
+    /// Allocations made per byte of memory retained, as a churn indicator.
+    ///
+    /// `total_allocations / max(1, active_memory)`: the tracker starts empty,
+    /// so the active memory is the net growth since tracking began. A high
+    /// value means much transient allocate/free activity for little retained
+    /// memory, which reuse or pooling could cut.
+    pub fn churn_efficiency(&self) -> TrackingResult<f64> {
+        let stats = self.get_stats()?;
+        Ok(stats.total_allocations as f64 / stats.active_memory.max(1) as f64)
+    }
+
+    /// Sum the sizes of all active allocations directly from the active map.
+    pub fn live_memory_exact(&self) -> TrackingResult<usize> {
+        let active = self.active_allocations.lock_all();
+        Ok(active.values().map(|a| a.size).sum())
+    }
+
+    /// Compare the aggregate statistics against the active allocation map.
+    ///
+    /// The result is only meaningful while no tracking calls are in flight,
+    /// since statistics are updated just after the active map.
+    pub fn verify_consistency(&self) -> TrackingResult<ConsistencyReport> {
+        let active = self.active_allocations.lock_all();
+        let stats = self.stats.snapshot();
+
+        Ok(ConsistencyReport {
+            stats_active_allocations: stats.active_allocations,
+            actual_active_allocations: active.len(),
+            stats_active_memory: stats.active_memory,
+            actual_active_memory: active.values().map(|a| a.size).sum(),
+        })
+    }
+
+    /// How many tracking events were recorded versus dropped.
+    ///
+    /// `track_allocation` and `track_deallocation` never wait for their shard
+    /// of the active map: when it is busy the event is skipped and counted here. A fidelity
+    /// well below 1.0 means the statistics undercount and the run's numbers
+    /// should be treated with caution.
+    pub fn tracking_fidelity(&self) -> TrackingFidelity {
+        let stats = self.stats.snapshot();
+        let tracked_allocations = stats.total_allocations as u64;
+        let tracked_deallocations = stats.total_deallocations as u64;
+        let dropped_allocations = self.dropped_allocations.load(Ordering::Relaxed);
+        let dropped_deallocations = self.dropped_deallocations.load(Ordering::Relaxed);
+
+        let tracked = tracked_allocations + tracked_deallocations;
+        let attempted = tracked + dropped_allocations + dropped_deallocations;
+        TrackingFidelity {
+            tracked_allocations,
+            tracked_deallocations,
+            dropped_allocations,
+            dropped_deallocations,
+            dropped_type_updates: self.dropped_type_updates.load(Ordering::Relaxed),
+            fidelity: if attempted == 0 {
+                1.0
+            } else {
+                tracked as f64 / attempted as f64
+            },
+        }
+    }
+
+    /// Check whether everything allocated has been freed, e.g. at the end of a test.
+    ///
+    /// The totals come from the statistics, but `outstanding` and
+    /// `outstanding_bytes` are read from the active map itself: dropped or
+    /// merged events can make `total_allocations - total_deallocations`
+    /// disagree with what is really still live. Stack and resource allocations
+    /// are not included.
+    pub fn allocation_balance(&self) -> AllocationBalance {
+        let (outstanding, outstanding_bytes) = {
+            let active = self.active_allocations.lock_all();
+            (active.len(), active.values().map(|a| a.size).sum())
+        };
+        let stats = self.stats.snapshot();
+
+        AllocationBalance {
+            total_allocations: stats.total_allocations,
+            total_deallocations: stats.total_deallocations,
+            outstanding,
+            outstanding_bytes,
+            is_balanced: outstanding == 0,
+        }
+    }
+
+    /// Forget everything tracked so far, e.g. between benchmark iterations.
+    ///
+    /// Clears the active allocations, the history, child links, stored scope
+    /// reports and the per-type totals and samples, and resets the statistics and dropped
+    /// event counts to zero. Configuration, hooks, metadata, stack, resource
+    /// and pool allocations are kept. Locks are taken in the same order as
+    /// tracking takes them (active map first), so concurrent tracking cannot
+    /// deadlock; events in flight during the reset may still be counted
+    /// afterwards.
+    pub fn reset(&self) -> TrackingResult<()> {
+        self.reset_with(|_| MemoryStats::default(), false);
+        self.child_links.clear();
+        self.recent_frees.clear();
+        self.take_scope_reports();
+        Ok(())
+    }
+
+    /// Like `reset`, but keep the currently live allocations.
+    ///
+    /// History keeps only the live allocations' entries, cumulative counters
+    /// restart from zero and the peaks restart from the current live usage,
+    /// which becomes the new baseline.
+    pub fn reset_keep_active(&self) -> TrackingResult<()> {
+        self.reset_with(
+            |active| {
+                let active_memory = active.values().map(|a| a.size).sum();
+                let now = Some(u128::from(unix_time_ms()));
+                MemoryStats {
+                    active_allocations: active.len(),
+                    active_memory,
+                    peak_allocations: active.len(),
+                    peak_memory: active_memory,
+                    peak_memory_timestamp: now,
+                    peak_allocations_timestamp: now,
+                    ..Default::default()
+                }
+            },
+            true,
+        );
+        Ok(())
+    }
+
+    fn reset_with(&self, stats_for: impl FnOnce(&AllShards) -> MemoryStats, keep_active: bool) {
+        let mut active = self.active_allocations.lock_all();
+        if !keep_active {
+            active.clear();
+        }
+        let type_totals = TypeTotals::from_active(&active);
+        match self.type_totals.lock() {
+            Ok(mut totals) => *totals = type_totals,
+            Err(poisoned) => *poisoned.into_inner() = type_totals,
+        }
+
+        {
+            let mut history = match self.allocation_history.lock() {
+                Ok(history) => history,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let mut live: Vec<AllocationInfo> = std::mem::take(&mut history.entries)
+                .into_iter()
+                .filter(|entry| keep_active && active.contains_key(&entry.ptr) && entry.is_active())
+                .collect();
+            let capacity = history.capacity;
+            if let Some(capacity) = capacity {
+                live.drain(..live.len().saturating_sub(capacity));
+            }
+            *history = AllocationHistory::from_entries(live, capacity, 0);
+        }
+
+        self.stats.restore(&stats_for(&active));
+        drop(active);
+
+        self.dropped_allocations.store(0, Ordering::Relaxed);
+        self.dropped_deallocations.store(0, Ordering::Relaxed);
+        self.dropped_type_updates.store(0, Ordering::Relaxed);
+        match self.type_samples.lock() {
+            Ok(mut samples) => samples.clear(),
+            Err(poisoned) => poisoned.into_inner().clear(),
+        }
+        match self.type_breakdown_cache.lock() {
+            Ok(mut cache) => *cache = None,
+            Err(poisoned) => *poisoned.into_inner() = None,
+        }
+        match self.last_rate_sample.lock() {
+            Ok(mut last) => *last = None,
+            Err(poisoned) => *poisoned.into_inner() = None,
+        }
+    }
+
+    /// Recompute the active statistics from the active map, correcting drift.
+    ///
+    /// Events dropped on the non-blocking tracking path can leave the active
+    /// counts out of step with the map; this resets them to the map's exact
+    /// values and raises the peaks if the corrected values exceed them. Returns
+    /// the state before correction, whose `allocation_drift`/`memory_drift`
+    /// give the size of the correction. Exact while no tracking calls are in
+    /// flight; concurrent calls may leave a drift of a few in-flight events.
+    pub fn reconcile(&self) -> TrackingResult<ConsistencyReport> {
+        let mut active = self.active_allocations.lock_all();
+        Ok(self.reconcile_locked(&mut active))
+    }
+
+    /// Reconcile automatically every `every_n_ops` tracked allocations and
+    /// deallocations, or never with `None`.
+    ///
+    /// Automatic reconciliation skips a round rather than wait if the active
+    /// map is busy.
+    pub fn set_auto_reconcile(&self, every_n_ops: Option<usize>) {
+        self.ops_since_reconcile.store(0, Ordering::Relaxed);
+        self.auto_reconcile_every
+            .store(every_n_ops.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Drop allocations freed within `window_ms` of being allocated from
+    /// history, or keep every allocation with `None`.
+    ///
+    /// Each coalesced pair is counted in `MemoryStats::coalesced`; the
+    /// allocation and deallocation totals still include it. Matching relies on
+    /// the history entry of every live allocation being retained until it is
+    /// freed, so only the history shrinks, not the memory held while an
+    /// allocation is live. Deallocations skipped because history was busy are
+    /// not coalesced.
+    pub fn set_coalesce_window_ms(&self, window_ms: Option<u64>) {
+        self.coalesce_window_ms
+            .store(window_ms.unwrap_or(COALESCE_DISABLED), Ordering::Relaxed);
+    }
+
+    /// Remember the last `window` freed pointers so that freeing one of them
+    /// again is reported by `suspected_double_frees`, or stop with `None`.
+    ///
+    /// Off by default. Every free of an untracked pointer is counted in
+    /// `MemoryStats::unknown_deallocations` either way; the window only tells
+    /// a free of a just-freed pointer apart from one of a never-tracked
+    /// pointer. Reallocating a pointer removes it from the window. Frees and
+    /// allocations skipped under contention can cause false positives, hence
+    /// "suspected".
+    pub fn set_double_free_window(&self, window: Option<usize>) {
+        self.recent_frees.set_window(window.unwrap_or(0));
+    }
+
+    /// Pointers freed again while still in the double-free window, in
+    /// detection order (see `set_double_free_window`).
+    pub fn suspected_double_frees(&self) -> Vec<usize> {
+        self.recent_frees.suspected()
+    }
+
+    /// Call `callback` when active memory rises above `bytes`.
+    ///
+    /// The callback fires once, on the tracked allocation that takes active
+    /// memory above `bytes`, and again only after usage has dropped back to
+    /// `bytes` or below and then crossed it anew. Any number of thresholds can
+    /// be registered. Callbacks run after the tracker's locks are released, so
+    /// they may allocate or call back into the tracker (e.g. to export a dump).
+    pub fn set_threshold(&self, bytes: usize, callback: Box<dyn Fn(&MemoryStats) + Send + Sync>) {
+        self.thresholds.add(bytes, callback);
+    }
+
+    /// Fail tracking calls that take active memory past `budget` bytes, or
+    /// stop enforcing a budget with `None` (the default).
+    ///
+    /// Once an allocation would take active memory above the budget, the
+    /// allocation-tracking calls return `TrackingError::BudgetExceeded`. The
+    /// allocation is recorded regardless, so the statistics stay consistent;
+    /// the error only reports the overrun. Meant for asserting in tests that a
+    /// code path stays within a memory budget.
+    pub fn set_budget(&self, budget: Option<usize>) {
+        self.budget
+            .store(budget.unwrap_or(NO_BUDGET), Ordering::Relaxed);
+    }
+
+    /// Remove every threshold registered with `set_threshold`.
+    pub fn clear_thresholds(&self) {
+        self.thresholds.clear();
+    }
+
+    fn check_thresholds(&self) {
+        if self.thresholds.is_active() {
+            self.thresholds.check(&self.stats.snapshot());
+        }
+    }
+
+    fn within_coalesce_window(&self, freed: &AllocationInfo) -> bool {
+        let window = self.coalesce_window_ms.load(Ordering::Relaxed);
+        window != COALESCE_DISABLED
+            && freed
+                .lifetime_ms()
+                .is_some_and(|lifetime| lifetime <= u128::from(window))
+    }
+
+    fn maybe_auto_reconcile(&self) {
+        let every = self.auto_reconcile_every.load(Ordering::Relaxed);
+        if every == 0 || self.ops_since_reconcile.fetch_add(1, Ordering::Relaxed) + 1 < every {
+            return;
+        }
+        self.ops_since_reconcile.store(0, Ordering::Relaxed);
+        // Runs on the tracking path, so a busy shard skips this round
+        if let Some(mut active) = self.active_allocations.try_lock_all() {
+            self.reconcile_locked(&mut active);
+        }
+    }
+
+    fn reconcile_locked(&self, active: &mut AllShards) -> ConsistencyReport {
+        let stats = self.stats.snapshot();
+        let actual_active_memory = active.values().map(|a| a.size).sum();
+        self.stats.reset_active(active.len(), actual_active_memory);
+        // The estimates of synthetic entries are now part of the statistics
+        for allocation in active.values_mut().filter(|a| a.size_estimated) {
+            allocation.estimate_counted = true;
+        }
+
+        ConsistencyReport {
+            stats_active_allocations: stats.active_allocations,
+            actual_active_allocations: active.len(),
+            stats_active_memory: stats.active_memory,
+            actual_active_memory,
+        }
+    }
+
+    /// Get all currently active allocations.
+    ///
+    /// Shards are copied one at a time, so tracking on other shards carries
+    /// on meanwhile; allocations made during the call may or may not appear.
+    pub fn get_active_allocations(&self) -> TrackingResult<Vec<AllocationInfo>> {
+        Ok(self.active_allocations.cloned_matching(|_| true))
+    }
+
+    /// Active allocations of at least `min_size` bytes.
+    ///
+    /// Only the matching allocations are copied, so this is much cheaper than
+    /// filtering `get_active_allocations` when most allocations are small.
+    /// Zero-size entries are excluded whenever `min_size` is above 0.
+    pub fn get_active_allocations_filtered(
+        &self,
+        min_size: usize,
+    ) -> TrackingResult<Vec<AllocationInfo>> {
+        Ok(self
+            .active_allocations
+            .cloned_matching(|allocation| allocation.size >= min_size))
+    }
+
+    /// Active allocations older than `min_age`, sorted by size descending.
+    ///
+    /// Allocations made during a warmup window (see `begin_warmup`) and those
+    /// marked with `mark_expected_persistent` are excluded.
+    pub fn detect_leaks(
+        &self,
+        min_age: std::time::Duration,
+    ) -> TrackingResult<Vec<AllocationInfo>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let min_age_ms = min_age.as_millis();
+
+        let mut leaks: Vec<AllocationInfo> = self
+            .get_active_allocations()?
+            .into_iter()
+            .filter(|a| {
+                !a.warmup
+                    && !a.expected_persistent
+                    && now.saturating_sub(a.timestamp_alloc) >= min_age_ms
+            })
+            .collect();
+        leaks.sort_by_key(|a| std::cmp::Reverse(a.size));
+        Ok(leaks)
+    }
+
+    /// `detect_leaks` with totals: the leaked bytes overall and per type.
+    ///
+    /// Allocations without a variable name and synthetic entries (estimated or
+    /// zero size) are still reported, flagged so they can be weighed
+    /// accordingly.
+    pub fn leak_report(&self, min_age: std::time::Duration) -> TrackingResult<LeakReport> {
+        let leaks = self.detect_leaks(min_age)?;
+        let leaked_bytes = leaks.iter().map(|a| a.size).sum();
+        let by_type = aggregate_memory_by_type(&leaks);
+
+        let leaks = leaks
+            .into_iter()
+            .map(|allocation| LeakedAllocation {
+                unnamed: allocation.var_name.is_none(),
+                synthetic: allocation.size_estimated || allocation.size == 0,
+                allocation,
+            })
+            .collect();
+        Ok(LeakReport {
+            leaks,
+            leaked_bytes,
+            by_type,
+        })
+    }
+
+    /// Mark an active allocation as intentionally long-lived.
+    ///
+    /// Caches, interned strings and similar permanent allocations marked this
+    /// way are left out of `detect_leaks` and report leak candidates. Returns
+    /// `TrackingError::InvalidPointer` if nothing is tracked at `ptr`.
+    pub fn mark_expected_persistent(&self, ptr: usize) -> TrackingResult<()> {
+        let mut active = self.active_allocations.lock_all();
+        match active.get_mut(&ptr) {
+            Some(allocation) => {
+                allocation.expected_persistent = true;
+                Ok(())
+            }
+            None => Err(TrackingError::InvalidPointer { ptr }),
+        }
+    }
+
+    /// Add `tag` to the metadata tags of every active allocation matching
+    /// `predicate`, returning how many allocations gained the tag.
+    ///
+    /// The active map stays locked while the predicate runs, so it must not
+    /// call back into the tracker. Allocations that already carry the tag are
+    /// left unchanged and not counted.
+    pub fn annotate_matching(
+        &self,
+        predicate: impl Fn(&AllocationInfo) -> bool,
+        tag: String,
+    ) -> TrackingResult<usize> {
+        let mut active = self.active_allocations.lock_all();
+        let mut modified = 0;
+        for allocation in active.values_mut() {
+            if !allocation.metadata_tags.contains(&tag) && predicate(allocation) {
+                allocation.add_metadata_tag(tag.clone());
+                modified += 1;
+            }
+        }
+        Ok(modified)
+    }
+
+    /// Open a warmup window.
+    ///
+    /// Allocations tracked until `end_warmup` are flagged as warmup allocations
+    /// and excluded from leak detection entirely, so intentionally long-lived
+    /// startup state (caches, configuration) is not reported as leaked.
+    pub fn begin_warmup(&self) {
+        self.warmup.store(true, Ordering::Relaxed);
+    }
+
+    /// Close the warmup window opened by `begin_warmup`.
+    pub fn end_warmup(&self) {
+        self.warmup.store(false, Ordering::Relaxed);
+    }
+
+    /// Start a new allocation generation and return its number.
+    ///
+    /// Allocations are stamped with the generation current when they are
+    /// tracked, starting at 0; see `survival_by_generation`.
+    pub fn advance_generation(&self) -> u32 {
+        self.generation
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(1)
+    }
+
+    /// Per generation, `(generation, created, surviving)`: how many allocations
+    /// were tracked in it and how many of those are still active, in
+    /// generation order.
+    ///
+    /// Created counts come from the allocation history, so allocations dropped
+    /// by the coalesce window are not included.
+    pub fn survival_by_generation(&self) -> Vec<(u32, usize, usize)> {
+        let mut generations: BTreeMap<u32, (usize, usize)> = BTreeMap::new();
+        for allocation in self.get_allocation_history().unwrap_or_default() {
+            generations.entry(allocation.generation).or_default().0 += 1;
+        }
+        for allocation in self.get_active_allocations().unwrap_or_default() {
+            let (created, surviving) = generations.entry(allocation.generation).or_default();
+            *surviving += 1;
+            // Keep survivors within the created count if history missed an entry
+            *created = (*created).max(*surviving);
+        }
+
+        generations
+            .into_iter()
+            .map(|(generation, (created, surviving))| (generation, created, surviving))
+            .collect()
+    }
+
+    /// Look up an allocation, active or freed, by its stable id (`AllocationInfo::alloc_id`).
+    pub fn get_allocation_by_id(&self, alloc_id: u64) -> TrackingResult<Option<AllocationInfo>> {
+        let _untracked = UntrackedScope::enter();
+        let history = match self.allocation_history.lock() {
+            Ok(history) => history,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        // History is appended in id order except for rare concurrent races
+        let found = match history.entries.binary_search_by_key(&alloc_id, |a| a.seq) {
+            Ok(index) => Some(history.entries[index].clone()),
+            Err(_) => history.entries.iter().find(|a| a.seq == alloc_id).cloned(),
+        };
+        drop(history);
+        if found.is_some() {
+            return Ok(found);
+        }
+
+        // Entries the history skipped under contention are only in the active map
+        Ok(self
+            .get_active_allocations()?
+            .into_iter()
+            .find(|a| a.seq == alloc_id))
+    }
+
+    /// Look up the allocation currently active at `ptr`.
+    pub fn get_allocation_by_ptr(&self, ptr: usize) -> TrackingResult<Option<AllocationInfo>> {
+        match self.active_allocations.shard(ptr).lock() {
+            Ok(active) => Ok(active.get(&ptr).cloned()),
+            Err(poisoned) => Ok(poisoned.into_inner().get(&ptr).cloned()),
+        }
+    }
+
+    /// Active allocations whose variable name is exactly `name`, oldest first.
+    ///
+    /// A name can map to several allocations, e.g. when a variable is
+    /// reassociated after a reallocation.
+    pub fn find_by_var_name(&self, name: &str) -> TrackingResult<Vec<AllocationInfo>> {
+        self.find_by_var(|var_name| var_name == name)
+    }
+
+    /// Active allocations whose variable name starts with `prefix`, oldest
+    /// first; e.g. `buffer_` matches `buffer_0` and `buffer_1`.
+    pub fn find_by_var_prefix(&self, prefix: &str) -> TrackingResult<Vec<AllocationInfo>> {
+        self.find_by_var(|var_name| var_name.starts_with(prefix))
+    }
+
+    fn find_by_var(&self, matches: impl Fn(&str) -> bool) -> TrackingResult<Vec<AllocationInfo>> {
+        let mut found: Vec<AllocationInfo> = self
+            .active_allocations
+            .lock_all()
+            .values()
+            .filter(|a| a.var_name.as_deref().is_some_and(&matches))
+            .cloned()
+            .collect();
+        found.sort_by_key(|a| a.seq);
+        Ok(found)
+    }
+
+    /// Record that the allocation at `parent` owns the allocation at `child`.
+    ///
+    /// Links feed `retained_size`. A freed parent's links are dropped; links to
+    /// a freed child stay but no longer contribute any bytes.
+    pub fn link_child(&self, parent: usize, child: usize) -> TrackingResult<()> {
+        self.child_links.link(parent, child);
+        Ok(())
+    }
+
+    /// Track an allocation made from an arena or pool identified by `pool_id`.
+    ///
+    /// Pool allocations are kept on a per-pool stack, apart from the heap
+    /// allocations and statistics, so `track_pool_deallocation` can check that
+    /// they are freed in reverse allocation order.
+    pub fn track_pool_allocation(
+        &self,
+        pool_id: u64,
+        ptr: usize,
+        size: usize,
+    ) -> TrackingResult<()> {
+        self.pools.push(pool_id, ptr, size);
+        Ok(())
+    }
+
+    /// Track the release of a pool allocation.
+    ///
+    /// Freeing anything but the pool's most recent live allocation is a
+    /// violation: it is counted in `pool_violations` and emitted as a
+    /// `TrackEvent::PoolViolation` to event hooks and the event log. Returns
+    /// `TrackingError::InvalidPointer` if `ptr` is not live in the pool.
+    pub fn track_pool_deallocation(&self, pool_id: u64, ptr: usize) -> TrackingResult<()> {
+        if let Some(expected) = self.pools.pop(pool_id, ptr)? {
+            tracing::debug!(
+                "Pool {} freed {:x} out of order; expected {:x}",
+                pool_id,
+                ptr,
+                expected
+            );
+            self.event_hooks.emit(&TrackEvent::PoolViolation {
+                pool_id,
+                ptr,
+                expected,
+            });
+        }
+        Ok(())
+    }
+
+    /// Number of out-of-order frees seen in a pool (see `track_pool_deallocation`).
+    pub fn pool_violations(&self, pool_id: u64) -> usize {
+        self.pools.violations(pool_id)
+    }
+
+    /// Bytes kept alive by the allocation at `ptr`: its own size plus every
+    /// active allocation reachable from it through `link_child` links.
+    ///
+    /// A child shared by several parents counts toward each of them.
+    pub fn retained_size(&self, ptr: usize) -> TrackingResult<usize> {
+        let reachable = self.child_links.reachable_from(ptr);
+        let active = self.active_allocations.lock_all();
+        Ok(reachable
+            .iter()
+            .filter_map(|p| active.get(p))
+            .map(|allocation| allocation.size)
+            .sum())
+    }
+
+    /// The active allocation whose `retained_size` is largest, with that size.
+    ///
+    /// This is the single allocation whose release would reclaim the most
+    /// memory. Without `link_child` links every retained size equals the
+    /// shallow size, so this is simply the largest allocation. Ties go to the
+    /// lowest address. Returns `None` when nothing is active.
+    pub fn largest_retained_allocation(&self) -> TrackingResult<Option<(AllocationInfo, usize)>> {
+        // Resolve the link graph before locking the active map, like `retained_size`
+        let reachable: HashMap<usize, HashSet<usize>> = self
+            .child_links
+            .parents()
+            .into_iter()
+            .map(|parent| (parent, self.child_links.reachable_from(parent)))
+            .collect();
+
+        let active = self.active_allocations.lock_all();
+        let largest = active
+            .values()
+            .map(|allocation| {
+                let retained = match reachable.get(&allocation.ptr) {
+                    Some(reachable) => reachable
+                        .iter()
+                        .filter_map(|p| active.get(p))
+                        .map(|child| child.size)
+                        .sum(),
+                    None => allocation.size,
+                };
+                (allocation, retained)
+            })
+            .max_by(|(a, a_retained), (b, b_retained)| {
+                a_retained.cmp(b_retained).then_with(|| b.ptr.cmp(&a.ptr))
+            })
+            .map(|(allocation, retained)| (allocation.clone(), retained));
+        Ok(largest)
+    }
+
+    /// Measure how much memory a value holds, including its nested allocations.
+    ///
+    /// The shallow size is `size_of_val(value)`. If the value itself lives in a
+    /// tracked allocation (e.g. behind a `Box`), the retained size is that
+    /// allocation's `retained_size`; otherwise it is the shallow size plus
+    /// everything reachable from the value's address through child links
+    /// (see `link_child`). Without links, both sizes are equal.
+    pub fn measure_value<T: ?Sized>(
+        &self,
+        value: &T,
+        var_name: &str,
+    ) -> TrackingResult<ValueMeasurement> {
+        let shallow_size = std::mem::size_of_val(value);
+        let address = value as *const T as *const u8 as usize;
+
+        let heap_resident = match self.active_allocations.shard(address).lock() {
+            Ok(active) => active.contains_key(&address),
+            Err(poisoned) => poisoned.into_inner().contains_key(&address),
+        };
+        let retained_size = if heap_resident {
+            self.retained_size(address)?
+        } else {
+            // The value's own address is not an allocation, so only its children count
+            shallow_size + self.retained_size(address)?
+        };
+
+        Ok(ValueMeasurement {
+            var_name: var_name.to_string(),
+            shallow_size,
+            retained_size,
+        })
+    }
+
+    /// Split active bytes into `(persistent, transient)` by allocation age.
+    ///
+    /// An allocation older than `threshold_ms` at `now` (milliseconds since
+    /// UNIX_EPOCH) is persistent: the baseline to provision for. Younger
+    /// allocations are transient, i.e. peak headroom.
+    pub fn persistent_vs_transient(
+        &self,
+        threshold_ms: u64,
+        now: u64,
+    ) -> TrackingResult<(usize, usize)> {
+        let active = self.active_allocations.lock_all();
+
+        Ok(active
+            .values()
+            .fold((0, 0), |(persistent, transient), allocation| {
+                let age = (now as u128).saturating_sub(allocation.timestamp_alloc);
+                if age > threshold_ms as u128 {
+                    (persistent + allocation.size, transient)
+                } else {
+                    (persistent, transient + allocation.size)
+                }
+            }))
+    }
+
+    /// Get the `n` oldest active allocations paired with their age in milliseconds.
+    ///
+    /// `now` is the reference time in milliseconds since UNIX_EPOCH (the same clock
+    /// as `timestamp_alloc`). Results are sorted oldest-first; unlike a threshold
+    /// based leak check, the top `n` are always returned regardless of absolute age.
+    pub fn oldest_live_allocations(
+        &self,
+        n: usize,
+        now: u64,
+    ) -> TrackingResult<Vec<(AllocationInfo, u64)>> {
+        let mut active = self.get_active_allocations()?;
+        active.sort_by_key(|a| (a.timestamp_alloc, a.seq));
+        active.truncate(n);
+
+        Ok(active
+            .into_iter()
+            .map(|allocation| {
+                let age = (now as u128).saturating_sub(allocation.timestamp_alloc) as u64;
+                (allocation, age)
+            })
+            .collect())
+    }
+
+    /// The `n` largest active allocations, largest first.
+    ///
+    /// Equal sizes are ordered oldest first (by `timestamp_alloc`, then
+    /// allocation order). A bounded heap of `n` entries is kept while scanning
+    /// the active map, so only the results are cloned and nothing is fully sorted.
+    pub fn top_allocations(&self, n: usize) -> TrackingResult<Vec<AllocationInfo>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let select = |active: &AllShards| -> Vec<AllocationInfo> {
+            // Min-heap on the ranking key, so the weakest candidate is evicted first
+            let mut heap = std::collections::BinaryHeap::with_capacity(n + 1);
+            for allocation in active.values() {
+                let key = (
+                    allocation.size,
+                    std::cmp::Reverse(allocation.timestamp_alloc),
+                    std::cmp::Reverse(allocation.seq),
+                    allocation.ptr,
+                );
+                heap.push(std::cmp::Reverse(key));
+                if heap.len() > n {
+                    heap.pop();
+                }
+            }
+            // Ascending order of `Reverse(key)` is descending order of `key`
+            heap.into_sorted_vec()
+                .into_iter()
+                .filter_map(|std::cmp::Reverse((_, _, _, ptr))| active.get(&ptr).cloned())
+                .collect()
+        };
+        Ok(select(&self.active_allocations.lock_all()))
+    }
+
+    /// The fewest active allocations that together hold `fraction` of active memory.
+    ///
+    /// Allocations are taken largest first until their cumulative size reaches
+    /// `fraction` (0.0..=1.0, clamped) of the active total, so `0.8` answers
+    /// "which allocations account for 80% of memory". Returns an empty list
+    /// for a zero fraction or when no memory is active.
+    pub fn top_allocations_by_coverage(
+        &self,
+        fraction: f64,
+    ) -> TrackingResult<Vec<AllocationInfo>> {
+        let mut active = self.get_active_allocations()?;
+        let total: usize = active.iter().map(|a| a.size).sum();
+        let fraction = if fraction.is_nan() {
+            0.0
+        } else {
+            fraction.clamp(0.0, 1.0)
+        };
+        let target = total as f64 * fraction;
+        if total == 0 || target <= 0.0 {
+            return Ok(Vec::new());
+        }
+
+        active.sort_by_key(|a| (std::cmp::Reverse(a.size), a.seq));
+        let mut covered = 0usize;
+        let prefix_len = active
+            .iter()
+            .position(|a| {
+                covered += a.size;
+                covered as f64 >= target
+            })
+            .map_or(active.len(), |index| index + 1);
+        active.truncate(prefix_len);
+        Ok(active)
+    }
+
+    /// Get the complete allocation history, in allocation (`seq`) order.
+    pub fn get_allocation_history(&self) -> TrackingResult<Vec<AllocationInfo>> {
+        let _untracked = UntrackedScope::enter();
+        let mut entries = match self.allocation_history.lock() {
+            Ok(history) => Vec::from(history.entries.clone()),
+            Err(poisoned) => {
+                // Handle poisoned lock by recovering the data
+                let history = poisoned.into_inner();
+                Vec::from(history.entries.clone())
+            }
+        };
+        // Concurrent trackers can append slightly out of order; the stable sort is
+        // near-linear on the almost-sorted history
+        entries.sort_by_key(|a| a.seq);
+        Ok(entries)
+    }
+
+    /// Sorted, deduplicated type names across active allocations and history.
+    pub fn distinct_type_names(&self) -> TrackingResult<Vec<String>> {
+        self.distinct_names(|allocation| allocation.type_name.as_deref())
+    }
+
+    /// Sorted, deduplicated variable names across active allocations and history.
+    pub fn distinct_var_names(&self) -> TrackingResult<Vec<String>> {
+        self.distinct_names(|allocation| allocation.var_name.as_deref())
+    }
+
+    fn distinct_names<F>(&self, name: F) -> TrackingResult<Vec<String>>
+    where
+        F: Fn(&AllocationInfo) -> Option<&str>,
+    {
+        let active = self.get_active_allocations()?;
+        let history = self.get_allocation_history()?;
+
+        let names: BTreeSet<&str> = active.iter().chain(&history).filter_map(name).collect();
+        Ok(names.into_iter().map(str::to_string).collect())
+    }
+
+    /// Count completed (freed) allocations in the history, grouped by type name.
+    pub fn get_deallocations_by_type(&self) -> TrackingResult<HashMap<String, usize>> {
+        let history = self.get_allocation_history()?;
+        let mut deallocations: HashMap<String, usize> = HashMap::new();
+
+        for allocation in history.iter().filter(|a| !a.is_active()) {
+            let type_name = allocation
+                .type_name
+                .clone()
+                .unwrap_or_else(|| "Unknown".to_string());
+            *deallocations.entry(type_name).or_insert(0) += 1;
+        }
+
+        Ok(deallocations)
+    }
+
+    /// Per-type `(allocated, deallocated, net)` counts from the allocation history.
+    ///
+    /// A type whose net count keeps growing across calls is a likely leak.
+    pub fn alloc_dealloc_balance_by_type(
+        &self,
+    ) -> TrackingResult<HashMap<String, (usize, usize, isize)>> {
+        let history = self.get_allocation_history()?;
+        let mut balance: HashMap<String, (usize, usize, isize)> = HashMap::new();
+
+        for allocation in &history {
+            let type_name = allocation
+                .type_name
+                .clone()
+                .unwrap_or_else(|| "Unknown".to_string());
+            let (allocated, deallocated, net) = balance.entry(type_name).or_insert((0, 0, 0));
+            *allocated += 1;
+            if allocation.is_active() {
+                *net += 1;
+            } else {
+                *deallocated += 1;
+            }
+        }
+
+        Ok(balance)
+    }
+
+    /// Reconstruct live heap bytes over time from the allocation history.
+    ///
+    /// Returns one `(timestamp_ms, live_bytes)` point per allocation or
+    /// deallocation, in time order. Deallocations carry no sequence number, so
+    /// within the same millisecond allocations are placed first (by `seq`),
+    /// then deallocations. Events the history skipped under contention are
+    /// missing, so this can drift from `get_stats` on heavily contended trackers.
+    pub fn memory_timeline(&self) -> TrackingResult<Vec<(u128, usize)>> {
+        let history = self.get_allocation_history()?;
+
+        // (time, is_dealloc, seq, size)
+        let mut events: Vec<(u128, bool, u64, usize)> = Vec::with_capacity(history.len() * 2);
+        for allocation in &history {
+            events.push((
+                allocation.timestamp_alloc,
+                false,
+                allocation.seq,
+                allocation.size,
+            ));
+            if let Some(dealloc) = allocation.timestamp_dealloc {
+                events.push((dealloc, true, allocation.seq, allocation.size));
+            }
+        }
+        events.sort_unstable();
+
+        let mut live = 0usize;
+        Ok(events
+            .into_iter()
+            .map(|(timestamp, is_dealloc, _, size)| {
+                live = if is_dealloc {
+                    live.saturating_sub(size)
+                } else {
+                    live.saturating_add(size)
+                };
+                (timestamp, live)
+            })
+            .collect())
+    }
+
+    /// Find bursts of allocations in the allocation history.
+    ///
+    /// A window of `window_ms` starting at any allocation is a burst window
+    /// when it holds more than `threshold` allocations. Overlapping burst
+    /// windows are merged into one `BurstEvent`, so each sustained spike is
+    /// reported once with its full time range and allocation count. Bursts
+    /// stress the allocator even when memory returns to its baseline.
+    pub fn allocation_burst_detector(
+        &self,
+        window_ms: u64,
+        threshold: u64,
+    ) -> TrackingResult<Vec<BurstEvent>> {
+        let mut timestamps: Vec<u128> = self
+            .get_allocation_history()?
+            .iter()
+            .map(|a| a.timestamp_alloc)
+            .collect();
+        timestamps.sort_unstable();
+        let window = u128::from(window_ms.max(1));
+
+        // (first index, one past the last index) of each merged burst
+        let mut bursts: Vec<(usize, usize)> = Vec::new();
+        let mut end = 0;
+        for (start, &start_time) in timestamps.iter().enumerate() {
+            end = end.max(start);
+            while end < timestamps.len() && timestamps[end] < start_time + window {
+                end += 1;
+            }
+            if (end - start) as u64 <= threshold {
+                continue;
+            }
+            match bursts.last_mut() {
+                Some((_, last_end)) if start < *last_end => *last_end = end,
+                _ => bursts.push((start, end)),
+            }
+        }
+
+        Ok(bursts
+            .into_iter()
+            .map(|(start, end)| BurstEvent {
+                start_ms: timestamps[start],
+                end_ms: timestamps[end - 1],
+                allocation_count: end - start,
+            })
+            .collect())
+    }
+
+    /// Export `memory_timeline` delta-encoded to a compact JSON file.
+    ///
+    /// The timeline is stored as a base point and run-length-encoded deltas
+    /// (see `DeltaTimeline`), which shrinks long, mostly stable sessions by
+    /// orders of magnitude without losing any point. Read it back with
+    /// `timeline_delta::decode_timeline_delta`.
+    pub fn export_timeline_delta<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        let timeline = self.memory_timeline()?;
+        crate::timeline_delta::write_timeline_delta(&timeline, path.as_ref())
+    }
+
+    /// Change in the heap growth rate between the last two windows of `window_ms`, in bytes/sec².
+    ///
+    /// Growth rates are taken from `memory_timeline` over `[now - 2w, now - w]`
+    /// and `[now - w, now]`; the result is their difference divided by the
+    /// window. A positive value means growth is speeding up, e.g. a worsening
+    /// leak. A second derivative amplifies noise: a single large allocation or
+    /// free near a window edge swings it sharply, so prefer windows well above
+    /// the typical allocation interval and smooth successive readings (e.g. with
+    /// a moving average) before alerting. Returns 0.0 for a zero window.
+    pub fn growth_acceleration(&self, window_ms: u64) -> TrackingResult<f64> {
+        if window_ms == 0 {
+            return Ok(0.0);
+        }
+
+        let timeline = self.memory_timeline()?;
+        let live_at = |time: u128| {
+            let index = timeline.partition_point(|(timestamp, _)| *timestamp <= time);
+            index.checked_sub(1).map_or(0, |i| timeline[i].1) as f64
+        };
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let window = window_ms as u128;
+        let start = live_at(now.saturating_sub(2 * window));
+        let middle = live_at(now.saturating_sub(window));
+        let end = live_at(now);
+
+        let window_secs = window_ms as f64 / 1000.0;
+        let earlier_rate = (middle - start) / window_secs;
+        let later_rate = (end - middle) / window_secs;
+        Ok((later_rate - earlier_rate) / window_secs)
+    }
+
+    /// Histogram of lifetimes of completed (freed) allocations.
+    ///
+    /// `buckets_ms` are inclusive upper bounds: each freed allocation is counted
+    /// in the first bucket whose bound is at least its lifetime. A final
+    /// `u64::MAX` bucket collects lifetimes beyond the largest bound. An empty
+    /// slice selects exponential buckets of 1 ms, 10 ms, 100 ms, 1 s, 10 s and 100 s.
+    pub fn lifetime_histogram(&self, buckets_ms: &[u64]) -> TrackingResult<Vec<(u64, usize)>> {
+        let mut bounds: Vec<u64> = if buckets_ms.is_empty() {
+            DEFAULT_LIFETIME_BUCKETS_MS.to_vec()
+        } else {
+            buckets_ms.to_vec()
+        };
+        bounds.sort_unstable();
+        bounds.dedup();
+        if bounds.last() != Some(&u64::MAX) {
+            bounds.push(u64::MAX);
+        }
+
+        let mut histogram: Vec<(u64, usize)> = bounds.iter().map(|&bound| (bound, 0)).collect();
+        for lifetime in self
+            .get_allocation_history()?
+            .iter()
+            .filter_map(AllocationInfo::lifetime_ms)
+        {
+            let lifetime = u64::try_from(lifetime).unwrap_or(u64::MAX);
+            let bucket = bounds.partition_point(|&bound| bound < lifetime);
+            histogram[bucket].1 += 1;
+        }
+
+        Ok(histogram)
+    }
+
+    /// Find repeated allocate-then-quickly-free cycles of the same type and size.
+    ///
+    /// Freed allocations from the history that lived at most 1 ms are grouped by
+    /// `(type, size)`; groups with at least 10 such cycles are reported, sorted by
+    /// churned bytes descending. These are candidates for buffer reuse or pooling.
+    pub fn ping_pong_report(&self) -> Vec<PingPongSite> {
+        let history = self.get_allocation_history().unwrap_or_default();
+        let mut sites: HashMap<(String, usize), usize> = HashMap::new();
+
+        for allocation in &history {
+            let short_lived = allocation
+                .lifetime_ms()
+                .is_some_and(|lifetime| lifetime <= PING_PONG_MAX_LIFETIME_MS);
+            if short_lived {
+                let type_name = allocation
+                    .type_name
+                    .clone()
+                    .unwrap_or_else(|| "Unknown".to_string());
+                *sites.entry((type_name, allocation.size)).or_insert(0) += 1;
+            }
+        }
+
+        let mut report: Vec<PingPongSite> = sites
+            .into_iter()
+            .filter(|(_, cycles)| *cycles >= PING_PONG_MIN_CYCLES)
+            .map(|((type_name, size), cycles)| PingPongSite {
+                type_name,
+                size,
+                cycles,
+                churn_bytes: size.saturating_mul(cycles),
+            })
+            .collect();
+        report.sort_by(|a, b| {
+            b.churn_bytes
+                .cmp(&a.churn_bytes)
+                .then_with(|| a.type_name.cmp(&b.type_name))
+        });
+        report
+    }
+
+    /// Get memory usage grouped by type.
+    pub fn get_memory_by_type(&self) -> TrackingResult<Vec<TypeMemoryUsage>> {
+        // Copy only the type and size out, so the lock is held as briefly as possible
+        let type_sizes: Vec<(Option<String>, usize)> = {
+            let active = self.active_allocations.lock_all();
+            active
+                .values()
+                .map(|allocation| (allocation.type_name.clone(), allocation.size))
+                .collect()
+        };
+
+        Ok(aggregate_memory_by_type(&type_sizes))
+    }
+
+    /// Lifetime statistics per type from the allocation history, longest
+    /// median lifetime first.
+    ///
+    /// Only freed allocations contribute lifetimes; those still active are
+    /// counted in `still_alive`. Allocations missing from the history (see
+    /// `history_evicted_count` and `set_coalesce_window_ms`) are not included.
+    pub fn lifetime_stats_by_type(&self) -> TrackingResult<Vec<TypeLifetimeStats>> {
+        // Copy only the type and lifetime out, so the lock is held as briefly as possible
+        let lifetimes: Vec<(Option<String>, Option<u128>)> = {
+            let _untracked = UntrackedScope::enter();
+            let history = match self.allocation_history.lock() {
+                Ok(history) => history,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            history
+                .entries
+                .iter()
+                .map(|allocation| (allocation.type_name.clone(), allocation.lifetime_ms()))
+                .collect()
+        };
+
+        let mut by_type: HashMap<String, (Vec<u128>, usize)> = HashMap::new();
+        for (type_name, lifetime) in lifetimes {
+            let type_name = type_name.unwrap_or_else(|| "Unknown".to_string());
+            let (completed, still_alive) = by_type.entry(type_name).or_default();
+            match lifetime {
+                Some(lifetime) => completed.push(lifetime),
+                None => *still_alive += 1,
+            }
+        }
+
+        let mut stats: Vec<TypeLifetimeStats> = by_type
+            .into_iter()
+            .map(|(type_name, (mut completed, still_alive))| {
+                completed.sort_unstable();
+                let count = completed.len();
+                let median_lifetime_ms = match count {
+                    0 => 0.0,
+                    _ if count % 2 == 1 => completed[count / 2] as f64,
+                    _ => (completed[count / 2 - 1] + completed[count / 2]) as f64 / 2.0,
+                };
+                TypeLifetimeStats {
+                    type_name,
+                    completed_count: count,
+                    still_alive,
+                    min_lifetime_ms: completed.first().copied().unwrap_or(0),
+                    max_lifetime_ms: completed.last().copied().unwrap_or(0),
+                    mean_lifetime_ms: if count == 0 {
+                        0.0
+                    } else {
+                        completed.iter().sum::<u128>() as f64 / count as f64
+                    },
+                    median_lifetime_ms,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.median_lifetime_ms
+                .total_cmp(&a.median_lifetime_ms)
+                .then_with(|| a.type_name.cmp(&b.type_name))
+        });
+        Ok(stats)
+    }
+
+    /// Like `get_memory_by_type`, but reuses the last result while it is
+    /// younger than `max_age`.
+    ///
+    /// Bounds the cost of frequent polling (e.g. a dashboard refreshing every
+    /// second) at the price of up to `max_age` of staleness. Concurrent callers
+    /// finding the cache stale wait for a single recomputation.
+    pub fn get_memory_by_type_cached(
+        &self,
+        max_age: std::time::Duration,
+    ) -> TrackingResult<Vec<TypeMemoryUsage>> {
+        let mut cache = match self.type_breakdown_cache.lock() {
+            Ok(cache) => cache,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some((computed_at, breakdown)) = cache.as_ref() {
+            if computed_at.elapsed() < max_age {
+                return Ok(breakdown.clone());
+            }
+        }
+
+        let breakdown = self.get_memory_by_type()?;
+        *cache = Some((std::time::Instant::now(), breakdown.clone()));
+        Ok(breakdown)
+    }
+
+    /// Record the current active bytes of every type for `monotonic_growth_types`.
+    ///
+    /// Call this periodically (e.g. once per request batch or timer tick); each
+    /// call keeps one small per-type map.
+    pub fn record_type_sample(&self) -> TrackingResult<()> {
+        let sample: TypeSample = self
+            .get_memory_by_type()?
+            .into_iter()
+            .map(|usage| (usage.type_name, usage.total_size))
+            .collect();
+        let mut samples = match self.type_samples.lock() {
+            Ok(samples) => samples,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        samples.push(sample);
+        Ok(())
+    }
+
+    /// Type names whose active bytes never decreased across the samples taken
+    /// by `record_type_sample` and grew overall, sorted by name.
+    ///
+    /// A type that only ever grows is a strong leak candidate. At least two
+    /// samples are needed; with fewer, nothing is returned. A type missing
+    /// from a sample counts as 0 bytes there.
+    pub fn monotonic_growth_types(&self) -> Vec<String> {
+        let samples = match self.type_samples.lock() {
+            Ok(samples) => samples,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if samples.len() < 2 {
+            return Vec::new();
+        }
+
+        let type_names: BTreeSet<&String> = samples.iter().flat_map(|s| s.keys()).collect();
+        type_names
+            .into_iter()
+            .filter(|type_name| {
+                let totals: Vec<usize> = samples
+                    .iter()
+                    .map(|sample| sample.get(*type_name).copied().unwrap_or(0))
+                    .collect();
+                totals.windows(2).all(|pair| pair[0] <= pair[1]) && totals.first() < totals.last()
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Active allocations grouped by call site: the innermost frame of their
+    /// backtrace outside the tracker, sorted by total bytes descending.
+    ///
+    /// Only allocations tracked while backtraces were enabled have a call
+    /// site; the rest are grouped under "Unknown".
+    pub fn get_allocations_by_callsite(&self) -> Vec<(String, Vec<AllocationInfo>)> {
+        let mut by_callsite: HashMap<String, Vec<AllocationInfo>> = HashMap::new();
+        for allocation in self.get_active_allocations().unwrap_or_default() {
+            let callsite = allocation
+                .backtrace
+                .as_ref()
+                .and_then(|frames| frames.first())
+                .cloned()
+                .unwrap_or_else(|| "Unknown".to_string());
+            by_callsite.entry(callsite).or_default().push(allocation);
+        }
+
+        let mut result: Vec<(String, Vec<AllocationInfo>)> = by_callsite.into_iter().collect();
+        for (_, allocations) in &mut result {
+            allocations.sort_by_key(|a| a.seq);
+        }
+        result.sort_by_cached_key(|(callsite, allocations)| {
+            let total: usize = allocations.iter().map(|a| a.size).sum();
+            (std::cmp::Reverse(total), callsite.clone())
+        });
+        result
+    }
+
+    /// Get active memory grouped by the crate that allocated it, sorted by total size descending.
+    ///
+    /// Allocations without attribution (see `track_crate!`) are grouped under "Unknown".
+    pub fn get_memory_by_crate(&self) -> TrackingResult<Vec<CrateMemoryUsage>> {
+        let mut by_crate: HashMap<String, (usize, usize)> = HashMap::new();
+        for allocation in self.get_active_allocations()? {
+            let crate_name = allocation
+                .source_crate
+                .unwrap_or_else(|| "Unknown".to_string());
+            let (total_size, count) = by_crate.entry(crate_name).or_insert((0, 0));
+            *total_size = total_size.saturating_add(allocation.size);
+            *count += 1;
+        }
+
+        let mut result: Vec<CrateMemoryUsage> = by_crate
+            .into_iter()
+            .map(
+                |(crate_name, (total_size, allocation_count))| CrateMemoryUsage {
+                    crate_name,
+                    total_size,
+                    allocation_count,
+                },
+            )
+            .collect();
+        result.sort_by(|a, b| {
+            b.total_size
+                .cmp(&a.total_size)
+                .then_with(|| a.crate_name.cmp(&b.crate_name))
+        });
+        Ok(result)
+    }
+
+    /// Get active memory grouped by the `tracing` span current at allocation
+    /// time, sorted by total size descending.
+    ///
+    /// Allocations made outside any span are grouped under `span_id: None`.
+    #[cfg(feature = "tracing")]
+    pub fn get_memory_by_span(&self) -> TrackingResult<Vec<crate::types::SpanMemoryUsage>> {
+        let mut by_span: HashMap<Option<u64>, (usize, usize)> = HashMap::new();
+        for allocation in self.get_active_allocations()? {
+            let (total_size, count) = by_span.entry(allocation.span_id).or_insert((0, 0));
+            *total_size = total_size.saturating_add(allocation.size);
+            *count += 1;
+        }
+
+        let mut result: Vec<crate::types::SpanMemoryUsage> = by_span
+            .into_iter()
+            .map(
+                |(span_id, (total_size, allocation_count))| crate::types::SpanMemoryUsage {
+                    span_id,
+                    total_size,
+                    allocation_count,
+                },
+            )
+            .collect();
+        result.sort_by(|a, b| {
+            b.total_size
+                .cmp(&a.total_size)
+                .then_with(|| a.span_id.cmp(&b.span_id))
+        });
+        Ok(result)
+    }
+
+    /// Get active memory grouped by the thread that allocated it, sorted by
+    /// total size descending.
+    pub fn get_memory_by_thread(&self) -> TrackingResult<Vec<ThreadMemoryUsage>> {
+        let mut by_thread: HashMap<String, ThreadMemoryUsage> = HashMap::new();
+        for allocation in self.get_active_allocations()? {
+            let usage = by_thread
+                .entry(allocation.thread_id.clone())
+                .or_insert_with(|| ThreadMemoryUsage {
+                    thread_id: allocation.thread_id,
+                    thread_name: None,
+                    total_size: 0,
+                    allocation_count: 0,
+                });
+            if usage.thread_name.is_none() {
+                usage.thread_name = allocation.thread_name;
+            }
+            usage.total_size = usage.total_size.saturating_add(allocation.size);
+            usage.allocation_count += 1;
+        }
+
+        let mut result: Vec<ThreadMemoryUsage> = by_thread.into_values().collect();
+        result.sort_by(|a, b| {
+            b.total_size
+                .cmp(&a.total_size)
+                .then_with(|| a.thread_id.cmp(&b.thread_id))
+        });
+        Ok(result)
+    }
+
+    /// Render a plain-text report for logs or CI output.
+    ///
+    /// `ReportOptions` selects the sections and their depth, so one entry point
+    /// serves both a terse CI gate (health and leaks only) and a full developer
+    /// report (`ReportOptions::default()`).
+    pub fn text_report(&self, options: &ReportOptions) -> TrackingResult<String> {
+        crate::report::render_text_report(self, options)
+    }
+
+    /// Freeze the current tracker state into an immutable analyzed report.
+    ///
+    /// Active allocations and statistics are captured together under the active
+    /// map lock; all analysis then runs on the captured copy, so the report
+    /// can be queried and passed around without touching the live tracker.
+    pub fn finalize(&self) -> AnalyzedReport {
+        let (active_allocations, stats) = {
+            let active = self.active_allocations.lock_all();
+            // Read the counters while holding the map lock so no new tracking
+            // call can slip in between the two captures
+            let stats = self.stats.snapshot();
+            (active.values().cloned().collect::<Vec<_>>(), stats)
+        };
+
+        let memory_by_type = aggregate_memory_by_type(&active_allocations);
+        let enhanced_types =
+            crate::export_enhanced::enhance_type_information(&memory_by_type, &active_allocations);
+        let hierarchy = build_hierarchical_json_structure(
+            &enhanced_types,
+            &active_allocations,
+            &stats,
+            self.get_metadata(),
+        );
+
+        AnalyzedReport::new(stats, memory_by_type, hierarchy, &active_allocations)
+    }
+
+    /// Group active allocations into power-of-two size classes, as allocators do.
+    ///
+    /// Each allocation is assigned to the smallest power of two that holds it.
+    /// Returns `(bucket_size, count, total_bytes)` sorted by bucket size;
+    /// `bucket_size * count - total_bytes` is the internal fragmentation that
+    /// rounding to the class would cause.
+    pub fn group_by_power_of_two(&self) -> TrackingResult<Vec<(usize, usize, usize)>> {
+        let active = self.get_active_allocations()?;
+        let mut buckets: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+
+        for allocation in &active {
+            let bucket = allocation
+                .size
+                .checked_next_power_of_two()
+                .unwrap_or(usize::MAX);
+            let (count, total_bytes) = buckets.entry(bucket).or_insert((0, 0));
+            *count += 1;
+            *total_bytes += allocation.size;
+        }
+
+        Ok(buckets
+            .into_iter()
+            .map(|(bucket, (count, total_bytes))| (bucket, count, total_bytes))
+            .collect())
+    }
+
+    /// Summarize how densely the active allocations fill their address range.
+    ///
+    /// `density` compares the active bytes with the span from the lowest to
+    /// the highest active address, `utilization` compares them with every byte
+    /// allocated so far. Both are heuristics, meant as a trend line across runs
+    /// rather than an absolute measure; size classes are the power-of-two
+    /// classes of `group_by_power_of_two`.
+    pub fn fragmentation_report(&self) -> TrackingResult<FragmentationReport> {
+        let (active_allocations, active_bytes, lowest, highest_end, size_classes) = {
+            let active = self.active_allocations.lock_all();
+            let mut size_classes = BTreeSet::new();
+            let mut lowest = usize::MAX;
+            let mut highest_end = 0;
+            let mut active_bytes = 0usize;
+            for allocation in active.values() {
+                lowest = lowest.min(allocation.ptr);
+                highest_end = highest_end.max(allocation.ptr.saturating_add(allocation.size));
+                active_bytes = active_bytes.saturating_add(allocation.size);
+                size_classes.insert(
+                    allocation
+                        .size
+                        .checked_next_power_of_two()
+                        .unwrap_or(usize::MAX),
+                );
             }
+            (
+                active.len(),
+                active_bytes,
+                lowest,
+                highest_end,
+                size_classes.len(),
+            )
+        };
+        let stats = self.get_stats()?;
+
+        if active_allocations == 0 {
+            return Ok(FragmentationReport {
+                active_allocations: 0,
+                active_bytes: 0,
+                address_span: 0,
+                density: 0.0,
+                average_allocation_size: 0.0,
+                distinct_size_classes: 0,
+                utilization: 0.0,
+            });
         }
+
+        let address_span = highest_end - lowest;
+        Ok(FragmentationReport {
+            active_allocations,
+            active_bytes,
+            address_span,
+            density: if address_span == 0 {
+                0.0
+            } else {
+                active_bytes as f64 / address_span as f64
+            },
+            average_allocation_size: active_bytes as f64 / active_allocations as f64,
+            distinct_size_classes: size_classes,
+            utilization: if stats.total_allocated == 0 {
+                0.0
+            } else {
+                stats.active_memory as f64 / stats.total_allocated as f64
+            },
+        })
     }
 
-    /// Associate a variable name and type with an allocation.
-    pub fn associate_var(
+    /// Histogram of active allocation sizes in power-of-two ranges: 0–8, 9–16,
+    /// 17–32 and so on, up to the range holding the largest allocation.
+    ///
+    /// Zero-sized (synthetic) allocations fall in the first bucket.
+    pub fn size_histogram(&self) -> TrackingResult<SizeHistogram> {
+        let sizes: Vec<usize> = self
+            .get_active_allocations()?
+            .iter()
+            .map(|a| a.size)
+            .collect();
+        Ok(build_size_histogram(&sizes, &[]))
+    }
+
+    /// Histogram of active allocation sizes with custom bucket boundaries.
+    ///
+    /// `boundaries` are inclusive upper bounds, in any order; a final bucket up
+    /// to `usize::MAX` collects larger sizes. An empty slice selects the
+    /// power-of-two ranges of `size_histogram`.
+    pub fn size_histogram_with_buckets(
         &self,
-        ptr: usize,
-        var_name: String,
-        type_name: String,
-    ) -> TrackingResult<()> {
-        // Use try_lock to avoid blocking if the allocator is currently tracking
-        match self.active_allocations.try_lock() {
-            Ok(mut active) => {
-                if let Some(allocation) = active.get_mut(&ptr) {
-                    allocation.var_name = Some(var_name.clone());
-                    allocation.type_name = Some(type_name.clone());
-                    tracing::debug!(
-                        "Associated variable '{}' with existing allocation at {:x}",
-                        var_name,
-                        ptr
-                    );
-                    Ok(())
-                } else {
-                    // For smart pointers and other complex types, create a synthetic allocation entry
-                    // This ensures we can track variables even when the exact pointer isn't in our allocator
-                    let mut synthetic_allocation = AllocationInfo::new(ptr, 0); // Size will be estimated
-                    synthetic_allocation.var_name = Some(var_name.clone());
-                    synthetic_allocation.type_name = Some(type_name.clone());
+        boundaries: &[usize],
+    ) -> TrackingResult<SizeHistogram> {
+        let sizes: Vec<usize> = self
+            .get_active_allocations()?
+            .iter()
+            .map(|a| a.size)
+            .collect();
+        Ok(build_size_histogram(&sizes, boundaries))
+    }
 
-                    // Estimate size based on type
-                    let estimated_size = estimate_type_size(&type_name);
-                    synthetic_allocation.size = estimated_size;
+    /// Split active allocations by whether their size is exactly a power of two.
+    ///
+    /// Returns `(pow2, non_pow2)`. Many allocators have fast paths for
+    /// power-of-two sizes, so a large non-pow2 share hints at sizes worth
+    /// rounding. Zero-sized allocations count as non-pow2.
+    pub fn power_of_two_breakdown(&self) -> TrackingResult<(SizeStat, SizeStat)> {
+        let mut pow2 = SizeStat::default();
+        let mut non_pow2 = SizeStat::default();
 
-                    // Add to active allocations for tracking
-                    active.insert(ptr, synthetic_allocation);
-                    tracing::debug!("Created synthetic allocation for variable '{}' at {:x} (estimated size: {})", 
-                                   var_name, ptr, estimated_size);
-                    Ok(())
-                }
-            }
-            Err(_) => {
-                // If we can't get the lock immediately, it's likely the allocator is busy
-                // We'll just skip the association to avoid deadlock
-                // tracing::warn!("Failed to associate variable '{}' - tracker busy", var_name);
-                Ok(())
-            }
+        for allocation in self.get_active_allocations()? {
+            let stat = if allocation.size.is_power_of_two() {
+                &mut pow2
+            } else {
+                &mut non_pow2
+            };
+            stat.count += 1;
+            stat.total_bytes += allocation.size;
         }
-    }
 
-    /// Get current memory usage statistics.
-    pub fn get_stats(&self) -> TrackingResult<MemoryStats> {
-        match self.stats.lock() {
-            Ok(stats) => Ok(stats.clone()),
-            Err(poisoned) => {
-                // Handle poisoned lock by recovering the data
-                let stats = poisoned.into_inner();
-                Ok(stats.clone())
-            }
-        }
+        Ok((pow2, non_pow2))
     }
 
-    /// Get all currently active allocations.
-    pub fn get_active_allocations(&self) -> TrackingResult<Vec<AllocationInfo>> {
-        match self.active_allocations.lock() {
-            Ok(active) => Ok(active.values().cloned().collect()),
-            Err(poisoned) => {
-                // Handle poisoned lock by recovering the data
-                let active = poisoned.into_inner();
-                Ok(active.values().cloned().collect())
-            }
-        }
+    /// Total padding bytes wasted by active allocations whose size is not a
+    /// multiple of their alignment.
+    ///
+    /// Each allocation contributes `align_up(size, align) - size`. Allocations
+    /// with unknown alignment (tracked without `track_aligned_allocation`) are
+    /// skipped.
+    pub fn alignment_waste_report(&self) -> TrackingResult<usize> {
+        Ok(self.alignment_waste_by_type()?.values().sum())
     }
 
-    /// Get the complete allocation history.
-    pub fn get_allocation_history(&self) -> TrackingResult<Vec<AllocationInfo>> {
-        match self.allocation_history.lock() {
-            Ok(history) => Ok(history.clone()),
-            Err(poisoned) => {
-                // Handle poisoned lock by recovering the data
-                let history = poisoned.into_inner();
-                Ok(history.clone())
+    /// Alignment padding of active allocations, grouped by type name.
+    ///
+    /// Types whose allocations waste no padding are omitted.
+    pub fn alignment_waste_by_type(&self) -> TrackingResult<HashMap<String, usize>> {
+        let mut waste_by_type: HashMap<String, usize> = HashMap::new();
+
+        for allocation in self.get_active_allocations()? {
+            let Some(align) = allocation.align.filter(|align| align.is_power_of_two()) else {
+                continue;
+            };
+            let waste = allocation.size.next_multiple_of(align) - allocation.size;
+            if waste > 0 {
+                let type_name = allocation
+                    .type_name
+                    .unwrap_or_else(|| "Unknown".to_string());
+                *waste_by_type.entry(type_name).or_insert(0) += waste;
             }
         }
+
+        Ok(waste_by_type)
     }
 
-    /// Get memory usage grouped by type.
-    pub fn get_memory_by_type(&self) -> TrackingResult<Vec<TypeMemoryUsage>> {
-        // Clone the active allocations to avoid holding the lock for too long
-        let active_clone = {
-            match self.active_allocations.lock() {
-                Ok(active) => active.values().cloned().collect::<Vec<_>>(),
-                Err(poisoned) => {
-                    // Handle poisoned lock by recovering the data
-                    let active = poisoned.into_inner();
-                    active.values().cloned().collect::<Vec<_>>()
-                }
-            }
-        };
+    /// Bytes the allocator reserved beyond what was requested, summed over the
+    /// active allocations with a known usable size.
+    ///
+    /// Usable sizes come from `track_allocation_with_usable`, or from the
+    /// tracking allocator on platforms where it can query them (glibc's
+    /// `malloc_usable_size`). Allocations without one are skipped.
+    pub fn overallocation_report(&self) -> TrackingResult<usize> {
+        Ok(self
+            .get_active_allocations()?
+            .iter()
+            .filter_map(|allocation| {
+                let usable = allocation.usable_size?;
+                Some(usable.saturating_sub(allocation.size))
+            })
+            .sum())
+    }
 
-        let mut type_usage: HashMap<String, (usize, usize)> = HashMap::new();
+    /// Requested bytes versus bytes an allocator would likely reserve for them.
+    ///
+    /// Returns `(requested, reserved)` for the active allocations, where
+    /// `reserved` rounds every size up to its power-of-two size class (see
+    /// `group_by_power_of_two`). This is an estimate: real allocators use finer
+    /// size classes for small sizes and page-granular mappings for large ones,
+    /// so `reserved / requested` is an upper bound on internal fragmentation.
+    pub fn ideal_vs_actual(&self) -> TrackingResult<(usize, usize)> {
+        let buckets = self.group_by_power_of_two()?;
+        Ok(buckets.iter().fold(
+            (0, 0),
+            |(requested, reserved), (bucket, count, total_bytes)| {
+                (
+                    requested + total_bytes,
+                    reserved + bucket.saturating_mul(*count),
+                )
+            },
+        ))
+    }
 
-        for allocation in active_clone {
-            let type_name = allocation
-                .type_name
-                .unwrap_or_else(|| "Unknown".to_string());
+    /// Measure how evenly memory is spread across types.
+    ///
+    /// Returns the normalized Shannon entropy of per-type byte shares from
+    /// `get_memory_by_type`: a value near 0.0 means a single type dominates,
+    /// a value near 1.0 means memory is spread evenly across types.
+    pub fn type_concentration(&self) -> TrackingResult<f64> {
+        let memory_by_type = self.get_memory_by_type()?;
+        let total: usize = memory_by_type.iter().map(|t| t.total_size).sum();
+        let type_count = memory_by_type.iter().filter(|t| t.total_size > 0).count();
 
-            let (total_size, count) = type_usage.entry(type_name).or_insert((0, 0));
-            *total_size = total_size.saturating_add(allocation.size);
-            *count = count.saturating_add(1);
+        if total == 0 || type_count < 2 {
+            return Ok(0.0);
         }
 
-        let mut result: Vec<TypeMemoryUsage> = type_usage
-            .into_iter()
-            .map(
-                |(type_name, (total_size, allocation_count))| TypeMemoryUsage {
-                    type_name,
-                    total_size,
-                    allocation_count,
-                },
-            )
-            .collect();
+        let entropy: f64 = memory_by_type
+            .iter()
+            .filter(|t| t.total_size > 0)
+            .map(|t| {
+                let share = t.total_size as f64 / total as f64;
+                -share * share.ln()
+            })
+            .sum();
+
+        Ok((entropy / (type_count as f64).ln()).clamp(0.0, 1.0))
+    }
 
-        // Sort by total size descending
-        result.sort_by(|a, b| b.total_size.cmp(&a.total_size));
+    /// Compare tracked active bytes against the process resident set size.
+    ///
+    /// Tracked bytes rarely match RSS because of allocator overhead, untracked
+    /// allocations, code and stacks; this reports how much of RSS is explained.
+    /// Supported on Linux and macOS, returns `TrackingError::Unsupported` elsewhere.
+    pub fn rss_reconciliation(&self) -> TrackingResult<RssReport> {
+        let rss_bytes = crate::utils::process_rss_bytes().ok_or_else(|| {
+            TrackingError::Unsupported("process RSS is not available on this platform".into())
+        })?;
+        let tracked_active_bytes = self.get_stats()?.active_memory;
 
-        Ok(result)
+        let tracked_percentage = if rss_bytes > 0 {
+            tracked_active_bytes as f64 / rss_bytes as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        Ok(RssReport {
+            tracked_active_bytes,
+            rss_bytes,
+            unaccounted_bytes: rss_bytes.saturating_sub(tracked_active_bytes),
+            tracked_percentage,
+        })
     }
 
     /// Export memory data to JSON format with hierarchical structure.
     pub fn export_to_json<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        self.export_to_json_with_options(path, &JsonExportOptions::default())
+    }
+
+    /// Export memory tracking data to JSON, with optional extra sections.
+    ///
+    /// With default options this writes exactly what `export_to_json` writes.
+    pub fn export_to_json_with_options<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        options: &JsonExportOptions,
+    ) -> TrackingResult<()> {
+        self.write_json_export(path.as_ref(), options).map(|_| ())
+    }
+
+    /// Export memory tracking data to JSON and report data-quality problems.
+    ///
+    /// Writes the same file as `export_to_json`, then returns warnings when the
+    /// dataset is degenerate (nothing tracked, no type information, or many
+    /// uncategorized allocations), so a near-empty export is not mistaken for
+    /// a healthy one. An empty list means no problems were found.
+    pub fn export_to_json_checked<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> TrackingResult<Vec<ExportWarning>> {
+        self.write_json_export(path.as_ref(), &JsonExportOptions::default())
+    }
+
+    fn write_json_export(
+        &self,
+        path: &std::path::Path,
+        options: &JsonExportOptions,
+    ) -> TrackingResult<Vec<ExportWarning>> {
         use std::fs::File;
-        let path = path.as_ref();
-        let active_allocations = self.get_active_allocations()?;
-        let memory_by_type = self.get_memory_by_type()?;
+        // One snapshot of the active map serves every section of the export
+        let active_allocations = match options.min_size {
+            Some(min_size) => self.get_active_allocations_filtered(min_size)?,
+            None => self.get_active_allocations()?,
+        };
+        let memory_by_type = aggregate_memory_by_type(&active_allocations);
         let stats = self.get_stats()?;
 
         // Build hierarchical structure using enhanced type information
         let enhanced_types =
             crate::export_enhanced::enhance_type_information(&memory_by_type, &active_allocations);
-        let hierarchical_data =
-            build_hierarchical_json_structure(&enhanced_types, &active_allocations, &stats);
+        let mut hierarchical_data = build_hierarchical_json_structure(
+            &enhanced_types,
+            &active_allocations,
+            &stats,
+            self.get_metadata(),
+        );
+        let warnings = export_warnings(&active_allocations, &enhanced_types);
+        if options.deduplicate {
+            deduplicate_type_allocations(&mut hierarchical_data);
+        }
+        if options.include_flat_allocations {
+            let mut allocations = active_allocations;
+            allocations.sort_by_key(|a| a.seq);
+            hierarchical_data.allocations = Some(allocations);
+        }
 
         let file = File::create(path)?;
         serde_json::to_writer_pretty(file, &hierarchical_data).map_err(|e| {
             crate::types::TrackingError::SerializationError(format!("JSON export failed: {e}"))
         })?;
-        Ok(())
+        Ok(warnings)
+    }
+
+    /// Render current statistics as InfluxDB line protocol, with measurement `memtrack`.
+    ///
+    /// One line carries the overall statistics and one line per type is tagged
+    /// with `type_name`, ready to push to an InfluxDB write endpoint.
+    pub fn export_influx_line(&self) -> TrackingResult<String> {
+        crate::export_influx::export_influx_line(self, &InfluxExportOptions::default())
+    }
+
+    /// Render current statistics as InfluxDB line protocol with a custom measurement and tags.
+    pub fn export_influx_line_with_options(
+        &self,
+        options: &InfluxExportOptions,
+    ) -> TrackingResult<String> {
+        crate::export_influx::export_influx_line(self, options)
+    }
+
+    /// Render current statistics in the Prometheus text exposition format,
+    /// for serving from a `/metrics` endpoint.
+    ///
+    /// Gauges such as `memtrack_active_memory_bytes` and counters such as
+    /// `memtrack_total_allocations_total` each come with `# HELP` and
+    /// `# TYPE` lines.
+    pub fn export_prometheus(&self) -> String {
+        crate::export_prometheus::render_prometheus(self, &PrometheusExportOptions::default())
+    }
+
+    /// Render current statistics in the Prometheus text format, optionally
+    /// with per-type gauges.
+    pub fn export_prometheus_with_options(&self, options: &PrometheusExportOptions) -> String {
+        crate::export_prometheus::render_prometheus(self, options)
+    }
+
+    /// Export live memory as folded stacks for flamegraph.pl or inferno.
+    ///
+    /// Each line is a call stack, outermost frame first, followed by the
+    /// active bytes allocated from it. Stacks come from backtraces captured
+    /// with `with_backtraces`; allocations without one are grouped under a
+    /// `[no-backtrace]` root, so nothing is dropped.
+    pub fn export_flamegraph_folded<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> TrackingResult<()> {
+        crate::export_flamegraph::export_flamegraph_folded(self, path)
+    }
+
+    /// Export the allocation history as CSV, one row per entry.
+    ///
+    /// Columns are `ptr`, `size`, `var_name`, `type_name`, `timestamp_alloc`
+    /// and `timestamp_dealloc`; absent values are blank cells and names are
+    /// quoted per RFC 4180 where needed. Write failures are reported as
+    /// `TrackingError::SerializationError`.
+    pub fn export_to_csv<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        crate::export_csv::export_to_csv(self, path)
+    }
+
+    /// Export the allocation history and statistics as a plain-text SQL dump.
+    ///
+    /// The file is a script of `CREATE TABLE` and `INSERT` statements, not a
+    /// database; replay it with e.g. `sqlite3 memory.db < path` to query
+    /// leaks, group by type or join with other data using standard SQL. The `allocations` table
+    /// holds one row per history entry (`timestamp_dealloc` is `NULL` while
+    /// active) and `stats` holds the current statistics. Rows are streamed to
+    /// the file as they are written.
+    pub fn export_sql_dump<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        crate::export_sql::export_sql_dump(self, path)
+    }
+
+    /// Export the category → subcategory → type hierarchy as treemap JSON.
+    ///
+    /// The nested `{name, value, children}` output can be fed directly to D3 or
+    /// plotly treemaps for an interactive drill-down view; `value` is the number
+    /// of active bytes at each type leaf.
+    pub fn export_treemap_json<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        crate::export_treemap::export_treemap_json(self, path)
     }
 
     /// Export memory analysis visualization showing variable names, types, and usage patterns.
@@ -273,15 +3983,52 @@ impl MemoryTracker {
         crate::visualization::export_lifecycle_timeline(self, path)
     }
 
+    /// Export allocation lifetimes in the Chrome Trace Event Format, to view
+    /// memory on the same timeline as CPU profiles in chrome://tracing or
+    /// Perfetto.
+    ///
+    /// Freed allocations are complete events spanning their lifetime; live
+    /// ones are begin events without an end. See
+    /// `export_chrome_trace::render_chrome_trace` for the event layout.
+    pub fn export_chrome_trace<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
+        crate::export_chrome_trace::export_chrome_trace(self, path)
+    }
+
     /// Legacy export method for backward compatibility.
     /// Redirects to the new memory analysis export.
     ///
     /// # Arguments
     /// * `path` - Output path for the SVG file
-    #[deprecated(since = "0.1.0", note = "Use export_memory_analysis instead")]
+    #[deprecated(
+        since = "0.1.0",
+        note = "Use export_memory_analysis (types and usage) or export_lifecycle_timeline (variable lifecycles) instead"
+    )]
     pub fn export_to_svg<P: AsRef<std::path::Path>>(&self, path: P) -> TrackingResult<()> {
         self.export_memory_analysis(path)
     }
+
+    /// Legacy export method with a choice of rendering.
+    ///
+    /// `LegacySvgStyle::MemoryAnalysis` behaves like `export_to_svg`;
+    /// `LegacySvgStyle::SimpleChart` reproduces the original compact chart.
+    ///
+    /// # Arguments
+    /// * `path` - Output path for the SVG file
+    /// * `options` - Which rendering to produce
+    #[deprecated(
+        since = "0.1.0",
+        note = "Use export_memory_analysis (types and usage) or export_lifecycle_timeline (variable lifecycles) instead"
+    )]
+    pub fn export_to_svg_with_options<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        options: &LegacySvgOptions,
+    ) -> TrackingResult<()> {
+        match options.style {
+            LegacySvgStyle::MemoryAnalysis => self.export_memory_analysis(path),
+            LegacySvgStyle::SimpleChart => crate::export_enhanced::export_enhanced_svg(self, path),
+        }
+    }
 }
 
 impl Default for MemoryTracker {
@@ -290,6 +4037,155 @@ impl Default for MemoryTracker {
     }
 }
 
+impl Drop for MemoryTracker {
+    fn drop(&mut self) {
+        // Errors cannot be reported from drop; flushing is best effort
+        let _ = self.shutdown();
+    }
+}
+
+/// Id of the current `tracing` span, read from the default subscriber.
+///
+/// Uses the subscriber's current-span lookup rather than `Span::current`,
+/// which would clone and drop a span handle on every tracked allocation.
+#[cfg(feature = "tracing")]
+fn current_span_id() -> Option<u64> {
+    tracing::dispatcher::get_default(|dispatch| {
+        dispatch.current_span().id().map(tracing::Id::into_u64)
+    })
+}
+
+/// Heap bytes owned by an allocation record's strings and tags
+fn allocation_heap_bytes(allocation: &AllocationInfo) -> usize {
+    allocation.thread_id.capacity()
+        + allocation.thread_name.as_ref().map_or(0, String::capacity)
+        + allocation.var_name.as_ref().map_or(0, String::capacity)
+        + allocation.type_name.as_ref().map_or(0, String::capacity)
+        + allocation.scope_name.as_ref().map_or(0, String::capacity)
+        + allocation.metadata_tags.capacity() * std::mem::size_of::<String>()
+        + allocation
+            .metadata_tags
+            .iter()
+            .map(String::capacity)
+            .sum::<usize>()
+}
+
+/// Active sets at least this large are aggregated in parallel with the `parallel` feature.
+///
+/// Set from `benches/aggregation.rs`: on a single rayon worker the parallel
+/// fold is never faster than the serial one (hence the worker check in
+/// `aggregate_memory_by_type`), and its fixed cost falls to a few percent of
+/// the fold from about 25k entries, the size at which it splits into two
+/// chunks that separate workers can take.
+#[cfg(feature = "parallel")]
+const PARALLEL_AGGREGATION_THRESHOLD: usize = 2 * PARALLEL_CHUNK_LEN;
+
+/// Entries folded per rayon task by the parallel aggregation
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_LEN: usize = 12_500;
+
+/// Group allocations by type name, sorted by total size descending
+pub(crate) fn aggregate_memory_by_type<T: TypedSize>(allocations: &[T]) -> Vec<TypeMemoryUsage> {
+    // With a single rayon worker the parallel fold is the serial one plus overhead
+    #[cfg(feature = "parallel")]
+    let type_usage = if allocations.len() >= PARALLEL_AGGREGATION_THRESHOLD
+        && rayon::current_num_threads() > 1
+    {
+        fold_type_usage_parallel(allocations)
+    } else {
+        fold_type_usage(allocations)
+    };
+    #[cfg(not(feature = "parallel"))]
+    let type_usage = fold_type_usage(allocations);
+
+    sorted_type_usage(type_usage)
+}
+
+/// Partial per-type maps built per chunk on the rayon pool, then merged pairwise
+#[cfg(feature = "parallel")]
+pub(crate) fn fold_type_usage_parallel<T: TypedSize>(
+    allocations: &[T],
+) -> HashMap<&str, (usize, usize)> {
+    use rayon::prelude::*;
+
+    allocations
+        .par_chunks(PARALLEL_CHUNK_LEN)
+        .map(fold_type_usage)
+        .reduce(HashMap::new, |mut merged, partial| {
+            for (type_name, (size, count)) in partial {
+                let (total_size, total_count) = merged.entry(type_name).or_insert((0, 0));
+                *total_size = total_size.saturating_add(size);
+                *total_count = total_count.saturating_add(count);
+            }
+            merged
+        })
+}
+
+/// Per-type `(total_size, count)` sums as `TypeMemoryUsage`, sorted by total size descending
+pub(crate) fn sorted_type_usage(type_usage: HashMap<&str, (usize, usize)>) -> Vec<TypeMemoryUsage> {
+    let mut result: Vec<TypeMemoryUsage> = type_usage
+        .into_iter()
+        .map(
+            |(type_name, (total_size, allocation_count))| TypeMemoryUsage {
+                type_name: type_name.to_string(),
+                total_size,
+                allocation_count,
+            },
+        )
+        .collect();
+
+    // Sort by total size descending; the stable sort keeps equal sizes in
+    // type-name order so the output is deterministic
+    result.sort_by(|a, b| a.type_name.cmp(&b.type_name));
+    result.sort_by_key(|t| std::cmp::Reverse(t.total_size));
+
+    result
+}
+
+/// An entry that `aggregate_memory_by_type` can sum per type; shared across
+/// rayon workers with the `parallel` feature, so `Sync` there
+#[cfg(feature = "parallel")]
+pub(crate) trait TypedSize: Sync {
+    /// The entry's type name, if known, and its size in bytes
+    fn type_and_size(&self) -> (Option<&str>, usize);
+}
+
+/// An entry that `aggregate_memory_by_type` can sum per type
+#[cfg(not(feature = "parallel"))]
+pub(crate) trait TypedSize {
+    /// The entry's type name, if known, and its size in bytes
+    fn type_and_size(&self) -> (Option<&str>, usize);
+}
+
+impl TypedSize for AllocationInfo {
+    fn type_and_size(&self) -> (Option<&str>, usize) {
+        (self.type_name.as_deref(), self.size)
+    }
+}
+
+/// A `(type_name, size)` pair copied out of the active map
+impl TypedSize for (Option<String>, usize) {
+    fn type_and_size(&self) -> (Option<&str>, usize) {
+        (self.0.as_deref(), self.1)
+    }
+}
+
+/// Sum `(total_size, count)` per type name
+pub(crate) fn fold_type_usage<T: TypedSize>(allocations: &[T]) -> HashMap<&str, (usize, usize)> {
+    let mut type_usage: HashMap<&str, (usize, usize)> = HashMap::new();
+
+    for allocation in allocations {
+        let (type_name, size) = allocation.type_and_size();
+        let type_name = type_name.unwrap_or("Unknown");
+
+        let (total_size, count) = type_usage.entry(type_name).or_insert((0, 0));
+        *total_size = total_size.saturating_add(size);
+        *count = count.saturating_add(1);
+    }
+
+    type_usage
+}
+
 /// Estimate the size of a type based on its name
 /// This is used for synthetic allocations when we can't get the exact size
 fn estimate_type_size(type_name: &str) -> usize {
@@ -326,20 +4222,138 @@ fn estimate_type_size(type_name: &str) -> usize {
     }
 }
 
+/// Collapse identical `(size, type, variable)` entries in every per-type
+/// allocation list into one entry carrying a `count`
+fn deduplicate_type_allocations(root: &mut ExportRoot) {
+    let type_details = root
+        .memory_hierarchy
+        .values_mut()
+        .flat_map(|category| category.subcategories.values_mut())
+        .flat_map(|subcategory| subcategory.types.iter_mut());
+
+    for type_detail in type_details {
+        let mut entries: Vec<TypeAllocationEntry> = Vec::new();
+        let mut index: HashMap<(usize, Option<String>, Option<String>), usize> = HashMap::new();
+
+        for entry in type_detail.allocations.drain(..) {
+            let key = (
+                entry.size_bytes,
+                entry.type_name.clone(),
+                entry.variable_name.clone(),
+            );
+            match index.get(&key) {
+                Some(&position) => {
+                    let existing = &mut entries[position];
+                    existing.count = existing.count.map(|count| count + 1);
+                    if (entry.allocation_time, entry.alloc_id)
+                        < (existing.allocation_time, existing.alloc_id)
+                    {
+                        existing.alloc_id = entry.alloc_id;
+                        existing.allocation_time = entry.allocation_time;
+                    }
+                }
+                None => {
+                    index.insert(key, entries.len());
+                    entries.push(TypeAllocationEntry {
+                        count: Some(1),
+                        ..entry
+                    });
+                }
+            }
+        }
+        type_detail.allocations = entries;
+    }
+}
+
+/// Data-quality warnings for an export of `active_allocations`
+fn export_warnings(
+    active_allocations: &[AllocationInfo],
+    enhanced_types: &[crate::export_enhanced::EnhancedTypeInfo],
+) -> Vec<ExportWarning> {
+    if active_allocations.is_empty() {
+        return vec![ExportWarning::NoActiveAllocations];
+    }
+    if enhanced_types.is_empty() {
+        return vec![ExportWarning::NoTypeInformation];
+    }
+
+    let untyped = active_allocations
+        .iter()
+        .filter(|a| a.type_name.is_none())
+        .count();
+    if untyped == 0 {
+        return Vec::new();
+    }
+    vec![ExportWarning::UncategorizedAllocations {
+        count: untyped,
+        percentage: untyped as f64 / active_allocations.len() as f64 * 100.0,
+    }]
+}
+
+/// Bucket `sizes` by inclusive upper `boundaries`, or by power-of-two ranges
+/// from 8 bytes up to the largest size when `boundaries` is empty
+fn build_size_histogram(sizes: &[usize], boundaries: &[usize]) -> SizeHistogram {
+    let mut bounds: Vec<usize> = if boundaries.is_empty() {
+        let largest = sizes.iter().copied().max().unwrap_or(0);
+        let top = largest
+            .max(MIN_HISTOGRAM_BUCKET)
+            .checked_next_power_of_two()
+            .unwrap_or(usize::MAX);
+        std::iter::successors(Some(MIN_HISTOGRAM_BUCKET), |&bound| {
+            (bound < top).then(|| bound.saturating_mul(2))
+        })
+        .collect()
+    } else {
+        boundaries.to_vec()
+    };
+    bounds.sort_unstable();
+    bounds.dedup();
+    if !boundaries.is_empty() && bounds.last() != Some(&usize::MAX) {
+        bounds.push(usize::MAX);
+    }
+
+    let mut buckets: Vec<SizeBucket> = bounds
+        .iter()
+        .enumerate()
+        .map(|(index, &max_size)| SizeBucket {
+            min_size: index.checked_sub(1).map_or(0, |i| bounds[i] + 1),
+            max_size,
+            count: 0,
+            total_bytes: 0,
+        })
+        .collect();
+    for &size in sizes {
+        let bucket = &mut buckets[bounds.partition_point(|&bound| bound < size)];
+        bucket.count += 1;
+        bucket.total_bytes = bucket.total_bytes.saturating_add(size);
+    }
+    SizeHistogram { buckets }
+}
+
 /// Build hierarchical JSON structure with categories and subcategories
-fn build_hierarchical_json_structure(
+pub(crate) fn build_hierarchical_json_structure(
     enhanced_types: &[crate::export_enhanced::EnhancedTypeInfo],
     active_allocations: &[AllocationInfo],
     stats: &MemoryStats,
-) -> serde_json::Value {
-    use std::collections::HashMap;
-
+    metadata: BTreeMap<String, String>,
+) -> ExportRoot {
     // Group enhanced types by category and subcategory
     let mut categories: HashMap<
         String,
         HashMap<String, Vec<&crate::export_enhanced::EnhancedTypeInfo>>,
     > = HashMap::new();
 
+    // Simplify each allocation's type once, with the same analysis that named the
+    // enhanced types, so allocations are linked by exact simplified-name equality
+    let simplified_names: Vec<Option<String>> = active_allocations
+        .iter()
+        .map(|alloc| {
+            alloc.type_name.as_ref().map(|type_name| {
+                crate::export_enhanced::analyze_type_with_detailed_subcategory(type_name).0
+            })
+        })
+        .collect();
+
     for enhanced_type in enhanced_types {
         categories
             .entry(enhanced_type.category.clone())
@@ -350,7 +4364,7 @@ fn build_hierarchical_json_structure(
     }
 
     // Build hierarchical structure
-    let mut category_data = serde_json::Map::new();
+    let mut category_data = BTreeMap::new();
     let total_memory: usize = enhanced_types.iter().map(|t| t.total_size).sum();
 
     for (category_name, subcategories) in categories {
@@ -366,7 +4380,7 @@ fn build_hierarchical_json_structure(
             0.0
         };
 
-        let mut subcategory_data = serde_json::Map::new();
+        let mut subcategory_data = BTreeMap::new();
         let subcategory_count = subcategories.len();
 
         for (subcategory_name, types) in subcategories {
@@ -386,77 +4400,89 @@ fn build_hierarchical_json_structure(
                     0.0
                 };
 
-                // Find allocations for this specific type
+                // Find allocations for this specific type. Substring or variable-name
+                // matching would let e.g. `Vec<u8>` claim `Vec<Vec<u8>>` allocations.
                 let type_allocations: Vec<_> = active_allocations
                     .iter()
-                    .filter(|alloc| {
-                        if let Some(type_name) = &alloc.type_name {
-                            // Use the same logic as in enhance_type_information to match types
-                            alloc.var_name.as_ref().map_or(false, |var_name| {
-                                type_info.variable_names.contains(var_name)
-                            }) || type_name.contains(&type_info.simplified_name)
-                        } else {
-                            false
-                        }
+                    .zip(&simplified_names)
+                    .filter(|(_, simplified)| {
+                        simplified.as_deref() == Some(type_info.simplified_name.as_str())
                     })
-                    .map(|alloc| {
-                        serde_json::json!({
-                            "variable_name": alloc.var_name,
-                            "size_bytes": alloc.size,
-                            "allocation_time": alloc.timestamp_alloc,
-                            "type_name": alloc.type_name
-                        })
+                    .map(|(alloc, _)| TypeAllocationEntry {
+                        alloc_id: alloc.alloc_id(),
+                        allocation_time: alloc.timestamp_alloc,
+                        count: None,
+                        size_bytes: alloc.size,
+                        source_crate: alloc.source_crate.clone(),
+                        type_name: alloc.type_name.clone(),
+                        variable_name: alloc.var_name.clone(),
                     })
                     .collect();
 
-                type_details.push(serde_json::json!({
-                    "type_name": type_info.simplified_name,
-                    "size_bytes": type_info.total_size,
-                    "allocation_count": type_info.allocation_count,
-                    "percentage_of_subcategory": format!("{:.1}%", type_percentage),
-                    "percentage_of_total": format!("{:.1}%", (type_info.total_size as f64 / total_memory as f64) * 100.0),
-                    "variable_names": type_info.variable_names,
-                    "allocations": type_allocations
-                }));
-            }
-
-            subcategory_data.insert(subcategory_name, serde_json::json!({
-                "summary": {
-                    "total_size_bytes": subcategory_total,
-                    "percentage_of_category": format!("{:.1}%", subcategory_percentage),
-                    "percentage_of_total": format!("{:.1}%", (subcategory_total as f64 / total_memory as f64) * 100.0),
-                    "type_count": type_count
+                type_details.push(TypeDetail {
+                    allocation_count: type_info.allocation_count,
+                    allocations: type_allocations,
+                    percentage_of_subcategory: format!("{:.1}%", type_percentage),
+                    percentage_of_total: format!(
+                        "{:.1}%",
+                        (type_info.total_size as f64 / total_memory as f64) * 100.0
+                    ),
+                    size_bytes: type_info.total_size,
+                    type_name: type_info.simplified_name.clone(),
+                    variable_names: type_info.variable_names.clone(),
+                });
+            }
+
+            subcategory_data.insert(
+                subcategory_name,
+                SubcategoryNode {
+                    summary: SubcategorySummary {
+                        percentage_of_category: format!("{:.1}%", subcategory_percentage),
+                        percentage_of_total: format!(
+                            "{:.1}%",
+                            (subcategory_total as f64 / total_memory as f64) * 100.0
+                        ),
+                        total_size_bytes: subcategory_total,
+                        type_count,
+                    },
+                    types: type_details,
                 },
-                "types": type_details
-            }));
+            );
         }
 
         category_data.insert(
             category_name,
-            serde_json::json!({
-                "summary": {
-                    "total_size_bytes": category_total,
-                    "percentage_of_total": format!("{:.1}%", category_percentage),
-                    "subcategory_count": subcategory_count
+            CategoryNode {
+                subcategories: subcategory_data,
+                summary: CategorySummary {
+                    percentage_of_total: format!("{:.1}%", category_percentage),
+                    subcategory_count,
+                    total_size_bytes: category_total,
                 },
-                "subcategories": subcategory_data
-            }),
+            },
         );
     }
 
-    serde_json::json!({
-        "metadata": {
-            "timestamp": chrono::Utc::now(),
-            "format_version": "1.0",
-            "description": "Hierarchical memory analysis with categories and subcategories"
+    let allocation_sizes: Vec<usize> = active_allocations.iter().map(|a| a.size).collect();
+    ExportRoot {
+        allocations: None,
+        memory_hierarchy: category_data,
+        metadata: ExportMetadata {
+            custom: metadata,
+            description: "Hierarchical memory analysis with categories and subcategories"
+                .to_string(),
+            format_version: "1.0".to_string(),
+            timestamp: chrono::Utc::now(),
         },
-        "summary": {
-            "total_memory_bytes": total_memory,
-            "total_allocations": stats.total_allocations,
-            "active_allocations": stats.active_allocations,
-            "active_memory_bytes": stats.active_memory,
-            "peak_memory_bytes": stats.peak_memory
+        size_histogram: build_size_histogram(&allocation_sizes, &[]),
+        summary: ExportSummary {
+            active_allocations: stats.active_allocations,
+            active_memory_bytes: stats.active_memory,
+            peak_memory_bytes: stats.peak_memory,
+            peak_memory_timestamp: stats.peak_memory_timestamp,
+            peak_allocations_timestamp: stats.peak_allocations_timestamp,
+            total_allocations: stats.total_allocations,
+            total_memory_bytes: total_memory,
         },
-        "memory_hierarchy": category_data
-    })
+    }
 }
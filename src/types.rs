@@ -24,6 +24,40 @@ pub enum TrackingError {
     #[error("Memory corruption detected")]
     MemoryCorruption,
 
+    /// An allocation was tracked at a pointer that is already active
+    #[error("Allocation already tracked at pointer: {ptr:?}")]
+    DuplicatePointer {
+        /// The pointer that is already active
+        ptr: usize,
+    },
+
+    /// Tracking an allocation took active memory past the budget set with
+    /// `MemoryTracker::set_budget`; the allocation is still recorded
+    #[error("Memory budget exceeded: {attempted} bytes active, limit {limit}")]
+    BudgetExceeded {
+        /// The budget in bytes
+        limit: usize,
+        /// Active memory including the allocation
+        attempted: usize,
+    },
+
+    /// `init_global_tracker` was called after the global tracker was created
+    #[error("Global tracker is already initialized")]
+    AlreadyInitialized,
+
+    /// The operation is not supported on this platform
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+
+    /// A saved tracker state was written by an incompatible format version
+    #[error("Tracker state format version {found} is not supported (expected {expected})")]
+    StateVersionMismatch {
+        /// Version found in the file
+        found: u32,
+        /// Version this build reads and writes
+        expected: u32,
+    },
+
     /// Serialization error
     #[error("Serialization error: {0}")]
     SerializationError(String),
@@ -37,7 +71,7 @@ pub enum TrackingError {
 pub type TrackingResult<T> = Result<T, TrackingError>;
 
 /// Enhanced information about a memory allocation with lifecycle tracking
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AllocationInfo {
     /// Memory address of the allocation
     pub ptr: usize,
@@ -47,15 +81,58 @@ pub struct AllocationInfo {
     pub timestamp_alloc: u128,
     /// Timestamp when the deallocation occurred (if applicable)
     pub timestamp_dealloc: Option<u128>,
+    /// Monotonic sequence number assigned when tracked, giving a total order
+    /// independent of timestamp resolution (0 if never tracked). Never reused,
+    /// so it also serves as the allocation's stable identity (see `alloc_id`).
+    #[serde(default)]
+    pub seq: u64,
+    /// Unit in which `size` is counted
+    #[serde(default)]
+    pub unit: ResourceUnit,
+    /// Allocated during a warmup window; never reported as a leak
+    #[serde(default)]
+    pub warmup: bool,
+    /// Intentionally permanent (e.g. a cache); never reported as a leak
+    #[serde(default)]
+    pub expected_persistent: bool,
+    /// Generation current when the allocation was tracked (see `advance_generation`)
+    #[serde(default)]
+    pub generation: u32,
+    /// Synthetic entry created by `associate_var` for an untracked pointer,
+    /// not counted in the statistics unless `estimate_counted`; `size` is an
+    /// estimate from the type name, or the size given to `associate_var_typed`
+    #[serde(default)]
+    pub size_estimated: bool,
+    /// A reconciliation folded this synthetic entry's estimated size into the
+    /// active statistics, so freeing it must take the estimate out again
+    #[serde(default)]
+    pub estimate_counted: bool,
+    /// Workspace crate that made the allocation (set by `track_crate!`)
+    #[serde(default)]
+    pub source_crate: Option<String>,
+    /// Alignment requested for the allocation, if known
+    #[serde(default)]
+    pub align: Option<usize>,
+    /// Bytes the allocator actually reserved, when known; at least `size`
+    #[serde(default)]
+    pub usable_size: Option<usize>,
     /// Optional name of the variable associated with this allocation
     pub var_name: Option<String>,
     /// Optional type name of the variable associated with this allocation
     pub type_name: Option<String>,
     /// Thread ID where the allocation occurred
     pub thread_id: String,
-    /// Backtrace information (if available)
-    #[cfg(feature = "backtrace")]
+    /// Name of that thread, when it has one and it could still be read
+    #[serde(default)]
+    pub thread_name: Option<String>,
+    /// Call stack at allocation time as `symbol at file:line` frames, innermost
+    /// first, when backtrace capture is enabled (see `MemoryTracker::with_backtraces`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub backtrace: Option<Vec<String>>,
+    /// Id of the `tracing` span that was current when the allocation was tracked
+    #[cfg(feature = "tracing")]
+    #[serde(default)]
+    pub span_id: Option<u64>,
 
     // Enhanced lifecycle tracking fields
     /// Peak memory size reached during lifetime (for growable types)
@@ -80,6 +157,25 @@ pub struct AllocationInfo {
     pub metadata_tags: Vec<String>,
 }
 
+thread_local! {
+    /// This thread's formatted id and name, computed once per thread
+    static THREAD_LABEL: (String, Option<String>) = {
+        let thread = std::thread::current();
+        (format!("{:?}", thread.id()), thread.name().map(str::to_owned))
+    };
+}
+
+/// Id and name of the current thread.
+///
+/// The tracking allocator also runs while a thread is exiting, after its
+/// thread-locals are destroyed; the name is then left out rather than
+/// panicking.
+fn current_thread_label() -> (String, Option<String>) {
+    THREAD_LABEL
+        .try_with(Clone::clone)
+        .unwrap_or_else(|_| (format!("{:?}", std::thread::current().id()), None))
+}
+
 impl AllocationInfo {
     /// Create a new allocation info with enhanced lifecycle tracking
     pub fn new(ptr: usize, size: usize) -> Self {
@@ -88,18 +184,30 @@ impl AllocationInfo {
             .unwrap_or_default()
             .as_millis();
 
-        let thread_id = format!("{:?}", std::thread::current().id());
+        let (thread_id, thread_name) = current_thread_label();
 
         Self {
             ptr,
             size,
             timestamp_alloc: timestamp,
             timestamp_dealloc: None,
+            seq: 0,
+            unit: ResourceUnit::Bytes,
+            warmup: false,
+            expected_persistent: false,
+            generation: 0,
+            size_estimated: false,
+            estimate_counted: false,
+            source_crate: None,
+            align: None,
+            usable_size: None,
             var_name: None,
             type_name: None,
             thread_id,
-            #[cfg(feature = "backtrace")]
+            thread_name,
             backtrace: None,
+            #[cfg(feature = "tracing")]
+            span_id: None,
 
             // Initialize enhanced lifecycle fields
             peak_size: Some(size), // Initially same as size
@@ -125,6 +233,15 @@ impl AllocationInfo {
         self.timestamp_dealloc = Some(timestamp);
     }
 
+    /// Stable unique id of this allocation.
+    ///
+    /// Unlike `ptr`, which the allocator reuses once memory is freed, the id is
+    /// never reused within a process, so it identifies one allocation across a
+    /// whole run.
+    pub fn alloc_id(&self) -> u64 {
+        self.seq
+    }
+
     /// Check if this allocation is still active
     pub fn is_active(&self) -> bool {
         self.timestamp_dealloc.is_none()
@@ -214,8 +331,42 @@ impl AllocationInfo {
     }
 }
 
+/// One-line summary for logs, e.g.
+/// `0x7f3a1000 id=42 size=1.5KB type=Vec<u8> var=buffer age=120ms`.
+///
+/// Optional fields appear only when known. Freed allocations show their
+/// `lifetime` instead of their age. Use `Debug` for the exact field values.
+impl std::fmt::Display for AllocationInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#x}", self.ptr)?;
+        if self.seq != 0 {
+            write!(f, " id={}", self.seq)?;
+        }
+        write!(f, " size={}", crate::utils::format_bytes(self.size))?;
+        if let Some(type_name) = &self.type_name {
+            write!(f, " type={type_name}")?;
+        }
+        if let Some(var_name) = &self.var_name {
+            write!(f, " var={var_name}")?;
+        }
+        if let Some(source_crate) = &self.source_crate {
+            write!(f, " crate={source_crate}")?;
+        }
+        match self.lifetime_ms() {
+            Some(lifetime) => write!(f, " lifetime={lifetime}ms"),
+            None => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis();
+                write!(f, " age={}ms", now.saturating_sub(self.timestamp_alloc))
+            }
+        }
+    }
+}
+
 /// Memory usage statistics
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct MemoryStats {
     /// Total number of allocations tracked
     pub total_allocations: usize,
@@ -233,12 +384,240 @@ pub struct MemoryStats {
     pub peak_allocations: usize,
     /// Peak memory usage in bytes
     pub peak_memory: usize,
+    /// Number of allocations tracked at a pointer that was already active
+    #[serde(default)]
+    pub ptr_reuse_collisions: usize,
+    /// Deallocations of synthetic allocations whose size was only estimated;
+    /// these are not counted in the deallocation totals
+    #[serde(default)]
+    pub estimated_deallocations: usize,
+    /// Short-lived allocations removed from history by the coalesce window
+    #[serde(default)]
+    pub coalesced: usize,
+    /// Deallocations of pointers that were not active: never tracked,
+    /// dropped under contention, or already freed
+    #[serde(default)]
+    pub unknown_deallocations: usize,
+    /// When `peak_memory` was reached (milliseconds since the Unix epoch, like
+    /// `AllocationInfo::timestamp_alloc`)
+    #[serde(default)]
+    pub peak_memory_timestamp: Option<u128>,
+    /// When `peak_allocations` was reached (milliseconds since the Unix epoch)
+    #[serde(default)]
+    pub peak_allocations_timestamp: Option<u128>,
     /// Lifecycle statistics
     pub lifecycle_stats: LifecycleStats,
 }
 
-/// Memory usage by type
+/// What to do when an allocation is tracked at a pointer that is already active
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DuplicatePolicy {
+    /// Replace the existing entry with the new allocation
+    #[default]
+    Overwrite,
+    /// Keep the existing entry and return `TrackingError::DuplicatePointer`
+    Reject,
+    /// Add the new size onto the existing entry
+    Sum,
+}
+
+/// Unit in which an allocation's size is counted
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ResourceUnit {
+    /// Heap bytes (the unit of every allocation seen by the tracking allocator)
+    #[default]
+    Bytes,
+    /// Countable handles such as file descriptors or sockets
+    Handles,
+    /// Any other user-defined resource (e.g. "gpu_bytes")
+    Custom(String),
+}
+
+/// Rendering produced by the deprecated `MemoryTracker::export_to_svg`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LegacySvgStyle {
+    /// The current memory analysis view (`export_memory_analysis`)
+    #[default]
+    MemoryAnalysis,
+    /// The original compact chart of the top types with a short summary
+    SimpleChart,
+}
+
+/// Options for the deprecated `MemoryTracker::export_to_svg_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct LegacySvgOptions {
+    /// Which rendering to produce
+    pub style: LegacySvgStyle,
+}
+
+/// Memory usage by allocating crate
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateMemoryUsage {
+    /// Name of the crate (its `CARGO_PKG_NAME`)
+    pub crate_name: String,
+    /// Total size in bytes allocated by this crate
+    pub total_size: usize,
+    /// Number of allocations made by this crate
+    pub allocation_count: usize,
+}
+
+/// Memory usage by the `tracing` span that was current at allocation time
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpanMemoryUsage {
+    /// Span id, or `None` for allocations made outside any span
+    pub span_id: Option<u64>,
+    /// Total size in bytes allocated within this span
+    pub total_size: usize,
+    /// Number of allocations made within this span
+    pub allocation_count: usize,
+}
+
+/// Memory usage by the thread that made the allocations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadMemoryUsage {
+    /// Thread id, as formatted in `AllocationInfo::thread_id`
+    pub thread_id: String,
+    /// Thread name, if the thread has one
+    pub thread_name: Option<String>,
+    /// Total size in bytes of the thread's active allocations
+    pub total_size: usize,
+    /// Number of active allocations made by the thread
+    pub allocation_count: usize,
+}
+
+/// A period in which allocations arrived faster than a threshold
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BurstEvent {
+    /// Timestamp of the first allocation in the burst (milliseconds since UNIX_EPOCH)
+    pub start_ms: u128,
+    /// Timestamp of the last allocation in the burst
+    pub end_ms: u128,
+    /// Number of allocations made during the burst
+    pub allocation_count: usize,
+}
+
+/// Number and total size of a group of allocations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeStat {
+    /// Number of allocations
+    pub count: usize,
+    /// Total bytes of the allocations
+    pub total_bytes: usize,
+}
+
+/// Active allocations bucketed by size (see `MemoryTracker::size_histogram`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeHistogram {
+    /// Buckets in ascending size order, covering every size without gaps
+    pub buckets: Vec<SizeBucket>,
+}
+
+/// One size range of a `SizeHistogram`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeBucket {
+    /// Smallest size in the bucket, inclusive
+    pub min_size: usize,
+    /// Largest size in the bucket, inclusive
+    pub max_size: usize,
+    /// Number of allocations in the bucket
+    pub count: usize,
+    /// Total bytes of the allocations in the bucket
+    pub total_bytes: usize,
+}
+
+/// Footprint of a single value, as measured by `MemoryTracker::measure_value`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueMeasurement {
+    /// Name given to the measured value
+    pub var_name: String,
+    /// Inline size of the value itself (`size_of_val`)
+    pub shallow_size: usize,
+    /// Shallow size plus every live allocation reachable through child links
+    pub retained_size: usize,
+}
+
+/// Which allocations a scope measurement counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScopeAttribution {
+    /// Only activity while the scope was the innermost open scope
+    #[default]
+    Exclusive,
+    /// Activity of the scope and every scope nested inside it
+    Inclusive,
+}
+
+/// Options for `MemoryTracker::begin_scope_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct ScopeOptions {
+    /// Whether nested scopes count towards this scope's report
+    pub attribution: ScopeAttribution,
+    /// Remember allocations made during the scope so `finish` can report the
+    /// ones still alive in `ScopeReport::escaped_ptrs`
+    pub track_escapes: bool,
+}
+
+/// Memory activity measured by a finished scope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScopeReport {
+    /// Name given to `begin_scope`
+    pub name: String,
+    /// Number of enclosing scopes on the same thread (0 for an outermost scope)
+    pub depth: usize,
+    /// How nested scopes were counted
+    pub attribution: ScopeAttribution,
+    /// Bytes allocated
+    pub allocated_bytes: usize,
+    /// Number of allocations
+    pub allocation_count: usize,
+    /// Bytes freed
+    pub deallocated_bytes: usize,
+    /// Number of deallocations
+    pub deallocation_count: usize,
+    /// Time the scope was open in milliseconds
+    pub duration_ms: u64,
+    /// Allocations made during the scope that were still alive when it
+    /// finished, oldest first (only when `track_escapes` was set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escaped_ptrs: Option<Vec<usize>>,
+}
+
+impl ScopeReport {
+    /// Bytes allocated minus bytes freed
+    pub fn net_bytes(&self) -> isize {
+        self.allocated_bytes as isize - self.deallocated_bytes as isize
+    }
+
+    /// Allocations minus deallocations
+    pub fn net_allocations(&self) -> isize {
+        self.allocation_count as isize - self.deallocation_count as isize
+    }
+}
+
+/// Leak candidates with totals, from `MemoryTracker::leak_report`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeakReport {
+    /// Leaked allocations, largest first
+    pub leaks: Vec<LeakedAllocation>,
+    /// Total bytes held by the leaked allocations
+    pub leaked_bytes: usize,
+    /// Leaked bytes and counts per type name, largest first
+    pub by_type: Vec<TypeMemoryUsage>,
+}
+
+/// One allocation in a `LeakReport`, with flags for weakly attributed entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeakedAllocation {
+    /// The allocation that was never freed
+    pub allocation: AllocationInfo,
+    /// No variable is associated with the allocation
+    pub unnamed: bool,
+    /// A synthetic entry whose size is estimated or zero, not a real allocation size
+    pub synthetic: bool,
+}
+
+/// Memory usage by type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TypeMemoryUsage {
     /// The name of the data type
     pub type_name: String,
@@ -248,6 +627,17 @@ pub struct TypeMemoryUsage {
     pub allocation_count: usize,
 }
 
+/// Current and highest-ever active bytes of one type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypePeakUsage {
+    /// The name of the data type
+    pub type_name: String,
+    /// Bytes of this type active now
+    pub current_size: usize,
+    /// Most bytes of this type active at once
+    pub peak_size: usize,
+}
+
 /// Allocation hotspot information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HotspotInfo {
@@ -262,7 +652,7 @@ pub struct HotspotInfo {
 }
 
 /// Enhanced lifecycle statistics for memory allocations per lifecycle.md specification
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct LifecycleStats {
     /// Number of completed allocations (with deallocation timestamps)
     pub completed_allocations: usize,
@@ -309,7 +699,7 @@ pub struct LifecycleStats {
 }
 
 /// Lifecycle percentile statistics
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct LifecyclePercentiles {
     /// 50th percentile (median)
     pub p50: f64,
@@ -334,6 +724,27 @@ pub struct TypeLifecycleStats {
     pub category: LifecycleCategory,
 }
 
+/// Lifetime distribution of the freed allocations of one type
+///
+/// The lifetime fields are 0 when no allocation of the type has been freed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypeLifetimeStats {
+    /// Type name ("Unknown" for untyped allocations)
+    pub type_name: String,
+    /// Number of freed allocations the lifetimes are computed from
+    pub completed_count: usize,
+    /// Allocations of this type still active, not part of the lifetimes
+    pub still_alive: usize,
+    /// Shortest lifetime in milliseconds
+    pub min_lifetime_ms: u128,
+    /// Longest lifetime in milliseconds
+    pub max_lifetime_ms: u128,
+    /// Mean lifetime in milliseconds
+    pub mean_lifetime_ms: f64,
+    /// Median lifetime in milliseconds
+    pub median_lifetime_ms: f64,
+}
+
 /// Categories for lifecycle duration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LifecycleCategory {
@@ -348,7 +759,7 @@ pub enum LifecycleCategory {
 }
 
 /// Risk classification distribution for memory allocations
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct RiskDistribution {
     /// High memory risk allocations (large size or high growth)
     pub high_memory_risk: usize,
@@ -363,7 +774,7 @@ pub struct RiskDistribution {
 }
 
 /// Scope-based lifecycle metrics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScopeLifecycleMetrics {
     /// Scope identifier (function name, block, etc.)
     pub scope_name: String,
@@ -380,7 +791,7 @@ pub struct ScopeLifecycleMetrics {
 }
 
 /// Type-specific lifecycle patterns
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TypeLifecyclePattern {
     /// Type name (String, Vec, Box, etc.)
     pub type_name: String,
@@ -397,7 +808,7 @@ pub struct TypeLifecyclePattern {
 }
 
 /// Ownership patterns for variables
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum OwnershipPattern {
     /// Exclusively owned (Box, Vec, String)
     Owned,
@@ -410,7 +821,7 @@ pub enum OwnershipPattern {
 }
 
 /// Risk levels for memory allocations
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RiskLevel {
     /// Low risk - small, predictable allocations
     Low,
@@ -421,3 +832,582 @@ pub enum RiskLevel {
     /// Critical risk - potential memory leaks or excessive growth
     Critical,
 }
+
+/// Root of the hierarchical JSON export produced by `export_to_json`.
+///
+/// Fields are declared in alphabetical order so the serialized output keeps
+/// the same key order as the original map-based format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRoot {
+    /// Every active allocation as a flat array, if requested with
+    /// `JsonExportOptions::include_flat_allocations`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<Vec<AllocationInfo>>,
+    /// Category name -> category node
+    pub memory_hierarchy: std::collections::BTreeMap<String, CategoryNode>,
+    /// Export metadata
+    pub metadata: ExportMetadata,
+    /// Active allocations bucketed by power-of-two size ranges
+    #[serde(default)]
+    pub size_histogram: SizeHistogram,
+    /// Overall memory summary
+    pub summary: ExportSummary,
+}
+
+/// Node of a treemap export in the nested `{name, value, children}` format
+/// used by D3 and plotly.
+///
+/// Only leaves (types) carry a `value` in bytes; renderers derive the size of
+/// inner nodes by summing their children.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreemapNode {
+    /// Child nodes, largest first (absent on leaves)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreemapNode>,
+    /// Category, subcategory or type name
+    pub name: String,
+    /// Bytes used, present on leaves only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<usize>,
+}
+
+impl TreemapNode {
+    pub(crate) fn leaf(name: String, bytes: usize) -> Self {
+        Self {
+            children: Vec::new(),
+            name,
+            value: Some(bytes),
+        }
+    }
+
+    pub(crate) fn branch(name: String, mut children: Vec<TreemapNode>) -> Self {
+        children.sort_by_key(|child| std::cmp::Reverse(child.total_bytes()));
+        Self {
+            children,
+            name,
+            value: None,
+        }
+    }
+
+    /// Bytes of this node: its value for leaves, the sum of its children otherwise
+    pub fn total_bytes(&self) -> usize {
+        self.value
+            .unwrap_or_else(|| self.children.iter().map(TreemapNode::total_bytes).sum())
+    }
+}
+
+/// Sections and depth of `MemoryTracker::text_report`
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Number of types listed by size; 0 leaves the section out
+    pub top_types: usize,
+    /// Minimum age for an allocation to be listed as a leak candidate
+    pub leak_age_ms: u64,
+    /// Include the leak candidate section
+    pub include_leaks: bool,
+    /// Include the health section (statistics consistency, collisions, overhead)
+    pub include_health: bool,
+    /// Include the allocation size percentiles
+    pub include_percentiles: bool,
+}
+
+impl Default for ReportOptions {
+    /// A full report: every section, top 10 types, 10 second leak age
+    fn default() -> Self {
+        Self {
+            top_types: 10,
+            leak_age_ms: 10_000,
+            include_leaks: true,
+            include_health: true,
+            include_percentiles: true,
+        }
+    }
+}
+
+/// Options for `MemoryTracker::export_to_json_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct JsonExportOptions {
+    /// Add a flat top-level `allocations` array with every active allocation
+    pub include_flat_allocations: bool,
+    /// Collapse allocations with the same size, type and variable name in the
+    /// per-type allocation lists into one entry with a `count`. The flat
+    /// `allocations` array is never collapsed.
+    pub deduplicate: bool,
+    /// Leave out active allocations smaller than this many bytes, keeping
+    /// the file small for big runs. The summary statistics still cover every
+    /// allocation.
+    pub min_size: Option<usize>,
+}
+
+/// Data-quality problem found while writing an export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExportWarning {
+    /// There were no active allocations to export
+    NoActiveAllocations,
+    /// No active allocation has a type associated, so the hierarchy is empty
+    NoTypeInformation,
+    /// Some active allocations have no type and appear in no category
+    UncategorizedAllocations {
+        /// Number of allocations without a type
+        count: usize,
+        /// Their share of all active allocations, in percent
+        percentage: f64,
+    },
+}
+
+impl std::fmt::Display for ExportWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportWarning::NoActiveAllocations => write!(f, "no active allocations to export"),
+            ExportWarning::NoTypeInformation => write!(
+                f,
+                "no type information available; associate variables with track_var!"
+            ),
+            ExportWarning::UncategorizedAllocations { count, percentage } => write!(
+                f,
+                "{count} allocations ({percentage:.1}%) have no type and are uncategorized"
+            ),
+        }
+    }
+}
+
+/// Options for `MemoryTracker::export_influx_line_with_options`
+#[derive(Debug, Clone)]
+pub struct InfluxExportOptions {
+    /// Measurement name of every line
+    pub measurement: String,
+    /// Tags added to every line (e.g. `host`, `service`)
+    pub tags: std::collections::BTreeMap<String, String>,
+}
+
+impl Default for InfluxExportOptions {
+    fn default() -> Self {
+        Self {
+            measurement: "memtrack".to_string(),
+            tags: std::collections::BTreeMap::new(),
+        }
+    }
+}
+
+/// Options for `MemoryTracker::export_prometheus_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct PrometheusExportOptions {
+    /// Add per-type gauges labelled with `type`. Off by default: every
+    /// distinct type name becomes its own time series, which can overwhelm
+    /// a Prometheus server when there are many types.
+    pub include_types: bool,
+}
+
+/// Metadata block of the JSON export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportMetadata {
+    /// User-supplied key-value metadata (see `MemoryTracker::set_metadata`)
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub custom: std::collections::BTreeMap<String, String>,
+    /// Human-readable description of the export
+    pub description: String,
+    /// Export format version
+    pub format_version: String,
+    /// Time the export was generated
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Summary block of the JSON export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    /// Current number of active allocations
+    pub active_allocations: usize,
+    /// Current bytes in active allocations
+    pub active_memory_bytes: usize,
+    /// Peak memory usage in bytes
+    pub peak_memory_bytes: usize,
+    /// When the peak memory usage was reached (milliseconds since the Unix epoch)
+    #[serde(default)]
+    pub peak_memory_timestamp: Option<u128>,
+    /// When the peak number of active allocations was reached (milliseconds
+    /// since the Unix epoch)
+    #[serde(default)]
+    pub peak_allocations_timestamp: Option<u128>,
+    /// Total number of allocations tracked
+    pub total_allocations: usize,
+    /// Total bytes across all categorized types
+    pub total_memory_bytes: usize,
+}
+
+/// A top-level category (e.g. "Collections") in the JSON export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryNode {
+    /// Subcategory name -> subcategory node
+    pub subcategories: std::collections::BTreeMap<String, SubcategoryNode>,
+    /// Category summary
+    pub summary: CategorySummary,
+}
+
+/// Summary of a category in the JSON export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorySummary {
+    /// Share of total memory, formatted as a percentage string
+    pub percentage_of_total: String,
+    /// Number of subcategories in this category
+    pub subcategory_count: usize,
+    /// Total bytes in this category
+    pub total_size_bytes: usize,
+}
+
+/// A subcategory (e.g. "Sequences") in the JSON export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubcategoryNode {
+    /// Subcategory summary
+    pub summary: SubcategorySummary,
+    /// Types belonging to this subcategory
+    pub types: Vec<TypeDetail>,
+}
+
+/// Summary of a subcategory in the JSON export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubcategorySummary {
+    /// Share of the parent category, formatted as a percentage string
+    pub percentage_of_category: String,
+    /// Share of total memory, formatted as a percentage string
+    pub percentage_of_total: String,
+    /// Total bytes in this subcategory
+    pub total_size_bytes: usize,
+    /// Number of types in this subcategory
+    pub type_count: usize,
+}
+
+/// Per-type detail in the JSON export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeDetail {
+    /// Number of allocations of this type
+    pub allocation_count: usize,
+    /// Allocations linked to this type
+    pub allocations: Vec<TypeAllocationEntry>,
+    /// Share of the parent subcategory, formatted as a percentage string
+    pub percentage_of_subcategory: String,
+    /// Share of total memory, formatted as a percentage string
+    pub percentage_of_total: String,
+    /// Total bytes for this type
+    pub size_bytes: usize,
+    /// Simplified type name
+    pub type_name: String,
+    /// Variable names associated with this type
+    pub variable_names: Vec<String>,
+}
+
+/// A single allocation listed under a type in the JSON export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeAllocationEntry {
+    /// Stable unique id of the allocation (see `AllocationInfo::alloc_id`)
+    #[serde(default)]
+    pub alloc_id: u64,
+    /// Allocation timestamp (milliseconds since UNIX_EPOCH)
+    pub allocation_time: u128,
+    /// Number of identical allocations this entry stands for, when the export
+    /// was deduplicated; the id and time are those of the earliest one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    /// Size of the allocation in bytes
+    pub size_bytes: usize,
+    /// Workspace crate that made the allocation, if attributed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_crate: Option<String>,
+    /// Full type name of the allocation
+    pub type_name: Option<String>,
+    /// Variable name of the allocation
+    pub variable_name: Option<String>,
+}
+
+/// A single tracking event that can be replayed against a tracker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackEvent {
+    /// An allocation of `size` bytes at `ptr`
+    Alloc {
+        /// Memory address of the allocation
+        ptr: usize,
+        /// Size of the allocation in bytes
+        size: usize,
+    },
+    /// A deallocation of the allocation at `ptr`
+    Dealloc {
+        /// Memory address being freed
+        ptr: usize,
+    },
+    /// The allocation at `old_ptr` moved to `new_ptr` and now holds `size` bytes
+    Realloc {
+        /// Memory address before the reallocation
+        old_ptr: usize,
+        /// Memory address after the reallocation
+        new_ptr: usize,
+        /// New size of the allocation in bytes
+        size: usize,
+    },
+    /// A pool allocation at `ptr` freed while `expected` was still on top of
+    /// the pool's stack (see `MemoryTracker::track_pool_deallocation`)
+    PoolViolation {
+        /// Pool the allocation belongs to
+        pool_id: u64,
+        /// Memory address being freed
+        ptr: usize,
+        /// Most recent live allocation of the pool, which should have been freed first
+        expected: usize,
+    },
+}
+
+/// Snapshot of the statistics taken by `MemoryTracker::mark_baseline`
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    /// Statistics at the time the baseline was marked
+    pub stats: MemoryStats,
+    /// When the baseline was marked
+    pub taken_at: std::time::Instant,
+}
+
+/// Change in the statistics since a `Baseline`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsDelta {
+    /// Allocations tracked since the baseline
+    pub allocations: usize,
+    /// Deallocations tracked since the baseline
+    pub deallocations: usize,
+    /// Bytes allocated since the baseline
+    pub allocated_bytes: usize,
+    /// Bytes freed since the baseline
+    pub deallocated_bytes: usize,
+    /// Change in the number of active allocations
+    pub active_allocations_change: isize,
+    /// Change in active bytes
+    pub active_memory_change: isize,
+    /// Time since the baseline in milliseconds
+    pub elapsed_ms: u64,
+}
+
+/// Allocation churn since the previous `MemoryTracker::rate_sample` call
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct RateSample {
+    /// Allocations tracked per second
+    pub allocations_per_sec: f64,
+    /// Deallocations tracked per second
+    pub deallocations_per_sec: f64,
+    /// Bytes allocated per second
+    pub bytes_allocated_per_sec: f64,
+    /// Time the rates are averaged over
+    pub interval: std::time::Duration,
+}
+
+/// Statistics and live allocations captured by `MemoryTracker::snapshot`
+#[derive(Debug, Clone)]
+pub struct MemorySnapshot {
+    /// Statistics at the time of the snapshot
+    pub stats: MemoryStats,
+    /// When the snapshot was taken
+    pub taken_at: std::time::Instant,
+    /// `(ptr, seq, size)` of every active allocation, sorted by pointer
+    live: Vec<(usize, u64, usize)>,
+}
+
+impl MemorySnapshot {
+    /// Build a snapshot from unsorted `(ptr, seq, size)` triples
+    pub(crate) fn new(stats: MemoryStats, mut live: Vec<(usize, u64, usize)>) -> Self {
+        live.sort_unstable();
+        Self {
+            stats,
+            taken_at: std::time::Instant::now(),
+            live,
+        }
+    }
+
+    /// Number of active allocations in the snapshot
+    pub fn active_allocations(&self) -> usize {
+        self.live.len()
+    }
+
+    /// Compare this snapshot with a later one.
+    ///
+    /// Allocations are matched by pointer and sequence number, so a pointer
+    /// freed and reused in between counts as one freed and one new allocation.
+    pub fn diff(&self, later: &MemorySnapshot) -> MemoryDiff {
+        let mut diff = MemoryDiff {
+            new_allocations: Vec::new(),
+            freed_allocations: Vec::new(),
+            bytes_added: 0,
+            bytes_removed: 0,
+            net_count_delta: later.live.len() as isize - self.live.len() as isize,
+            elapsed_ms: later
+                .taken_at
+                .saturating_duration_since(self.taken_at)
+                .as_millis() as u64,
+        };
+
+        // Both sides are sorted by (ptr, seq): merge them
+        let (mut before, mut after) = (self.live.iter().peekable(), later.live.iter().peekable());
+        loop {
+            let order = match (before.peek(), after.peek()) {
+                (Some(old), Some(new)) => (old.0, old.1).cmp(&(new.0, new.1)),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => break,
+            };
+            match order {
+                std::cmp::Ordering::Less => {
+                    if let Some(&(ptr, _, size)) = before.next() {
+                        diff.freed_allocations.push(ptr);
+                        diff.bytes_removed += size;
+                    }
+                }
+                std::cmp::Ordering::Greater => {
+                    if let Some(&(ptr, _, size)) = after.next() {
+                        diff.new_allocations.push(ptr);
+                        diff.bytes_added += size;
+                    }
+                }
+                std::cmp::Ordering::Equal => {
+                    // The same allocation; its size can still grow under `DuplicatePolicy::Sum`
+                    if let (Some(old), Some(new)) = (before.next(), after.next()) {
+                        diff.bytes_added += new.2.saturating_sub(old.2);
+                        diff.bytes_removed += old.2.saturating_sub(new.2);
+                    }
+                }
+            }
+        }
+        diff
+    }
+}
+
+/// Change in live allocations between two `MemorySnapshot`s
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryDiff {
+    /// Pointers of allocations live in the later snapshot but not the earlier one
+    pub new_allocations: Vec<usize>,
+    /// Pointers of allocations live in the earlier snapshot but freed since
+    pub freed_allocations: Vec<usize>,
+    /// Bytes held by the new allocations, plus growth of surviving ones
+    pub bytes_added: usize,
+    /// Bytes held by the freed allocations, plus shrinkage of surviving ones
+    pub bytes_removed: usize,
+    /// Change in the number of live allocations
+    pub net_count_delta: isize,
+    /// Time between the two snapshots in milliseconds
+    pub elapsed_ms: u64,
+}
+
+impl MemoryDiff {
+    /// Net change in live bytes
+    pub fn net_bytes_delta(&self) -> isize {
+        self.bytes_added as isize - self.bytes_removed as isize
+    }
+}
+
+/// Comparison of the aggregate statistics against the active allocation map
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    /// Active allocation count according to the statistics
+    pub stats_active_allocations: usize,
+    /// Active allocation count according to the active map
+    pub actual_active_allocations: usize,
+    /// Active bytes according to the statistics
+    pub stats_active_memory: usize,
+    /// Active bytes according to the active map
+    pub actual_active_memory: usize,
+}
+
+impl ConsistencyReport {
+    /// Correction needed to bring the active allocation count in line with the map
+    pub fn allocation_drift(&self) -> isize {
+        self.actual_active_allocations as isize - self.stats_active_allocations as isize
+    }
+
+    /// Correction needed to bring the active byte count in line with the map
+    pub fn memory_drift(&self) -> isize {
+        self.actual_active_memory as isize - self.stats_active_memory as isize
+    }
+
+    /// Whether the statistics agree exactly with the active map
+    pub fn is_consistent(&self) -> bool {
+        self.stats_active_allocations == self.actual_active_allocations
+            && self.stats_active_memory == self.actual_active_memory
+    }
+}
+
+/// Recorded versus dropped tracking events, from `MemoryTracker::tracking_fidelity`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackingFidelity {
+    /// Allocations recorded in the statistics
+    pub tracked_allocations: u64,
+    /// Deallocations recorded in the statistics
+    pub tracked_deallocations: u64,
+    /// Allocations skipped because the active map was busy
+    pub dropped_allocations: u64,
+    /// Deallocations skipped because the active map was busy
+    pub dropped_deallocations: u64,
+    /// Per-type total updates skipped because the totals were busy; the
+    /// events themselves were recorded, but `get_peak_by_type` and
+    /// `get_peak_memory_by_type` may be off by that many allocations
+    #[serde(default)]
+    pub dropped_type_updates: u64,
+    /// Share of tracking events that were recorded, from 0.0 to 1.0
+    pub fidelity: f64,
+}
+
+/// Whether every tracked allocation was freed, from `MemoryTracker::allocation_balance`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationBalance {
+    /// Allocations counted by the statistics
+    pub total_allocations: usize,
+    /// Deallocations counted by the statistics
+    pub total_deallocations: usize,
+    /// Allocations still in the active map
+    pub outstanding: usize,
+    /// Bytes still in the active map
+    pub outstanding_bytes: usize,
+    /// True when nothing is outstanding
+    pub is_balanced: bool,
+}
+
+/// Reconciliation of tracked memory against the process resident set size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RssReport {
+    /// Bytes in active tracked allocations
+    pub tracked_active_bytes: usize,
+    /// Resident set size of the process in bytes, as reported by the OS
+    pub rss_bytes: usize,
+    /// RSS not explained by tracked allocations (allocator overhead, untracked memory, code, stacks)
+    pub unaccounted_bytes: usize,
+    /// Percentage of RSS explained by tracked allocations
+    pub tracked_percentage: f64,
+}
+
+/// Heuristic fragmentation and utilization figures for the active allocations
+///
+/// All fields are 0 when nothing is active.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FragmentationReport {
+    /// Number of active allocations
+    pub active_allocations: usize,
+    /// Bytes in active allocations
+    pub active_bytes: usize,
+    /// Bytes from the lowest active address to the end of the highest allocation
+    pub address_span: usize,
+    /// `active_bytes / address_span`: 1.0 means the allocations are packed
+    /// back to back, lower values mean gaps between them
+    pub density: f64,
+    /// Mean active allocation size in bytes
+    pub average_allocation_size: f64,
+    /// Number of distinct power-of-two size classes among active allocations
+    pub distinct_size_classes: usize,
+    /// Active bytes per byte ever allocated (`active_memory / total_allocated`)
+    pub utilization: f64,
+}
+
+/// A site that repeatedly allocates and quickly frees same-sized memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingPongSite {
+    /// Type name of the allocations ("Unknown" if never associated)
+    pub type_name: String,
+    /// Size of each allocation in bytes
+    pub size: usize,
+    /// Number of short-lived alloc/free cycles observed
+    pub cycles: usize,
+    /// Bytes allocated and thrown away across all cycles
+    pub churn_bytes: usize,
+}
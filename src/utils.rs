@@ -11,6 +11,66 @@ pub fn format_bytes(bytes: usize) -> String {
     }
 }
 
+/// Read the resident set size of the current process in bytes.
+///
+/// Returns `None` on platforms without a supported source or if the OS query fails.
+pub fn process_rss_bytes() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        // VmRSS is reported in kB, which avoids needing the page size for /proc/self/statm
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+        let kb: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        #[repr(C, packed(4))]
+        #[derive(Default)]
+        struct MachTaskBasicInfo {
+            virtual_size: u64,
+            resident_size: u64,
+            resident_size_max: u64,
+            user_time: [i32; 2],
+            system_time: [i32; 2],
+            policy: i32,
+            suspend_count: i32,
+        }
+
+        extern "C" {
+            static mach_task_self_: u32;
+            fn task_info(task: u32, flavor: i32, info: *mut i32, count: *mut u32) -> i32;
+        }
+
+        const MACH_TASK_BASIC_INFO: i32 = 20;
+        let mut info = MachTaskBasicInfo::default();
+        let mut count =
+            (std::mem::size_of::<MachTaskBasicInfo>() / std::mem::size_of::<i32>()) as u32;
+
+        // SAFETY: `info` is a correctly sized MACH_TASK_BASIC_INFO buffer and `count`
+        // holds its length in natural_t units, as task_info requires.
+        let result = unsafe {
+            task_info(
+                mach_task_self_,
+                MACH_TASK_BASIC_INFO,
+                &mut info as *mut MachTaskBasicInfo as *mut i32,
+                &mut count,
+            )
+        };
+        if result == 0 {
+            Some(info.resident_size as usize)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
 /// Simplify Rust type names for better readability - Enhanced Unknown Type identification
 pub fn simplify_type_name(type_name: &str) -> (String, String) {
     // Handle empty or explicitly unknown types first
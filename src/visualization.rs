@@ -10,6 +10,16 @@ use std::path::Path;
 use svg::node::element::{Circle, Group, Line, Rectangle, Style, Text as SvgText};
 use svg::Document;
 
+/// Allocation count above which the SVG exports draw aggregated bands
+/// instead of one element per allocation
+pub const SVG_AGGREGATION_THRESHOLD: usize = 10_000;
+
+/// Number of time buckets allocations are grouped into when aggregating
+const AGGREGATION_TIME_BUCKETS: u128 = 20;
+
+/// Maximum number of bands kept when aggregating; smaller bands are merged
+const MAX_AGGREGATED_BANDS: usize = 500;
+
 /// Export memory analysis visualization showing variable names, types, and usage
 pub fn export_memory_analysis<P: AsRef<Path>>(
     tracker: &MemoryTracker,
@@ -27,7 +37,11 @@ pub fn export_memory_analysis<P: AsRef<Path>>(
     let active_allocations = tracker.get_active_allocations()?;
     let stats = tracker.get_stats()?;
 
-    let document = create_memory_analysis_svg(&active_allocations, &stats, tracker)?;
+    let (allocations, note) = aggregate_for_svg(active_allocations);
+    let mut document = create_memory_analysis_svg(&allocations, &stats, tracker)?;
+    if let Some(note) = note {
+        document = document.add(note);
+    }
 
     let mut file = File::create(path)?;
     svg::write(&mut file, &document)
@@ -54,7 +68,11 @@ pub fn export_lifecycle_timeline<P: AsRef<Path>>(
     let active_allocations = tracker.get_active_allocations()?;
     let stats = tracker.get_stats()?;
 
-    let document = create_lifecycle_timeline_svg(&active_allocations, &stats)?;
+    let (allocations, note) = aggregate_for_svg(active_allocations);
+    let mut document = create_lifecycle_timeline_svg(&allocations, &stats)?;
+    if let Some(note) = note {
+        document = document.add(note);
+    }
 
     let mut file = File::create(path)?;
     svg::write(&mut file, &document)
@@ -64,6 +82,93 @@ pub fn export_lifecycle_timeline<P: AsRef<Path>>(
     Ok(())
 }
 
+/// Group allocations into representative bands when there are too many to draw.
+///
+/// Above `SVG_AGGREGATION_THRESHOLD`, allocations are grouped by type and
+/// time bucket; each band becomes one synthetic allocation carrying the
+/// band's total size, so the SVG stays a bounded size. Returns the
+/// allocations to render and, when aggregated, a note explaining it.
+fn aggregate_for_svg(allocations: Vec<AllocationInfo>) -> (Vec<AllocationInfo>, Option<SvgText>) {
+    if allocations.len() <= SVG_AGGREGATION_THRESHOLD {
+        return (allocations, None);
+    }
+
+    let start = allocations
+        .iter()
+        .map(|a| a.timestamp_alloc)
+        .min()
+        .unwrap_or(0);
+    let end = allocations
+        .iter()
+        .map(|a| a.timestamp_alloc)
+        .max()
+        .unwrap_or(0);
+    let bucket_width = ((end - start) / AGGREGATION_TIME_BUCKETS).max(1);
+
+    // (type, bucket) -> (representative allocation, allocation count)
+    let mut bands: HashMap<(Option<String>, u128), (AllocationInfo, usize)> = HashMap::new();
+    for allocation in &allocations {
+        let bucket =
+            ((allocation.timestamp_alloc - start) / bucket_width).min(AGGREGATION_TIME_BUCKETS - 1);
+        let key = (allocation.type_name.clone(), bucket);
+        match bands.get_mut(&key) {
+            Some((band, count)) => {
+                band.size += allocation.size;
+                band.timestamp_alloc = band.timestamp_alloc.min(allocation.timestamp_alloc);
+                if band.var_name.is_none() {
+                    band.var_name = allocation.var_name.clone();
+                }
+                *count += 1;
+            }
+            None => {
+                bands.insert(key, (allocation.clone(), 1));
+            }
+        }
+    }
+
+    let mut bands: Vec<(AllocationInfo, usize)> = bands.into_values().collect();
+    bands.sort_by_key(|(band, _)| std::cmp::Reverse(band.size));
+    if bands.len() > MAX_AGGREGATED_BANDS {
+        let rest = bands.split_off(MAX_AGGREGATED_BANDS - 1);
+        let mut other = AllocationInfo::new(rest[0].0.ptr, 0);
+        other.type_name = Some("Other".to_string());
+        other.var_name = Some("other".to_string());
+        other.timestamp_alloc = start;
+        let mut other_count = 0;
+        for (band, count) in rest {
+            other.size += band.size;
+            other_count += count;
+        }
+        bands.push((other, other_count));
+    }
+
+    let band_count = bands.len();
+    let aggregated = bands
+        .into_iter()
+        .map(|(mut band, count)| {
+            if let Some(var_name) = &mut band.var_name {
+                if count > 1 {
+                    *var_name = format!("{var_name} (+{} more)", count - 1);
+                }
+            }
+            band
+        })
+        .collect();
+
+    let note = SvgText::new(format!(
+        "Aggregated {} allocations into {} bands by type and time",
+        allocations.len(),
+        band_count
+    ))
+    .set("x", 10)
+    .set("y", 16)
+    .set("font-size", 11)
+    .set("font-style", "italic")
+    .set("fill", "#7F8C8D");
+
+    (aggregated, Some(note))
+}
+
 /// Create comprehensive memory analysis SVG with original 12-section layout
 fn create_memory_analysis_svg(
     allocations: &[AllocationInfo],
@@ -0,0 +1,64 @@
+//! Replay randomized allocation sequences and check the accounting invariants.
+
+use memscope_rs::test_support::EventGenerator;
+use memscope_rs::types::TrackEvent;
+use memscope_rs::MemoryTracker;
+use std::collections::HashMap;
+
+#[test]
+fn test_generator_is_deterministic() {
+    let a = EventGenerator::new(42).generate(200);
+    let b = EventGenerator::new(42).generate(200);
+    let c = EventGenerator::new(43).generate(200);
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn test_random_sequences_keep_stats_consistent() {
+    for seed in 0..50 {
+        let mut generator = EventGenerator::new(seed);
+        let events = generator.generate(1_000);
+
+        // A private tracker driven from a single thread never drops events
+        let tracker = MemoryTracker::new();
+        tracker.apply_events(&events).unwrap();
+
+        let report = tracker.verify_consistency().unwrap();
+        assert!(report.is_consistent(), "seed {seed}: {report:?}");
+
+        let stats = tracker.get_stats().unwrap();
+        assert_eq!(tracker.live_memory_exact().unwrap(), stats.active_memory);
+        assert_eq!(stats.active_allocations, generator.live_ptrs().len());
+
+        // Cross-check against an independent model of the sequence
+        let mut model: HashMap<usize, usize> = HashMap::new();
+        let mut peak = 0;
+        for event in &events {
+            match *event {
+                TrackEvent::Alloc { ptr, size } => {
+                    model.insert(ptr, size);
+                }
+                TrackEvent::Dealloc { ptr } => {
+                    model.remove(&ptr);
+                }
+                TrackEvent::Realloc {
+                    old_ptr,
+                    new_ptr,
+                    size,
+                } => {
+                    model.remove(&old_ptr);
+                    model.insert(new_ptr, size);
+                }
+                TrackEvent::PoolViolation { .. } => {}
+            }
+            peak = peak.max(model.values().sum::<usize>());
+        }
+        assert_eq!(stats.active_memory, model.values().sum::<usize>());
+        assert_eq!(stats.peak_memory, peak, "seed {seed}");
+        assert_eq!(
+            stats.total_allocated - stats.total_deallocated,
+            stats.active_memory
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Tests for the aggregate-only tracker.
+
+use memscope_rs::types::ExportRoot;
+use memscope_rs::AggregateOnlyTracker;
+
+#[test]
+fn test_aggregate_only_tracker_keeps_running_totals() {
+    let tracker = AggregateOnlyTracker::new();
+    for _ in 0..1_000 {
+        tracker.track_allocation(64, Some("Vec<u8>")).unwrap();
+    }
+    for _ in 0..10 {
+        tracker.track_allocation(24, Some("String")).unwrap();
+    }
+    tracker.track_allocation(8, None).unwrap();
+    for _ in 0..400 {
+        tracker.track_deallocation(64, Some("Vec<u8>")).unwrap();
+    }
+    tracker.track_deallocation(8, None).unwrap();
+
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.total_allocations, 1_011);
+    assert_eq!(stats.total_deallocations, 401);
+    assert_eq!(stats.active_allocations, 610);
+    assert_eq!(stats.active_memory, 600 * 64 + 10 * 24);
+    assert_eq!(stats.peak_memory, 1_000 * 64 + 10 * 24 + 8);
+
+    let by_type = tracker.get_memory_by_type().unwrap();
+    assert_eq!(by_type.len(), 2, "fully freed types are omitted");
+    assert_eq!(by_type[0].type_name, "Vec<u8>");
+    assert_eq!(by_type[0].total_size, 600 * 64);
+    assert_eq!(by_type[0].allocation_count, 600);
+    assert_eq!(by_type[1].type_name, "String");
+}
+
+#[test]
+fn test_aggregate_only_export_has_no_allocation_detail() {
+    let tracker = AggregateOnlyTracker::new();
+    tracker.track_allocation(64, Some("Vec<u8>")).unwrap();
+    tracker.track_allocation(24, Some("String")).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("aggregate.json");
+    tracker.export_to_json(&path).unwrap();
+
+    let root: ExportRoot = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(root.summary.active_memory_bytes, 88);
+    assert!(!root.memory_hierarchy.is_empty());
+    for category in root.memory_hierarchy.values() {
+        for subcategory in category.subcategories.values() {
+            for type_detail in &subcategory.types {
+                assert!(type_detail.allocations.is_empty());
+            }
+        }
+    }
+}
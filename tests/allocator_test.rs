@@ -0,0 +1,72 @@
+//! Tests for the TrackingAllocator wrapper.
+
+use memscope_rs::alloc::TrackingAllocator;
+use memscope_rs::get_global_tracker;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static INNER_ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static INNER_DEALLOCS: AtomicUsize = AtomicUsize::new(0);
+static INNER_REALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+/// System allocator that counts the calls forwarded to it
+struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        INNER_ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        INNER_DEALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        INNER_REALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[test]
+fn test_wrapped_allocator_forwards_and_tracks() {
+    let allocator = TrackingAllocator::wrap(CountingAlloc);
+    let tracker = get_global_tracker();
+    let layout = Layout::from_size_align(4096, 16).unwrap();
+
+    unsafe {
+        let ptr = allocator.alloc(layout);
+        assert!(!ptr.is_null());
+        let tracked = tracker
+            .get_allocation_by_ptr(ptr as usize)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tracked.size, 4096);
+        assert_eq!(tracked.align, Some(16));
+
+        let grown = allocator.realloc(ptr, layout, 8192);
+        assert!(!grown.is_null());
+        let tracked = tracker
+            .get_allocation_by_ptr(grown as usize)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tracked.size, 8192);
+        if grown != ptr {
+            assert!(tracker
+                .get_allocation_by_ptr(ptr as usize)
+                .unwrap()
+                .is_none());
+        }
+
+        allocator.dealloc(grown, Layout::from_size_align(8192, 16).unwrap());
+        assert!(tracker
+            .get_allocation_by_ptr(grown as usize)
+            .unwrap()
+            .is_none());
+    }
+
+    assert_eq!(INNER_ALLOCS.load(Ordering::Relaxed), 1);
+    assert_eq!(INNER_REALLOCS.load(Ordering::Relaxed), 1);
+    assert_eq!(INNER_DEALLOCS.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,855 @@
+//! Tests for the structured export formats.
+
+use memscope_rs::types::ExportRoot;
+use memscope_rs::{MemoryTracker, TrackingError};
+
+#[test]
+fn test_json_export_parses_into_typed_structs() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker
+        .associate_var(0x2000, "name".to_string(), "String".to_string())
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export.json");
+    tracker.export_to_json(&path).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let root: ExportRoot = serde_json::from_str(&content).unwrap();
+
+    assert_eq!(root.metadata.format_version, "1.0");
+    assert_eq!(root.summary.active_allocations, 2);
+    assert_eq!(root.summary.active_memory_bytes, 96);
+    assert!(root.memory_hierarchy.contains_key("Collections"));
+    let histogram_total: usize = root
+        .size_histogram
+        .buckets
+        .iter()
+        .map(|b| b.total_bytes)
+        .sum();
+    assert_eq!(histogram_total, 96);
+
+    // Re-serializing the typed structure must reproduce the file exactly
+    assert_eq!(serde_json::to_string_pretty(&root).unwrap(), content);
+}
+
+#[test]
+fn test_tracker_metadata_is_emitted_in_json_export() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+
+    // No metadata: the block keeps its original shape
+    let plain = dir.path().join("plain.json");
+    tracker.export_to_json(&plain).unwrap();
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&plain).unwrap()).unwrap();
+    assert!(value["metadata"].get("custom").is_none());
+
+    tracker.set_metadata("git_commit", "abc123".to_string());
+    tracker.set_metadata("workload", "ingest".to_string());
+    tracker.set_metadata("workload", "query".to_string());
+
+    let tagged = dir.path().join("tagged.json");
+    tracker.export_to_json(&tagged).unwrap();
+    let root: ExportRoot =
+        serde_json::from_str(&std::fs::read_to_string(&tagged).unwrap()).unwrap();
+    assert_eq!(root.metadata.custom.len(), 2);
+    assert_eq!(root.metadata.custom["git_commit"], "abc123");
+    assert_eq!(root.metadata.custom["workload"], "query");
+    assert_eq!(tracker.get_metadata(), root.metadata.custom);
+}
+
+#[test]
+fn test_json_export_links_allocations_to_exact_types() {
+    let tracker = MemoryTracker::new();
+    let allocations = [
+        (0x1000, 64, "bytes", "Vec<u8>"),
+        (0x2000, 128, "queue", "VecDeque<u8>"),
+        (0x3000, 256, "rows", "Vec<Vec<u8>>"),
+    ];
+    for (ptr, size, var, type_name) in allocations {
+        tracker.track_allocation(ptr, size).unwrap();
+        tracker
+            .associate_var(ptr, var.to_string(), type_name.to_string())
+            .unwrap();
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export.json");
+    tracker.export_to_json(&path).unwrap();
+    let root: ExportRoot = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+    let collections = &root.memory_hierarchy["Collections"];
+    let linked = |type_name: &str| -> Vec<String> {
+        collections
+            .subcategories
+            .values()
+            .flat_map(|sub| &sub.types)
+            .filter(|detail| detail.type_name == type_name)
+            .flat_map(|detail| &detail.allocations)
+            .filter_map(|entry| entry.variable_name.clone())
+            .collect()
+    };
+
+    assert_eq!(linked("Vec<u8>"), vec!["bytes".to_string()]);
+    assert_eq!(linked("VecDeque<T>"), vec!["queue".to_string()]);
+    assert_eq!(linked("Vec<Vec<u8>>"), vec!["rows".to_string()]);
+}
+
+#[test]
+fn test_json_export_includes_source_crate() {
+    let tracker = MemoryTracker::new();
+    tracker
+        .track_crate_allocation(0x1000, 64, "storage")
+        .unwrap();
+    tracker
+        .associate_var(0x1000, "pages".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export.json");
+    tracker.export_to_json(&path).unwrap();
+    let root: ExportRoot = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+    let entry = root
+        .memory_hierarchy
+        .values()
+        .flat_map(|category| category.subcategories.values())
+        .flat_map(|sub| &sub.types)
+        .flat_map(|detail| &detail.allocations)
+        .find(|entry| entry.variable_name.as_deref() == Some("pages"))
+        .unwrap();
+    assert_eq!(entry.source_crate.as_deref(), Some("storage"));
+}
+
+#[test]
+fn test_json_export_with_flat_allocations() {
+    use memscope_rs::types::JsonExportOptions;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+
+    let plain = dir.path().join("plain.json");
+    tracker.export_to_json(&plain).unwrap();
+    let value: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&plain).unwrap()).unwrap();
+    assert!(value.get("allocations").is_none());
+
+    let flat = dir.path().join("flat.json");
+    tracker
+        .export_to_json_with_options(
+            &flat,
+            &JsonExportOptions {
+                include_flat_allocations: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let root: ExportRoot = serde_json::from_str(&std::fs::read_to_string(&flat).unwrap()).unwrap();
+    let allocations = root.allocations.unwrap();
+    assert_eq!(
+        allocations.iter().map(|a| a.ptr).collect::<Vec<_>>(),
+        vec![0x1000, 0x2000]
+    );
+    assert_eq!(allocations[0].var_name.as_deref(), Some("buffer"));
+    assert_eq!(allocations[1].size, 32);
+}
+
+#[test]
+fn test_json_export_min_size_filter() {
+    use memscope_rs::types::JsonExportOptions;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 4096).unwrap();
+    tracker.track_allocation(0x2000, 16).unwrap();
+    tracker.track_allocation(0x3000, 0).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("large_only.json");
+    tracker
+        .export_to_json_with_options(
+            &path,
+            &JsonExportOptions {
+                include_flat_allocations: true,
+                min_size: Some(1024),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let root: ExportRoot = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let allocations = root.allocations.unwrap();
+    assert_eq!(
+        allocations.iter().map(|a| a.ptr).collect::<Vec<_>>(),
+        vec![0x1000]
+    );
+    // The summary still covers every allocation
+    assert_eq!(root.summary.active_allocations, 3);
+}
+
+#[test]
+fn test_checked_json_export_reports_degenerate_data() {
+    use memscope_rs::types::ExportWarning;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export.json");
+
+    let tracker = MemoryTracker::new();
+    assert_eq!(
+        tracker.export_to_json_checked(&path).unwrap(),
+        vec![ExportWarning::NoActiveAllocations]
+    );
+
+    tracker.track_allocation(0x1000, 64).unwrap();
+    assert_eq!(
+        tracker.export_to_json_checked(&path).unwrap(),
+        vec![ExportWarning::NoTypeInformation]
+    );
+
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    assert!(tracker.export_to_json_checked(&path).unwrap().is_empty());
+
+    for ptr in [0x2000, 0x3000, 0x4000] {
+        tracker.track_allocation(ptr, 8).unwrap();
+    }
+    let warnings = tracker.export_to_json_checked(&path).unwrap();
+    assert_eq!(
+        warnings,
+        vec![ExportWarning::UncategorizedAllocations {
+            count: 3,
+            percentage: 75.0
+        }]
+    );
+    assert!(warnings[0].to_string().contains("75.0%"));
+    assert!(path.exists());
+}
+
+#[test]
+fn test_influx_line_protocol_export() {
+    use memscope_rs::types::InfluxExportOptions;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+
+    let output = tracker.export_influx_line().unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("memtrack active_allocations=2i,active_memory=96i,"));
+    assert!(lines[1].starts_with("memtrack,type_name=Vec<u8> allocation_count=1i,total_size=64i "));
+
+    let timestamp = lines[0].rsplit(' ').next().unwrap();
+    assert!(timestamp.parse::<u128>().unwrap() > 0);
+    assert!(lines.iter().all(|line| line.ends_with(timestamp)));
+
+    let mut options = InfluxExportOptions {
+        measurement: "heap usage".to_string(),
+        ..Default::default()
+    };
+    options.tags.insert("host".to_string(), "web,1".to_string());
+    let tagged = tracker.export_influx_line_with_options(&options).unwrap();
+    assert!(tagged.starts_with("heap\\ usage,host=web\\,1 active_allocations=2i"));
+}
+
+#[test]
+fn test_prometheus_export() {
+    use memscope_rs::types::PrometheusExportOptions;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    let output = tracker.export_prometheus();
+    assert!(output.contains("memtrack_active_memory_bytes 64\n"));
+    assert!(output.contains("memtrack_peak_memory_bytes 96\n"));
+    assert!(output.contains("memtrack_total_allocations_total 2\n"));
+    assert!(!output.contains("type=\""));
+
+    // Every sample has HELP and TYPE lines; counters and only counters end in _total
+    let mut kinds = std::collections::HashMap::new();
+    for line in output.lines() {
+        if let Some(rest) = line.strip_prefix("# TYPE ") {
+            let (name, kind) = rest.split_once(' ').unwrap();
+            kinds.insert(name.to_string(), kind.to_string());
+        } else if !line.starts_with("# HELP ") {
+            let (name, value) = line.split_once(' ').unwrap();
+            value.parse::<f64>().unwrap();
+            let kind = &kinds[name];
+            assert_eq!(name.ends_with("_total"), kind == "counter", "{name}");
+            assert!(output.contains(&format!("# HELP {name} ")));
+        }
+    }
+    assert_eq!(kinds.values().filter(|k| *k == "counter").count(), 4);
+
+    let typed = tracker.export_prometheus_with_options(&PrometheusExportOptions {
+        include_types: true,
+    });
+    assert!(typed.contains("# TYPE memtrack_type_active_memory_bytes gauge\n"));
+    assert!(typed.contains("memtrack_type_active_memory_bytes{type=\"Vec<u8>\"} 64\n"));
+    assert!(typed.contains("memtrack_type_active_allocations{type=\"Vec<u8>\"} 1\n"));
+}
+
+#[inline(never)]
+fn allocate_for_flamegraph(tracker: &MemoryTracker, ptr: usize, size: usize) {
+    tracker.track_allocation(ptr, size).unwrap();
+}
+
+#[test]
+fn test_flamegraph_folded_export() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+
+    let tracker = tracker.with_backtraces(true);
+    allocate_for_flamegraph(&tracker, 0x3000, 512);
+    allocate_for_flamegraph(&tracker, 0x4000, 256);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("memory.folded");
+    tracker.export_flamegraph_folded(&path).unwrap();
+    let folded = std::fs::read_to_string(&path).unwrap();
+
+    let lines: Vec<(&str, usize)> = folded
+        .lines()
+        .map(|line| {
+            let (stack, bytes) = line.rsplit_once(' ').unwrap();
+            (stack, bytes.parse().unwrap())
+        })
+        .collect();
+    assert!(lines.contains(&("[no-backtrace];Vec<u8>", 64)));
+    assert!(lines.contains(&("[no-backtrace];Unknown", 32)));
+
+    // Both allocations share one stack, innermost frame last
+    let (stack, bytes) = lines
+        .iter()
+        .find(|(stack, _)| !stack.starts_with("[no-backtrace]"))
+        .unwrap();
+    assert_eq!(*bytes, 768);
+    assert!(stack
+        .rsplit(';')
+        .next()
+        .unwrap()
+        .ends_with("allocate_for_flamegraph"));
+    assert!(!stack.contains(" at "));
+    assert_eq!(lines.len(), 3);
+}
+
+#[test]
+fn test_treemap_json_export() {
+    use memscope_rs::types::TreemapNode;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker
+        .associate_var(0x2000, "name".to_string(), "String".to_string())
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("treemap.json");
+    tracker.export_treemap_json(&path).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert_eq!(value["name"], "memory");
+    assert!(value.get("value").is_none());
+
+    let root: TreemapNode = serde_json::from_value(value).unwrap();
+    // Leaf values add up to the active memory, with no inner-type double counting
+    assert_eq!(root.total_bytes(), 96);
+    let collections = root
+        .children
+        .iter()
+        .find(|category| category.name == "Collections")
+        .unwrap();
+    let leaf = &collections.children[0].children[0];
+    assert_eq!(leaf.value, Some(64));
+    assert!(leaf.children.is_empty());
+}
+
+#[test]
+fn test_lifecycle_svg_aggregates_large_datasets() {
+    use memscope_rs::visualization::SVG_AGGREGATION_THRESHOLD;
+
+    let tracker = MemoryTracker::new();
+    let count = SVG_AGGREGATION_THRESHOLD + 2_000;
+    for i in 0..count {
+        let ptr = 0x10_0000 + i * 64;
+        tracker.track_allocation(ptr, 32).unwrap();
+        let type_name = if i % 3 == 0 { "Vec<u8>" } else { "String" };
+        tracker
+            .associate_var(ptr, format!("item_{i}"), type_name.to_string())
+            .unwrap();
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("timeline.svg");
+    tracker.export_lifecycle_timeline(&path).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains(&format!("Aggregated {count} allocations into")));
+    assert!(content.matches("<rect").count() < 500);
+}
+
+#[test]
+fn test_timeline_delta_round_trip() {
+    use memscope_rs::timeline_delta::{decode_timeline_delta, DeltaTimeline};
+
+    let tracker = MemoryTracker::new();
+    for i in 0..50 {
+        tracker.track_allocation(0x1000 + i * 16, 16).unwrap();
+    }
+    for i in 0..20 {
+        tracker.track_deallocation(0x1000 + i * 16).unwrap();
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("timeline.json");
+    tracker.export_timeline_delta(&path).unwrap();
+
+    let timeline = tracker.memory_timeline().unwrap();
+    assert_eq!(decode_timeline_delta(&path).unwrap(), timeline);
+
+    // A stable step collapses into a single run
+    let steady: Vec<(u128, usize)> = (0..1000).map(|i| (i * 5, 4096)).collect();
+    let encoded = DeltaTimeline::encode(&steady);
+    assert_eq!(encoded.runs, vec![(5, 0, 999)]);
+    assert_eq!(encoded.decode(), steady);
+    assert!(DeltaTimeline::encode(&[]).decode().is_empty());
+}
+
+#[test]
+fn test_json_export_deduplicates_identical_allocations() {
+    use memscope_rs::types::JsonExportOptions;
+
+    let tracker = MemoryTracker::new();
+    for i in 0..5 {
+        let ptr = 0x1000 + i * 0x100;
+        tracker.track_allocation(ptr, 64).unwrap();
+        tracker
+            .associate_var(ptr, "row".to_string(), "Vec<u8>".to_string())
+            .unwrap();
+    }
+    tracker.track_allocation(0x9000, 128).unwrap();
+    tracker
+        .associate_var(0x9000, "row".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dedup.json");
+    tracker
+        .export_to_json_with_options(
+            &path,
+            &JsonExportOptions {
+                deduplicate: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let root: ExportRoot = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let entries: Vec<_> = root
+        .memory_hierarchy
+        .values()
+        .flat_map(|category| category.subcategories.values())
+        .flat_map(|subcategory| &subcategory.types)
+        .flat_map(|type_detail| &type_detail.allocations)
+        .collect();
+    assert_eq!(entries.len(), 2);
+    let total: usize = entries
+        .iter()
+        .map(|entry| entry.size_bytes * entry.count.unwrap())
+        .sum();
+    assert_eq!(total, 5 * 64 + 128);
+    assert!(entries.iter().any(|entry| entry.count == Some(5)));
+}
+
+#[test]
+fn test_sql_dump_export() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "it's".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("memory.sql");
+    tracker.export_sql_dump(&path).unwrap();
+    let sql = std::fs::read_to_string(&path).unwrap();
+
+    assert!(sql.starts_with("BEGIN TRANSACTION;\n"));
+    assert!(sql.ends_with("COMMIT;\n"));
+    assert!(sql.contains("CREATE TABLE allocations (ptr INTEGER, size INTEGER, type_name TEXT"));
+    assert!(sql.contains(&format!(
+        "INSERT INTO allocations VALUES ({}, 64, 'Vec<u8>', 'it''s', ",
+        0x1000
+    )));
+    let rows: Vec<&str> = sql
+        .lines()
+        .filter(|line| line.starts_with("INSERT INTO allocations"))
+        .collect();
+    assert_eq!(rows.len(), 2);
+    // Only the active allocation has no deallocation time
+    let timestamp_dealloc = |row: &str| row.split(", ").nth(5).unwrap().to_string();
+    assert_eq!(timestamp_dealloc(rows[0]), "NULL");
+    assert_ne!(timestamp_dealloc(rows[1]), "NULL");
+    assert!(sql.contains("INSERT INTO stats VALUES (2, 1, 96, 32, 1, 64, 2, 96);"));
+}
+
+#[test]
+fn test_peak_timestamps_in_stats_and_json_summary() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 128).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+    tracker.track_allocation(0x3000, 16).unwrap();
+
+    let allocated_at = tracker.get_allocation_history().unwrap()[1].timestamp_alloc;
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.peak_memory, 192);
+    let peak_at = stats.peak_memory_timestamp.unwrap();
+    assert!(peak_at >= allocated_at);
+    assert!(stats.peak_allocations_timestamp.is_some());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export.json");
+    tracker.export_to_json(&path).unwrap();
+    let root: ExportRoot = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(root.summary.peak_memory_timestamp, Some(peak_at));
+
+    // Statistics saved before the timestamps existed still load
+    let mut old = serde_json::to_value(&stats).unwrap();
+    let fields = old.as_object_mut().unwrap();
+    fields.remove("peak_memory_timestamp");
+    fields.remove("peak_allocations_timestamp");
+    let old: memscope_rs::types::MemoryStats = serde_json::from_value(old).unwrap();
+    assert_eq!(old.peak_memory, 192);
+    assert_eq!(old.peak_memory_timestamp, None);
+}
+
+#[test]
+fn test_csv_export() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(
+            0x1000,
+            "say \"hi\"".to_string(),
+            "HashMap<String, u8>".to_string(),
+        )
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.csv");
+    tracker.export_to_csv(&path).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+
+    let rows: Vec<&str> = csv.split("\r\n").collect();
+    assert_eq!(
+        rows[0],
+        "ptr,size,var_name,type_name,timestamp_alloc,timestamp_dealloc"
+    );
+    assert!(rows[1].starts_with(&format!(
+        "{},64,\"say \"\"hi\"\"\",\"HashMap<String, u8>\",",
+        0x1000
+    )));
+    // Only the active allocation has a blank deallocation time
+    assert!(rows[1].ends_with(','));
+    assert!(rows[2].starts_with(&format!("{},32,,,", 0x2000)));
+    assert!(!rows[2].ends_with(','));
+    assert_eq!(rows[3], "");
+
+    let error = tracker
+        .export_to_csv(dir.path().join("missing").join("history.csv"))
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        memscope_rs::TrackingError::SerializationError(_)
+    ));
+}
+
+#[test]
+fn test_core_types_round_trip_through_serde() {
+    use memscope_rs::export_enhanced::EnhancedTypeInfo;
+    use memscope_rs::types::{MemoryStats, TypeMemoryUsage};
+    use memscope_rs::AllocationInfo;
+
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    let allocation = tracker.get_allocation_by_ptr(0x1000).unwrap().unwrap();
+    assert_eq!(round_trip(&allocation), allocation);
+    let freed = tracker.get_allocation_history().unwrap()[1].clone();
+    assert_eq!(round_trip(&freed), freed);
+
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(round_trip(&stats), stats);
+    let _: MemoryStats = round_trip(&MemoryStats::default());
+
+    let usage: Vec<TypeMemoryUsage> = tracker.get_memory_by_type().unwrap();
+    assert_eq!(round_trip(&usage), usage);
+
+    let type_info = EnhancedTypeInfo {
+        simplified_name: "Vec<u8>".to_string(),
+        category: "Collections".to_string(),
+        subcategory: "Vec<T>".to_string(),
+        total_size: 64,
+        allocation_count: 1,
+        variable_names: vec!["buffer".to_string()],
+    };
+    assert_eq!(round_trip(&type_info), type_info);
+
+    // Optional fields may be missing from the input
+    let mut value = serde_json::to_value(&allocation).unwrap();
+    let fields = value.as_object_mut().unwrap();
+    fields.remove("var_name");
+    fields.remove("timestamp_dealloc");
+    fields.remove("efficiency_score");
+    let partial: AllocationInfo = serde_json::from_value(value).unwrap();
+    assert_eq!(partial.var_name, None);
+    assert_eq!(partial.size, 64);
+}
+
+#[test]
+fn test_raw_json_round_trips_through_import() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("raw.json");
+    tracker.export_raw_json(&path).unwrap();
+    let imported = MemoryTracker::import_from_json(&path).unwrap();
+
+    assert_eq!(imported.get_stats().unwrap(), tracker.get_stats().unwrap());
+    assert_eq!(
+        imported.get_allocation_history().unwrap(),
+        tracker.get_allocation_history().unwrap()
+    );
+    assert_eq!(
+        imported.get_allocation_by_ptr(0x1000).unwrap(),
+        tracker.get_allocation_by_ptr(0x1000).unwrap()
+    );
+}
+
+#[test]
+fn test_binary_export_round_trips_and_is_smaller_than_json() {
+    let tracker = MemoryTracker::new();
+    for i in 0..200 {
+        let ptr = 0x1000 + i * 0x100;
+        tracker.track_allocation(ptr, 64 + i).unwrap();
+        tracker
+            .associate_var(ptr, format!("buffer_{i}"), "Vec<u8>".to_string())
+            .unwrap();
+        if i % 3 == 0 {
+            tracker.track_deallocation(ptr).unwrap();
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let binary_path = dir.path().join("dump.bin");
+    let json_path = dir.path().join("raw.json");
+    tracker.export_to_binary(&binary_path).unwrap();
+    tracker.export_raw_json(&json_path).unwrap();
+    let imported = MemoryTracker::import_from_binary(&binary_path).unwrap();
+
+    assert_eq!(imported.get_stats().unwrap(), tracker.get_stats().unwrap());
+    assert_eq!(
+        imported.get_allocation_history().unwrap(),
+        tracker.get_allocation_history().unwrap()
+    );
+
+    let binary_len = std::fs::metadata(&binary_path).unwrap().len();
+    let json_len = std::fs::metadata(&json_path).unwrap().len();
+    assert!(binary_len * 2 < json_len);
+}
+
+#[test]
+fn test_import_from_binary_rejects_bad_header() {
+    let dir = tempfile::tempdir().unwrap();
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+
+    let json_path = dir.path().join("raw.json");
+    tracker.export_raw_json(&json_path).unwrap();
+    assert!(matches!(
+        MemoryTracker::import_from_binary(&json_path),
+        Err(TrackingError::SerializationError(_))
+    ));
+
+    let binary_path = dir.path().join("dump.bin");
+    tracker.export_to_binary(&binary_path).unwrap();
+    let mut bytes = std::fs::read(&binary_path).unwrap();
+    bytes[memscope_rs::export_binary::BINARY_MAGIC.len()] += 1;
+    std::fs::write(&binary_path, bytes).unwrap();
+    assert!(matches!(
+        MemoryTracker::import_from_binary(&binary_path),
+        Err(TrackingError::SerializationError(_))
+    ));
+}
+
+#[test]
+fn test_import_from_binary_rejects_hostile_input() {
+    use memscope_rs::export_binary::{BINARY_FORMAT_VERSION, BINARY_MAGIC, MAX_NESTING};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dump.bin");
+    let dump = |body: &[u8]| {
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&BINARY_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(body);
+        std::fs::write(&path, bytes).unwrap();
+        MemoryTracker::import_from_binary(&path)
+    };
+
+    // Arrays nested far past the limit (tag 7) must fail, not overflow the stack
+    let nested = vec![7u8; MAX_NESTING * 1000];
+    assert!(matches!(
+        dump(&nested),
+        Err(TrackingError::SerializationError(message)) if message.contains("nested deeper")
+    ));
+
+    // A string (tag 6) claiming far more bytes than the file holds
+    let mut huge_string = vec![6u8];
+    huge_string.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+    huge_string.extend_from_slice(b"short");
+    assert!(matches!(
+        dump(&huge_string),
+        Err(TrackingError::SerializationError(message)) if message.contains("bytes with 5 left")
+    ));
+}
+
+#[test]
+fn test_import_hierarchical_json_export() {
+    let tracker = MemoryTracker::new();
+    tracker.set_metadata("run", "nightly".to_string());
+    for ptr in [0x1000, 0x2000] {
+        tracker.track_allocation(ptr, 64).unwrap();
+        tracker
+            .associate_var(ptr, "buffer".to_string(), "Vec<u8>".to_string())
+            .unwrap();
+    }
+    tracker.track_allocation(0x3000, 16).unwrap();
+    tracker
+        .associate_var(0x3000, "name".to_string(), "String".to_string())
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("export.json");
+    tracker.export_to_json(&path).unwrap();
+    let imported = MemoryTracker::import_from_json(&path).unwrap();
+
+    let stats = imported.get_stats().unwrap();
+    assert_eq!(stats.active_allocations, 3);
+    assert_eq!(stats.active_memory, 144);
+    assert_eq!(stats.peak_memory, tracker.get_stats().unwrap().peak_memory);
+    assert_eq!(
+        imported.get_metadata().get("run").map(String::as_str),
+        Some("nightly")
+    );
+
+    let mut active: Vec<(usize, Option<String>)> = imported
+        .get_active_allocations()
+        .unwrap()
+        .into_iter()
+        .map(|a| (a.size, a.var_name))
+        .collect();
+    active.sort();
+    assert_eq!(
+        active,
+        vec![
+            (16, Some("name".to_string())),
+            (64, Some("buffer".to_string())),
+            (64, Some("buffer".to_string())),
+        ]
+    );
+
+    std::fs::write(&path, "{\"summary\": 1}").unwrap();
+    match MemoryTracker::import_from_json(&path) {
+        Err(memscope_rs::TrackingError::SerializationError(message)) => {
+            assert!(message.contains("export.json"), "{message}");
+        }
+        Err(other) => panic!("unexpected error: {other:?}"),
+        Ok(_) => panic!("a malformed export was imported"),
+    }
+}
+
+#[test]
+fn test_chrome_trace_export() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation_at(0x1000, 64, 1_000).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation_at(0x2000, 32, 1_500).unwrap();
+    tracker.track_deallocation_at(0x2000, 1_750).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trace.json");
+    tracker.export_chrome_trace(&path).unwrap();
+    let trace: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+    let events = trace["traceEvents"].as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    // The live allocation only begins
+    assert_eq!(events[0]["name"], "buffer");
+    assert_eq!(events[0]["ph"], "B");
+    assert_eq!(events[0]["ts"], 1_000_000);
+    assert!(events[0].get("dur").is_none());
+    assert_eq!(events[0]["args"]["size"], 64);
+    assert_eq!(events[0]["args"]["ptr"], "0x1000");
+    // The freed allocation spans its lifetime, in microseconds
+    assert_eq!(events[1]["ph"], "X");
+    assert_eq!(events[1]["ts"], 1_500_000);
+    assert_eq!(events[1]["dur"], 250_000);
+    assert_eq!(events[1]["name"], "allocation");
+}
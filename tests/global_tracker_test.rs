@@ -0,0 +1,25 @@
+//! Tests for installing a configured global tracker.
+//!
+//! Kept in its own test binary: the global tracker can only be installed once
+//! per process, before anything else has created it.
+
+use memscope_rs::{get_global_tracker, init_global_tracker, MemoryTrackerBuilder, TrackingError};
+use std::sync::Arc;
+
+#[test]
+fn test_init_global_tracker_installs_once() {
+    let builder = MemoryTrackerBuilder::new()
+        .shard_count(2)
+        .history_limit(1_000);
+    let installed = init_global_tracker(builder.clone()).unwrap();
+
+    let global = get_global_tracker();
+    assert!(Arc::ptr_eq(&installed, &global));
+    assert_eq!(global.shard_count(), 2);
+    assert_eq!(global.history_capacity(), Some(1_000));
+
+    assert!(matches!(
+        init_global_tracker(builder),
+        Err(TrackingError::AlreadyInitialized)
+    ));
+}
@@ -1,6 +1,6 @@
 //! Performance tests for memscope-rs to measure overhead and identify bottlenecks.
 
-use memscope_rs::{get_global_tracker, init, track_var};
+use memscope_rs::{get_global_tracker, init, track_var, MemoryTracker};
 use std::sync::Once;
 use std::time::{Duration, Instant};
 
@@ -289,3 +289,113 @@ fn benchmark_memory_usage_of_tracker() {
         );
     }
 }
+
+#[test]
+fn benchmark_tracking_latency_during_export() {
+    let tracker = std::sync::Arc::new(MemoryTracker::new());
+    for i in 0..20_000 {
+        let ptr = 0x1000_0000 + i * 64;
+        tracker.track_allocation(ptr, 64).unwrap();
+        tracker
+            .associate_var(ptr, format!("item_{i}"), format!("Vec<Item{}>", i % 50))
+            .unwrap();
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let export_path = dir.path().join("concurrent_export.json");
+    let exporter = {
+        let tracker = tracker.clone();
+        std::thread::spawn(move || {
+            for _ in 0..2 {
+                tracker.get_memory_by_type().unwrap();
+                tracker.export_to_json(&export_path).unwrap();
+            }
+        })
+    };
+
+    // Track short-lived allocations continuously until the exports finish
+    let before = tracker.get_stats().unwrap().total_allocations;
+    let mut latencies = Vec::new();
+    while !exporter.is_finished() {
+        let ptr = 0x9000_0000 + (latencies.len() % 1024) * 64;
+        let start = Instant::now();
+        let _ = tracker.track_allocation(ptr, 32);
+        latencies.push(start.elapsed());
+        let _ = tracker.track_deallocation(ptr);
+        if latencies.len() % 100 == 0 {
+            std::thread::yield_now();
+        }
+    }
+    exporter.join().unwrap();
+
+    // Tracking never blocks on a busy map; events that find it locked are dropped
+    let operations = latencies.len().max(1);
+    let recorded = tracker.get_stats().unwrap().total_allocations - before;
+    latencies.sort();
+    let p99 = latencies
+        .get(operations * 99 / 100)
+        .copied()
+        .unwrap_or_default();
+    let dropped = operations.saturating_sub(recorded);
+
+    println!("Tracking latency during concurrent export:");
+    println!("  Operations: {operations}");
+    println!("  p99: {p99:?}");
+    println!(
+        "  Dropped while the map was locked: {dropped} ({:.1}%)",
+        dropped as f64 * 100.0 / operations as f64
+    );
+
+    assert!(
+        p99 < Duration::from_millis(10),
+        "Tracking p99 too slow: {p99:?}"
+    );
+}
+
+#[test]
+fn benchmark_dropped_events_by_shard_count() {
+    fn dropped_rate(shards: usize) -> f64 {
+        let tracker = std::sync::Arc::new(MemoryTracker::new().with_shard_count(shards));
+        let handles: Vec<_> = (0..8)
+            .map(|thread| {
+                let tracker = tracker.clone();
+                std::thread::spawn(move || {
+                    for i in 0..5_000 {
+                        let ptr = 0x1000_0000 + thread * 0x100_0000 + (i % 256) * 64;
+                        let _ = tracker.track_allocation(ptr, 64);
+                        let _ = tracker.track_deallocation(ptr);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        1.0 - tracker.tracking_fidelity().fidelity
+    }
+
+    /// Median of a few runs, so one unlucky schedule does not decide the comparison
+    fn median_dropped_rate(shards: usize) -> f64 {
+        let mut rates: Vec<f64> = (0..3).map(|_| dropped_rate(shards)).collect();
+        rates.sort_by(f64::total_cmp);
+        rates[1]
+    }
+
+    let single = median_dropped_rate(1);
+    let sharded = median_dropped_rate(16);
+
+    println!("Dropped events with 8 tracking threads:");
+    println!("  1 shard: {:.2}%", single * 100.0);
+    println!("  16 shards: {:.2}%", sharded * 100.0);
+
+    // Threads only contend for a shard while running at the same time
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if cores > 1 {
+        assert!(
+            sharded <= single,
+            "sharding should not drop more events: {:.2}% with 16 shards vs {:.2}% with 1",
+            sharded * 100.0,
+            single * 100.0
+        );
+    }
+}
@@ -0,0 +1,22 @@
+//! Queries on the global tracker must not record their own copies as allocations.
+
+use memscope_rs::{get_global_tracker, init};
+
+#[test]
+fn test_active_allocation_queries_are_not_tracked() {
+    init();
+    let tracker = get_global_tracker();
+    let data: Vec<Vec<u8>> = (0..1000).map(|i| vec![0u8; 16 + i % 64]).collect();
+
+    let before = tracker.get_stats().unwrap().active_allocations;
+    for _ in 0..5 {
+        let active = tracker.get_active_allocations().unwrap();
+        assert!(active.len() >= data.len());
+        assert_eq!(tracker.get_stats().unwrap().active_allocations, before);
+    }
+    tracker.get_allocation_history().unwrap();
+    tracker.get_memory_by_type().unwrap();
+    assert_eq!(tracker.get_stats().unwrap().active_allocations, before);
+
+    drop(data);
+}
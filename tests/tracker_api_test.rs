@@ -0,0 +1,2637 @@
+//! Tests for the MemoryTracker query and configuration API.
+
+use memscope_rs::{AllocationInfo, MemoryTracker};
+
+#[test]
+fn test_stack_allocations_are_kept_apart_from_heap() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 128).unwrap();
+
+    tracker
+        .track_stack_allocation(1, 4096, "frame_buffer".to_string())
+        .unwrap();
+    tracker
+        .track_stack_allocation(2, 1024, "scratch".to_string())
+        .unwrap();
+
+    let heap = tracker.get_stats().unwrap();
+    assert_eq!(heap.active_allocations, 1);
+    assert_eq!(heap.active_memory, 128);
+
+    let stack = tracker.get_stack_stats().unwrap();
+    assert_eq!(stack.active_allocations, 2);
+    assert_eq!(stack.active_memory, 5120);
+    assert_eq!(stack.peak_memory, 5120);
+
+    tracker.track_stack_deallocation(1).unwrap();
+    let stack = tracker.get_stack_stats().unwrap();
+    assert_eq!(stack.active_memory, 1024);
+    assert_eq!(stack.total_deallocations, 1);
+    assert_eq!(stack.peak_memory, 5120);
+
+    let flagged = tracker.get_stack_allocations().unwrap();
+    assert_eq!(flagged.len(), 1);
+    assert_eq!(flagged[0].var_name.as_deref(), Some("scratch"));
+    assert!(tracker
+        .get_active_allocations()
+        .unwrap()
+        .iter()
+        .all(|a| a.var_name.as_deref() != Some("scratch")));
+}
+
+#[test]
+fn test_type_concentration() {
+    let tracker = MemoryTracker::new();
+    assert_eq!(tracker.type_concentration().unwrap(), 0.0);
+
+    tracker.track_allocation(0x1000, 100).unwrap();
+    tracker
+        .associate_var(0x1000, "a".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    assert_eq!(tracker.type_concentration().unwrap(), 0.0);
+
+    tracker.track_allocation(0x2000, 100).unwrap();
+    tracker
+        .associate_var(0x2000, "b".to_string(), "String".to_string())
+        .unwrap();
+    let even = tracker.type_concentration().unwrap();
+    assert!((even - 1.0).abs() < 1e-9);
+
+    tracker.track_allocation(0x3000, 10_000).unwrap();
+    tracker
+        .associate_var(0x3000, "c".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    let skewed = tracker.type_concentration().unwrap();
+    assert!(skewed > 0.0 && skewed < 0.2);
+}
+
+#[test]
+fn test_finalize_produces_detached_report() {
+    let tracker = MemoryTracker::new();
+    for i in 0..10 {
+        tracker
+            .track_allocation(0x1000 + i * 0x100, (i + 1) * 10)
+            .unwrap();
+    }
+    tracker
+        .associate_var(0x1000, "first".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+
+    let report = tracker.finalize();
+
+    // Mutating the tracker afterwards must not affect the report
+    tracker.track_allocation(0x9000, 1_000_000).unwrap();
+
+    assert_eq!(report.stats().active_allocations, 10);
+    assert_eq!(report.stats().active_memory, 550);
+    assert_eq!(report.size_percentiles().p50, 50);
+    assert_eq!(report.size_percentiles().p99, 100);
+    assert!(report.leak_candidates().is_empty());
+    assert_eq!(report.largest_type().unwrap().type_name, "Unknown");
+    assert!(report
+        .hierarchy()
+        .memory_hierarchy
+        .contains_key("Collections"));
+
+    let cloned = report.clone();
+    assert_eq!(cloned.memory_by_type().len(), report.memory_by_type().len());
+    assert!(serde_json::to_string(&report).is_ok());
+}
+
+#[test]
+fn test_atomic_stats_match_active_map_under_concurrency() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let tracker = Arc::new(MemoryTracker::new());
+    let handles: Vec<_> = (0..8)
+        .map(|t| {
+            let tracker = Arc::clone(&tracker);
+            thread::spawn(move || {
+                for i in 0..500 {
+                    let ptr = 0x10_0000 * (t + 1) + i * 16;
+                    tracker.track_allocation(ptr, 16).unwrap();
+                    if i % 2 == 0 {
+                        tracker.track_deallocation(ptr).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // Events may be dropped under contention, but the counters must always
+    // agree with what actually landed in the active map
+    let stats = tracker.get_stats().unwrap();
+    let active = tracker.get_active_allocations().unwrap();
+    assert_eq!(stats.active_allocations, active.len());
+    assert_eq!(
+        stats.active_memory,
+        active.iter().map(|a| a.size).sum::<usize>()
+    );
+    assert!(stats.peak_memory >= stats.active_memory);
+    assert!(stats.peak_allocations >= stats.active_allocations);
+}
+
+#[test]
+fn test_deallocations_by_type_and_balance() {
+    let tracker = MemoryTracker::new();
+    for i in 0..3 {
+        let ptr = 0x1000 + i * 0x10;
+        tracker.track_allocation(ptr, 64).unwrap();
+        tracker
+            .associate_var(ptr, format!("buf_{i}"), "Vec<u8>".to_string())
+            .unwrap();
+    }
+    tracker.track_allocation(0x2000, 24).unwrap();
+    tracker
+        .associate_var(0x2000, "name".to_string(), "String".to_string())
+        .unwrap();
+    tracker.track_allocation(0x3000, 8).unwrap();
+
+    tracker.track_deallocation(0x1000).unwrap();
+    tracker.track_deallocation(0x1010).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+    tracker.track_deallocation(0x3000).unwrap();
+
+    let history = tracker.get_allocation_history().unwrap();
+    assert_eq!(history.len(), 5);
+    assert_eq!(history.iter().filter(|a| a.is_active()).count(), 1);
+
+    let deallocations = tracker.get_deallocations_by_type().unwrap();
+    assert_eq!(deallocations["Vec<u8>"], 2);
+    assert_eq!(deallocations["String"], 1);
+    assert_eq!(deallocations["Unknown"], 1);
+
+    let balance = tracker.alloc_dealloc_balance_by_type().unwrap();
+    assert_eq!(balance["Vec<u8>"], (3, 2, 1));
+    assert_eq!(balance["String"], (1, 1, 0));
+    assert_eq!(balance["Unknown"], (1, 1, 0));
+}
+
+#[test]
+fn test_duplicate_pointer_policies() {
+    use memscope_rs::types::DuplicatePolicy;
+    use memscope_rs::TrackingError;
+
+    // Overwrite (default): the newer allocation replaces the old entry
+    let tracker = MemoryTracker::new();
+    assert_eq!(tracker.duplicate_policy(), DuplicatePolicy::Overwrite);
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x1000, 128).unwrap();
+    let active = tracker.get_active_allocations().unwrap();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].size, 128);
+    assert_eq!(tracker.get_stats().unwrap().ptr_reuse_collisions, 1);
+
+    // Reject: the old entry is kept and an error is returned
+    let tracker = MemoryTracker::new();
+    tracker.set_duplicate_policy(DuplicatePolicy::Reject);
+    tracker.track_allocation(0x1000, 64).unwrap();
+    let err = tracker.track_allocation(0x1000, 128).unwrap_err();
+    assert!(matches!(
+        err,
+        TrackingError::DuplicatePointer { ptr: 0x1000 }
+    ));
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.active_memory, 64);
+    assert_eq!(stats.total_allocations, 1);
+    assert_eq!(stats.ptr_reuse_collisions, 1);
+
+    // Sum: sizes accumulate onto the existing entry
+    let tracker = MemoryTracker::new();
+    tracker.set_duplicate_policy(DuplicatePolicy::Sum);
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x1000, 32).unwrap();
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.active_allocations, 1);
+    assert_eq!(stats.active_memory, 96);
+    assert_eq!(stats.peak_memory, 96);
+    assert_eq!(tracker.get_active_allocations().unwrap()[0].size, 96);
+    assert!(tracker.verify_consistency().unwrap().is_consistent());
+
+    tracker.track_deallocation(0x1000).unwrap();
+    assert_eq!(tracker.get_stats().unwrap().active_memory, 0);
+}
+
+#[test]
+fn test_oldest_live_allocations() {
+    use std::thread;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 10).unwrap();
+    thread::sleep(Duration::from_millis(5));
+    tracker.track_allocation(0x2000, 20).unwrap();
+    thread::sleep(Duration::from_millis(5));
+    tracker.track_allocation(0x3000, 30).unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+
+    let oldest = tracker.oldest_live_allocations(1, now).unwrap();
+    assert_eq!(oldest.len(), 1);
+    assert_eq!(oldest[0].0.ptr, 0x2000);
+    assert!(oldest[0].1 >= 5);
+
+    let all = tracker.oldest_live_allocations(10, now).unwrap();
+    assert_eq!(
+        all.iter().map(|(a, _)| a.ptr).collect::<Vec<_>>(),
+        vec![0x2000, 0x3000]
+    );
+    assert!(all[0].1 >= all[1].1);
+}
+
+#[test]
+fn test_rss_reconciliation() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 4096).unwrap();
+
+    match tracker.rss_reconciliation() {
+        Ok(report) => {
+            assert!(report.rss_bytes > 0);
+            assert_eq!(report.tracked_active_bytes, 4096);
+            assert_eq!(
+                report.unaccounted_bytes,
+                report.rss_bytes.saturating_sub(4096)
+            );
+            assert!(report.tracked_percentage > 0.0 && report.tracked_percentage <= 100.0);
+        }
+        Err(memscope_rs::TrackingError::Unsupported(_))
+            if !cfg!(any(target_os = "linux", target_os = "macos")) => {}
+        Err(e) => panic!("unexpected error: {e}"),
+    }
+}
+
+#[test]
+fn test_event_log_records_tracked_events() {
+    use memscope_rs::events::LoggedEvent;
+    use memscope_rs::types::TrackEvent;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.jsonl");
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.start_event_log(&path).unwrap();
+    tracker.track_allocation(0x2000, 128).unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+    tracker.stop_event_log().unwrap();
+    tracker.track_allocation(0x3000, 256).unwrap();
+
+    let events: Vec<TrackEvent> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<LoggedEvent>(line).unwrap().event)
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            TrackEvent::Alloc {
+                ptr: 0x2000,
+                size: 128
+            },
+            TrackEvent::Dealloc { ptr: 0x1000 },
+        ]
+    );
+
+    // Stopping again is a no-op
+    tracker.stop_event_log().unwrap();
+}
+
+#[test]
+fn test_ping_pong_report() {
+    let tracker = MemoryTracker::new();
+    let mut now = 1_000_000u128;
+    let mut cycle = |ptr: usize, size: usize, type_name: Option<&str>, lifetime: u128| {
+        tracker.track_allocation_at(ptr, size, now).unwrap();
+        if let Some(type_name) = type_name {
+            tracker
+                .associate_var(ptr, "scratch".to_string(), type_name.to_string())
+                .unwrap();
+        }
+        tracker.track_deallocation_at(ptr, now + lifetime).unwrap();
+        now += 10;
+    };
+
+    // Freed immediately, at the edge of the window
+    for i in 0..20 {
+        cycle(0x1000, 256, Some("Vec<u8>"), i % 2);
+    }
+    // Too few cycles to be reported
+    for _ in 0..3 {
+        cycle(0x2000, 32, None, 0);
+    }
+    // Short-lived buffers, but not freed immediately
+    for _ in 0..20 {
+        cycle(0x3000, 512, Some("String"), 2);
+    }
+
+    let report = tracker.ping_pong_report();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].type_name, "Vec<u8>");
+    assert_eq!(report[0].size, 256);
+    assert_eq!(report[0].cycles, 20);
+    assert_eq!(report[0].churn_bytes, 20 * 256);
+}
+
+#[test]
+fn test_allocations_get_increasing_sequence_numbers() {
+    let tracker = MemoryTracker::new();
+    for i in 0..5 {
+        tracker.track_allocation(0x1000 + i * 0x100, 16).unwrap();
+    }
+    tracker.track_deallocation(0x1000).unwrap();
+
+    let history = tracker.get_allocation_history().unwrap();
+    assert_eq!(history.len(), 5);
+    assert!(history.iter().all(|a| a.seq > 0));
+    assert!(history.windows(2).all(|w| w[0].seq < w[1].seq));
+    assert_eq!(
+        history.iter().map(|a| a.ptr).collect::<Vec<_>>(),
+        vec![0x1000, 0x1100, 0x1200, 0x1300, 0x1400]
+    );
+}
+
+#[test]
+fn test_save_and_load_state() {
+    use memscope_rs::types::DuplicatePolicy;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tracker.state");
+
+    let tracker = MemoryTracker::new();
+    tracker.set_duplicate_policy(DuplicatePolicy::Sum);
+    tracker.set_metadata("run", "nightly".to_string());
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 128).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+    tracker
+        .track_stack_allocation(7, 512, "frame".to_string())
+        .unwrap();
+    tracker.save_state(&path).unwrap();
+
+    let restored = MemoryTracker::load_state(&path).unwrap();
+    let stats = restored.get_stats().unwrap();
+    assert_eq!(stats.total_allocations, 2);
+    assert_eq!(stats.total_deallocations, 1);
+    assert_eq!(stats.active_memory, 64);
+    assert_eq!(stats.peak_memory, 192);
+    assert_eq!(restored.duplicate_policy(), DuplicatePolicy::Sum);
+    assert_eq!(restored.get_metadata()["run"], "nightly");
+    assert_eq!(restored.get_stack_stats().unwrap().active_memory, 512);
+
+    let active = restored.get_active_allocations().unwrap();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].var_name.as_deref(), Some("buffer"));
+    assert_eq!(restored.get_allocation_history().unwrap().len(), 2);
+
+    // The restored tracker keeps tracking where the original left off
+    restored.track_deallocation(0x1000).unwrap();
+    assert_eq!(restored.get_stats().unwrap().active_memory, 0);
+    assert!(restored
+        .get_allocation_history()
+        .unwrap()
+        .iter()
+        .all(|a| !a.is_active()));
+}
+
+#[test]
+fn test_save_and_load_state_keeps_links_pools_and_samples() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tracker.state");
+
+    let tracker = MemoryTracker::new();
+    tracker.set_double_free_window(Some(8));
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker.link_child(0x1000, 0x2000).unwrap();
+    tracker.record_type_sample().unwrap();
+    tracker.advance_generation();
+    tracker.advance_generation();
+    {
+        let _scope = tracker.scope("load");
+        tracker.track_allocation(0x3000, 16).unwrap();
+    }
+    tracker.record_type_sample().unwrap();
+    tracker.track_deallocation(0x3000).unwrap();
+    tracker.track_deallocation(0x3000).ok();
+    tracker.track_pool_allocation(9, 0x9000, 8).unwrap();
+    tracker.track_pool_allocation(9, 0x9100, 8).unwrap();
+    tracker.track_pool_deallocation(9, 0x9000).unwrap();
+    let retained = tracker.retained_size(0x1000).unwrap();
+    assert_eq!(retained, 96);
+    tracker.save_state(&path).unwrap();
+
+    let restored = MemoryTracker::load_state(&path).unwrap();
+    assert_eq!(restored.retained_size(0x1000).unwrap(), retained);
+    assert_eq!(restored.suspected_double_frees(), vec![0x3000]);
+    assert_eq!(restored.pool_violations(9), 1);
+    restored.track_pool_deallocation(9, 0x9100).unwrap();
+    assert_eq!(restored.monotonic_growth_types(), ["Unknown"]);
+    let reports = restored.scope_reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].name, "load");
+    assert_eq!(restored.advance_generation(), 3);
+
+    // The binary dump holds the same state
+    let binary_path = dir.path().join("tracker.bin");
+    tracker.export_to_binary(&binary_path).unwrap();
+    let imported = MemoryTracker::import_from_binary(&binary_path).unwrap();
+    assert_eq!(imported.retained_size(0x1000).unwrap(), retained);
+    assert_eq!(imported.scope_reports().len(), 1);
+    assert_eq!(imported.pool_violations(9), 1);
+}
+
+#[test]
+fn test_save_and_load_state_keeps_configuration() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tracker.state");
+
+    let tracker = MemoryTracker::builder()
+        .history_limit(2)
+        .blocking_mode(true)
+        .capture_backtraces(true)
+        .shard_count(4)
+        .budget(Some(1_000))
+        .build();
+    for ptr in [0x1000, 0x2000, 0x3000] {
+        tracker.track_allocation(ptr, 256).unwrap();
+        tracker
+            .associate_var(ptr, "buffer".to_string(), "Vec<u8>".to_string())
+            .unwrap();
+    }
+    for ptr in [0x1000, 0x2000] {
+        tracker.track_deallocation(ptr).unwrap();
+    }
+    tracker.set_coalesce_window_ms(Some(60_000));
+    tracker.save_state(&path).unwrap();
+
+    let restored = MemoryTracker::load_state(&path).unwrap();
+    assert_eq!(restored.history_capacity(), Some(2));
+    assert_eq!(restored.history_evicted_count(), 1);
+    assert!(restored.is_blocking_mode());
+    assert_eq!(restored.shard_count(), 4);
+    // The per-type peak survives although only one allocation is still live
+    assert_eq!(restored.get_peak_by_type().unwrap()["Vec<u8>"], 768);
+    assert_eq!(
+        restored.get_peak_by_type().unwrap(),
+        tracker.get_peak_by_type().unwrap()
+    );
+
+    restored.track_allocation(0x4000, 64).unwrap();
+    assert!(restored
+        .get_allocation_by_ptr(0x4000)
+        .unwrap()
+        .unwrap()
+        .backtrace
+        .is_some());
+    assert!(matches!(
+        restored.track_allocation(0x5000, 1_000),
+        Err(memscope_rs::TrackingError::BudgetExceeded { limit: 1_000, .. })
+    ));
+    // Freed within the coalesce window, so dropped from the history
+    restored.track_deallocation(0x4000).unwrap();
+    assert_eq!(restored.get_stats().unwrap().coalesced, 1);
+}
+
+#[test]
+fn test_load_state_never_reuses_restored_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tracker.state");
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.save_state(&path).unwrap();
+
+    // As if the checkpoint came from a process that handed out far more ids
+    let restored_id = 1u64 << 40;
+    let mut state: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    state["active_allocations"][0]["seq"] = restored_id.into();
+    state["history"][0]["seq"] = restored_id.into();
+    std::fs::write(&path, state.to_string()).unwrap();
+
+    let restored = MemoryTracker::load_state(&path).unwrap();
+    restored.track_allocation(0x2000, 32).unwrap();
+    let new_id = restored.get_allocation_by_ptr(0x2000).unwrap().unwrap().seq;
+    assert!(new_id > restored_id);
+    assert_eq!(
+        restored
+            .get_allocation_by_id(restored_id)
+            .unwrap()
+            .map(|a| a.ptr),
+        Some(0x1000)
+    );
+    assert_eq!(
+        restored
+            .get_allocation_by_id(new_id)
+            .unwrap()
+            .map(|a| a.ptr),
+        Some(0x2000)
+    );
+}
+
+#[test]
+fn test_load_state_rejects_other_versions() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tracker.state");
+    std::fs::write(&path, r#"{"format_version": 999}"#).unwrap();
+
+    match MemoryTracker::load_state(&path) {
+        Err(memscope_rs::TrackingError::StateVersionMismatch { found, expected }) => {
+            assert_eq!(found, 999);
+            assert_eq!(expected, memscope_rs::state::STATE_FORMAT_VERSION);
+        }
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("loaded a state file with the wrong version"),
+    }
+}
+
+#[test]
+fn test_group_by_power_of_two() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 100).unwrap();
+    tracker.track_allocation(0x2000, 128).unwrap();
+    tracker.track_allocation(0x3000, 129).unwrap();
+    tracker.track_allocation(0x4000, 1).unwrap();
+
+    assert_eq!(
+        tracker.group_by_power_of_two().unwrap(),
+        vec![(1, 1, 1), (128, 2, 228), (256, 1, 129)]
+    );
+}
+
+#[test]
+fn test_resource_units_are_tracked_separately() {
+    use memscope_rs::types::ResourceUnit;
+
+    let gpu = ResourceUnit::Custom("gpu_bytes".to_string());
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .track_resource_allocation(0x1000, 1 << 20, gpu.clone())
+        .unwrap();
+    tracker
+        .track_resource_allocation(3, 1, ResourceUnit::Handles)
+        .unwrap();
+    tracker
+        .track_resource_allocation(4, 1, ResourceUnit::Handles)
+        .unwrap();
+    tracker
+        .track_resource_deallocation(3, &ResourceUnit::Handles)
+        .unwrap();
+
+    let heap = tracker.get_stats_for_unit(&ResourceUnit::Bytes).unwrap();
+    assert_eq!(heap.active_memory, 64);
+    assert_eq!(heap.total_allocations, 1);
+
+    let gpu_stats = tracker.get_stats_for_unit(&gpu).unwrap();
+    assert_eq!(gpu_stats.active_memory, 1 << 20);
+
+    let handles = tracker.get_stats_for_unit(&ResourceUnit::Handles).unwrap();
+    assert_eq!(handles.active_allocations, 1);
+    assert_eq!(handles.peak_allocations, 2);
+    assert_eq!(handles.total_deallocations, 1);
+
+    let open = tracker
+        .get_active_allocations_for_unit(&ResourceUnit::Handles)
+        .unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].ptr, 4);
+    assert_eq!(open[0].unit, ResourceUnit::Handles);
+
+    // Heap-only views never see other units
+    assert_eq!(tracker.get_active_allocations().unwrap().len(), 1);
+    assert_eq!(tracker.get_stats().unwrap().active_memory, 64);
+}
+
+#[test]
+fn test_ideal_vs_actual() {
+    let tracker = MemoryTracker::new();
+    assert_eq!(tracker.ideal_vs_actual().unwrap(), (0, 0));
+
+    tracker.track_allocation(0x1000, 100).unwrap();
+    tracker.track_allocation(0x2000, 64).unwrap();
+    tracker.track_allocation(0x3000, 513).unwrap();
+
+    assert_eq!(tracker.ideal_vs_actual().unwrap(), (677, 128 + 64 + 1024));
+}
+
+#[test]
+fn test_lifetime_histogram() {
+    let tracker = MemoryTracker::new();
+    for i in 0..3 {
+        tracker.track_allocation(0x1000 + i, 8).unwrap();
+        tracker.track_deallocation(0x1000 + i).unwrap();
+    }
+    tracker.track_allocation(0x2000, 8).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(30));
+    tracker.track_deallocation(0x2000).unwrap();
+    // Still active, so not counted
+    tracker.track_allocation(0x3000, 8).unwrap();
+
+    let histogram = tracker.lifetime_histogram(&[20, 5]).unwrap();
+    assert_eq!(histogram, vec![(5, 3), (20, 0), (u64::MAX, 1)]);
+
+    let default = tracker.lifetime_histogram(&[]).unwrap();
+    assert_eq!(
+        default.iter().map(|(bound, _)| *bound).collect::<Vec<_>>(),
+        vec![1, 10, 100, 1_000, 10_000, 100_000, u64::MAX]
+    );
+    assert_eq!(default.iter().map(|(_, count)| count).sum::<usize>(), 4);
+    assert_eq!(default[2], (100, 1));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_legacy_svg_options() {
+    use memscope_rs::types::{LegacySvgOptions, LegacySvgStyle};
+
+    let dir = tempfile::tempdir().unwrap();
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 1024).unwrap();
+    tracker
+        .associate_var(0x1000, "data".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+
+    let simple = dir.path().join("simple.svg");
+    tracker
+        .export_to_svg_with_options(
+            &simple,
+            &LegacySvgOptions {
+                style: LegacySvgStyle::SimpleChart,
+            },
+        )
+        .unwrap();
+    let simple_svg = std::fs::read_to_string(&simple).unwrap();
+    assert!(simple_svg.contains("Compact View"));
+
+    let analysis = dir.path().join("analysis.svg");
+    tracker
+        .export_to_svg_with_options(&analysis, &LegacySvgOptions::default())
+        .unwrap();
+    assert!(!std::fs::read_to_string(&analysis)
+        .unwrap()
+        .contains("Compact View"));
+}
+
+#[test]
+fn test_overhead_ratio() {
+    let tracker = MemoryTracker::new();
+    assert!(tracker.self_overhead_bytes().unwrap() > 0);
+
+    // Many tiny allocations: bookkeeping dwarfs the tracked bytes
+    for i in 0..100 {
+        tracker.track_allocation(0x1000 + i * 8, 8).unwrap();
+    }
+    let tiny = tracker.overhead_ratio().unwrap();
+    assert!(tiny > 1.0);
+
+    // One huge allocation drowns the overhead
+    tracker.track_allocation(0x10_0000, 64 << 20).unwrap();
+    let large = tracker.overhead_ratio().unwrap();
+    assert!(large < 0.01);
+
+    let empty = MemoryTracker::new();
+    assert_eq!(empty.overhead_ratio().unwrap(), f64::INFINITY);
+}
+
+#[test]
+fn test_distinct_type_and_var_names() {
+    let tracker = MemoryTracker::new();
+    let allocations = [
+        (0x1000, "b", "Vec<u8>"),
+        (0x2000, "a", "String"),
+        (0x3000, "c", "Vec<u8>"),
+    ];
+    for (ptr, var, type_name) in allocations {
+        tracker.track_allocation(ptr, 16).unwrap();
+        tracker
+            .associate_var(ptr, var.to_string(), type_name.to_string())
+            .unwrap();
+    }
+    // Freed allocations still count through the history
+    tracker.track_deallocation(0x2000).unwrap();
+    // Unassociated allocations contribute nothing
+    tracker.track_allocation(0x4000, 16).unwrap();
+
+    assert_eq!(
+        tracker.distinct_type_names().unwrap(),
+        vec!["String".to_string(), "Vec<u8>".to_string()]
+    );
+    assert_eq!(
+        tracker.distinct_var_names().unwrap(),
+        vec!["a".to_string(), "b".to_string(), "c".to_string()]
+    );
+}
+
+#[test]
+fn test_warmup_allocations_are_not_leaks() {
+    use std::time::Duration;
+
+    let tracker = MemoryTracker::new();
+    tracker.begin_warmup();
+    tracker.track_allocation(0x1000, 4096).unwrap();
+    tracker.end_warmup();
+    tracker.track_allocation(0x2000, 64).unwrap();
+    tracker.track_allocation(0x3000, 128).unwrap();
+
+    let leaks = tracker.detect_leaks(Duration::ZERO).unwrap();
+    assert_eq!(
+        leaks.iter().map(|a| a.ptr).collect::<Vec<_>>(),
+        vec![0x3000, 0x2000]
+    );
+    assert!(tracker
+        .get_active_allocations()
+        .unwrap()
+        .iter()
+        .any(|a| a.ptr == 0x1000 && a.warmup));
+
+    assert!(tracker
+        .detect_leaks(Duration::from_secs(3600))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_memory_by_type_on_large_active_set() {
+    // Large enough to take the parallel path with the `parallel` feature and several workers
+    let tracker = MemoryTracker::new();
+    for i in 0..60_000 {
+        let ptr = 0x1000 + i * 16;
+        tracker.track_allocation(ptr, 16).unwrap();
+        if i % 3 == 0 {
+            tracker
+                .associate_var(ptr, format!("v{i}"), format!("T{}", i % 7))
+                .unwrap();
+        }
+    }
+
+    let by_type = tracker.get_memory_by_type().unwrap();
+    assert_eq!(by_type.len(), 8);
+    assert_eq!(
+        by_type.iter().map(|t| t.allocation_count).sum::<usize>(),
+        60_000
+    );
+    assert_eq!(
+        by_type.iter().map(|t| t.total_size).sum::<usize>(),
+        60_000 * 16
+    );
+    assert_eq!(by_type[0].type_name, "Unknown");
+    assert_eq!(by_type[0].allocation_count, 40_000);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parallel_aggregation_matches_serial() {
+    use memscope_rs::test_support::{aggregate_by_type_parallel, aggregate_by_type_serial};
+
+    // Spans several parallel chunks, whatever the number of rayon workers
+    let entries: Vec<(Option<String>, usize)> = (0..60_000)
+        .map(|i| ((i % 5 != 0).then(|| format!("T{}", i % 7)), 8 + i % 100))
+        .collect();
+    assert_eq!(
+        aggregate_by_type_parallel(&entries),
+        aggregate_by_type_serial(&entries)
+    );
+}
+
+#[test]
+fn test_memory_by_type_breaks_size_ties_by_name() {
+    let tracker = MemoryTracker::new();
+    for (i, type_name) in ["Zeta", "Alpha", "Mid", "Big"].iter().enumerate() {
+        let ptr = 0x1000 + i * 0x100;
+        let size = if *type_name == "Big" { 128 } else { 64 };
+        tracker.track_allocation(ptr, size).unwrap();
+        tracker
+            .associate_var(ptr, format!("v{i}"), type_name.to_string())
+            .unwrap();
+    }
+
+    let names: Vec<String> = tracker
+        .get_memory_by_type()
+        .unwrap()
+        .into_iter()
+        .map(|t| t.type_name)
+        .collect();
+    assert_eq!(names, ["Big", "Alpha", "Mid", "Zeta"]);
+}
+
+#[test]
+fn test_memory_by_crate() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    // Attribution only, the allocation is already tracked
+    tracker
+        .track_crate_allocation(0x1000, 64, "storage")
+        .unwrap();
+    tracker
+        .track_crate_allocation(0x2000, 256, "network")
+        .unwrap();
+    tracker
+        .track_crate_allocation(0x3000, 32, "storage")
+        .unwrap();
+    tracker.track_allocation(0x4000, 8).unwrap();
+
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.total_allocations, 4);
+    assert_eq!(stats.ptr_reuse_collisions, 0);
+
+    let by_crate = tracker.get_memory_by_crate().unwrap();
+    let summary: Vec<_> = by_crate
+        .iter()
+        .map(|c| (c.crate_name.as_str(), c.total_size, c.allocation_count))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("network", 256, 1), ("storage", 96, 2), ("Unknown", 8, 1)]
+    );
+}
+
+#[test]
+fn test_track_crate_macro_uses_calling_crate() {
+    let buffer = vec![0u8; 4096];
+    let ptr = buffer.as_ptr() as usize;
+    memscope_rs::track_crate!(ptr, buffer.capacity()).unwrap();
+
+    let tracker = memscope_rs::get_global_tracker();
+    let attributed = tracker
+        .get_active_allocations()
+        .unwrap()
+        .into_iter()
+        .find(|a| a.ptr == ptr)
+        .and_then(|a| a.source_crate);
+    assert_eq!(attributed.as_deref(), Some(env!("CARGO_PKG_NAME")));
+}
+
+#[test]
+fn test_memory_timeline() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 100).unwrap();
+    tracker.track_allocation(0x2000, 50).unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+
+    let live: Vec<usize> = tracker
+        .memory_timeline()
+        .unwrap()
+        .into_iter()
+        .map(|(_, live)| live)
+        .collect();
+    assert_eq!(live, vec![100, 150, 50]);
+}
+
+#[test]
+fn test_growth_acceleration() {
+    use std::time::Duration;
+
+    let tracker = MemoryTracker::new();
+    assert_eq!(tracker.growth_acceleration(100).unwrap(), 0.0);
+
+    // Slow growth in the earlier window, fast growth in the later one
+    tracker.track_allocation(0x1000, 100).unwrap();
+    std::thread::sleep(Duration::from_millis(150));
+    tracker.track_allocation(0x2000, 1000).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+
+    assert!(tracker.growth_acceleration(100).unwrap() > 0.0);
+    assert_eq!(tracker.growth_acceleration(0).unwrap(), 0.0);
+}
+
+#[test]
+fn test_allocation_ids_survive_pointer_reuse() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    let first = tracker.get_allocation_by_ptr(0x1000).unwrap().unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+
+    // The allocator hands out the same address again
+    tracker.track_allocation(0x1000, 128).unwrap();
+    let second = tracker.get_allocation_by_ptr(0x1000).unwrap().unwrap();
+    assert_ne!(first.alloc_id(), second.alloc_id());
+
+    let freed = tracker
+        .get_allocation_by_id(first.alloc_id())
+        .unwrap()
+        .unwrap();
+    assert_eq!(freed.size, 64);
+    assert!(!freed.is_active());
+
+    let live = tracker
+        .get_allocation_by_id(second.alloc_id())
+        .unwrap()
+        .unwrap();
+    assert_eq!(live.size, 128);
+    assert!(live.is_active());
+
+    assert!(tracker.get_allocation_by_id(u64::MAX).unwrap().is_none());
+    assert!(tracker.get_allocation_by_ptr(0x2000).unwrap().is_none());
+}
+
+#[test]
+fn test_retained_size_and_measure_value() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 100).unwrap();
+    tracker.track_allocation(0x2000, 50).unwrap();
+    tracker.track_allocation(0x3000, 25).unwrap();
+    tracker.link_child(0x1000, 0x2000).unwrap();
+    tracker.link_child(0x2000, 0x3000).unwrap();
+    // Cycles are only followed once
+    tracker.link_child(0x3000, 0x1000).unwrap();
+
+    assert_eq!(tracker.retained_size(0x1000).unwrap(), 175);
+    assert_eq!(tracker.retained_size(0x4000).unwrap(), 0);
+
+    tracker.track_deallocation(0x3000).unwrap();
+    assert_eq!(tracker.retained_size(0x1000).unwrap(), 150);
+
+    let state = [0u64; 8];
+    let plain = tracker.measure_value(&state, "state").unwrap();
+    assert_eq!(plain.shallow_size, 64);
+    assert_eq!(plain.retained_size, 64);
+
+    tracker.link_child(state.as_ptr() as usize, 0x2000).unwrap();
+    let linked = tracker.measure_value(&state, "state").unwrap();
+    assert_eq!(linked.var_name, "state");
+    assert_eq!(linked.retained_size, 64 + 50);
+}
+
+#[test]
+fn test_reconcile_corrects_stats_drift() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    // Synthetic entries enter the active map without touching the statistics
+    tracker
+        .associate_var(0x2000, "shared".to_string(), "Arc<String>".to_string())
+        .unwrap();
+
+    let before = tracker.verify_consistency().unwrap();
+    assert!(!before.is_consistent());
+
+    let correction = tracker.reconcile().unwrap();
+    assert_eq!(correction.allocation_drift(), 1);
+    assert!(correction.memory_drift() > 0);
+
+    let after = tracker.verify_consistency().unwrap();
+    assert!(after.is_consistent());
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.active_allocations, 2);
+    assert!(stats.peak_allocations >= 2);
+
+    assert_eq!(tracker.reconcile().unwrap().allocation_drift(), 0);
+}
+
+#[test]
+fn test_auto_reconcile() {
+    let tracker = MemoryTracker::new();
+    tracker.set_auto_reconcile(Some(2));
+    tracker
+        .associate_var(0x2000, "shared".to_string(), "Arc<String>".to_string())
+        .unwrap();
+
+    tracker.track_allocation(0x1000, 64).unwrap();
+    assert!(!tracker.verify_consistency().unwrap().is_consistent());
+    tracker.track_allocation(0x3000, 64).unwrap();
+    assert!(tracker.verify_consistency().unwrap().is_consistent());
+}
+
+#[test]
+fn test_persistent_vs_transient() {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 4096).unwrap();
+    std::thread::sleep(Duration::from_millis(30));
+    tracker.track_allocation(0x2000, 64).unwrap();
+    tracker.track_allocation(0x3000, 32).unwrap();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    assert_eq!(
+        tracker.persistent_vs_transient(20, now).unwrap(),
+        (4096, 96)
+    );
+    assert_eq!(
+        tracker.persistent_vs_transient(60_000, now).unwrap(),
+        (0, 4192)
+    );
+}
+
+#[test]
+fn test_alignment_waste_report() {
+    let tracker = MemoryTracker::new();
+    tracker.track_aligned_allocation(0x1000, 13, 8).unwrap();
+    tracker
+        .associate_var(0x1000, "header".to_string(), "Header".to_string())
+        .unwrap();
+    tracker.track_aligned_allocation(0x2000, 64, 16).unwrap();
+    tracker.track_aligned_allocation(0x3000, 1, 4).unwrap();
+    // Unknown alignment is skipped
+    tracker.track_allocation(0x4000, 3).unwrap();
+
+    assert_eq!(tracker.alignment_waste_report().unwrap(), 3 + 3);
+    let by_type = tracker.alignment_waste_by_type().unwrap();
+    assert_eq!(by_type.len(), 2);
+    assert_eq!(by_type["Header"], 3);
+    assert_eq!(by_type["Unknown"], 3);
+}
+
+#[test]
+fn test_nested_scopes_report_own_activity() {
+    let tracker = MemoryTracker::new();
+
+    let outer = tracker.begin_scope("outer");
+    tracker.track_allocation(0x1000, 100).unwrap();
+
+    let inner = tracker.begin_scope("inner");
+    tracker.track_allocation(0x2000, 40).unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+    let inner_report = inner.finish();
+
+    tracker.track_allocation(0x3000, 10).unwrap();
+    let outer_report = outer.finish();
+
+    assert_eq!(inner_report.name, "inner");
+    assert_eq!(inner_report.depth, 1);
+    assert_eq!(inner_report.allocated_bytes, 40);
+    assert_eq!(inner_report.deallocated_bytes, 100);
+    assert_eq!(inner_report.net_bytes(), -60);
+
+    assert_eq!(outer_report.depth, 0);
+    assert_eq!(outer_report.allocated_bytes, 110);
+    assert_eq!(outer_report.allocation_count, 2);
+    assert_eq!(outer_report.deallocated_bytes, 0);
+}
+
+#[test]
+fn test_inclusive_scope_counts_children() {
+    use memscope_rs::types::{ScopeAttribution, ScopeOptions};
+
+    let tracker = MemoryTracker::new();
+    let options = ScopeOptions {
+        attribution: ScopeAttribution::Inclusive,
+        ..Default::default()
+    };
+
+    let outer = tracker.begin_scope_with_options("outer", &options);
+    tracker.track_allocation(0x1000, 100).unwrap();
+    {
+        let _inner = tracker.begin_scope("inner");
+        tracker.track_allocation(0x2000, 40).unwrap();
+    }
+    let report = outer.finish();
+
+    assert_eq!(report.allocated_bytes, 140);
+    assert_eq!(report.allocation_count, 2);
+
+    // Scopes are per thread
+    let handle_tracker = std::sync::Arc::new(MemoryTracker::new());
+    let scope = handle_tracker.begin_scope("main");
+    let other = std::sync::Arc::clone(&handle_tracker);
+    std::thread::spawn(move || other.track_allocation(0x5000, 64).unwrap())
+        .join()
+        .unwrap();
+    assert_eq!(scope.finish().allocated_bytes, 0);
+}
+
+#[test]
+fn test_scope_reports_escaped_allocations() {
+    use memscope_rs::types::ScopeOptions;
+
+    let tracker = MemoryTracker::new();
+    let options = ScopeOptions {
+        track_escapes: true,
+        ..Default::default()
+    };
+
+    let scope = tracker.begin_scope_with_options("suspect", &options);
+    tracker.track_allocation(0x1000, 16).unwrap();
+    tracker.track_allocation(0x2000, 16).unwrap();
+    tracker.track_allocation(0x3000, 16).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+    // An address freed in the scope and reused by another thread is not an escape
+    tracker.track_deallocation(0x3000).unwrap();
+    std::thread::scope(|s| {
+        s.spawn(|| tracker.track_allocation(0x3000, 16).unwrap());
+    });
+    let report = scope.finish();
+    assert_eq!(report.escaped_ptrs, Some(vec![0x1000]));
+
+    // Without the option nothing is recorded
+    let scope = tracker.begin_scope("plain");
+    tracker.track_allocation(0x4000, 16).unwrap();
+    assert_eq!(scope.finish().escaped_ptrs, None);
+}
+
+#[test]
+fn test_delta_since_independent_baselines() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 100).unwrap();
+
+    let first = tracker.mark_baseline();
+    tracker.track_allocation(0x2000, 50).unwrap();
+    let second = tracker.mark_baseline();
+    tracker.track_deallocation(0x1000).unwrap();
+
+    let since_first = tracker.delta_since(&first).unwrap();
+    assert_eq!(since_first.allocations, 1);
+    assert_eq!(since_first.allocated_bytes, 50);
+    assert_eq!(since_first.deallocated_bytes, 100);
+    assert_eq!(since_first.active_memory_change, -50);
+    assert_eq!(since_first.active_allocations_change, 0);
+
+    let since_second = tracker.delta_since(&second).unwrap();
+    assert_eq!(since_second.allocations, 0);
+    assert_eq!(since_second.active_memory_change, -100);
+
+    // Baselines can be queried again later
+    tracker.track_allocation(0x3000, 10).unwrap();
+    assert_eq!(tracker.delta_since(&first).unwrap().allocated_bytes, 60);
+}
+
+#[test]
+fn test_shutdown_runs_finalizers_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let tracker = MemoryTracker::new();
+    for _ in 0..2 {
+        let runs = Arc::clone(&runs);
+        tracker.on_shutdown(Box::new(move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+        }));
+    }
+
+    tracker.shutdown().unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    tracker.shutdown().unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+    // Finalizers still pending when the tracker is dropped run then
+    let dropped_runs = Arc::clone(&runs);
+    tracker.on_shutdown(Box::new(move || {
+        dropped_runs.fetch_add(1, Ordering::SeqCst);
+    }));
+    drop(tracker);
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_power_of_two_breakdown() {
+    use memscope_rs::types::SizeStat;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 4096).unwrap();
+    tracker.track_allocation(0x3000, 100).unwrap();
+    tracker.track_allocation(0x4000, 0).unwrap();
+
+    let (pow2, non_pow2) = tracker.power_of_two_breakdown().unwrap();
+    assert_eq!(
+        pow2,
+        SizeStat {
+            count: 2,
+            total_bytes: 4160
+        }
+    );
+    assert_eq!(
+        non_pow2,
+        SizeStat {
+            count: 2,
+            total_bytes: 100
+        }
+    );
+}
+
+#[test]
+fn test_expected_persistent_allocations_are_not_leaks() {
+    use memscope_rs::TrackingError;
+    use std::time::Duration;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 4096).unwrap();
+    tracker.track_allocation(0x2000, 64).unwrap();
+    tracker.mark_expected_persistent(0x1000).unwrap();
+
+    let leaks = tracker.detect_leaks(Duration::ZERO).unwrap();
+    assert_eq!(
+        leaks.iter().map(|a| a.ptr).collect::<Vec<_>>(),
+        vec![0x2000]
+    );
+
+    assert!(matches!(
+        tracker.mark_expected_persistent(0x9000),
+        Err(TrackingError::InvalidPointer { ptr: 0x9000 })
+    ));
+}
+
+#[test]
+fn test_top_allocations_by_coverage() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 700).unwrap();
+    tracker.track_allocation(0x2000, 200).unwrap();
+    for i in 0..10 {
+        tracker.track_allocation(0x3000 + i * 16, 10).unwrap();
+    }
+
+    let ptrs = |fraction| {
+        tracker
+            .top_allocations_by_coverage(fraction)
+            .unwrap()
+            .iter()
+            .map(|a| a.ptr)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ptrs(0.5), vec![0x1000]);
+    assert_eq!(ptrs(0.8), vec![0x1000, 0x2000]);
+    assert_eq!(ptrs(1.0).len(), 12);
+    assert!(ptrs(0.0).is_empty());
+}
+
+#[test]
+fn test_peak_by_type() {
+    let tracker = MemoryTracker::new();
+    for i in 0..4 {
+        let ptr = 0x1000 + i * 0x100;
+        tracker.track_allocation(ptr, 100).unwrap();
+        tracker
+            .associate_var(ptr, format!("buf{i}"), "Vec<u8>".to_string())
+            .unwrap();
+    }
+    tracker.track_allocation(0x9000, 32).unwrap();
+    tracker
+        .associate_var(0x9000, "name".to_string(), "String".to_string())
+        .unwrap();
+
+    for i in 0..3 {
+        tracker.track_deallocation(0x1000 + i * 0x100).unwrap();
+    }
+    tracker.track_allocation(0xA000, 50).unwrap();
+    tracker
+        .associate_var(0xA000, "small".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+
+    let peaks = tracker.get_peak_by_type().unwrap();
+    assert_eq!(peaks["Vec<u8>"], 400);
+    assert_eq!(peaks["String"], 32);
+}
+
+#[test]
+fn test_peak_memory_by_type_sorted_by_peak() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 300).unwrap();
+    tracker.track_allocation(0x2000, 200).unwrap();
+    tracker.track_allocation(0x3000, 100).unwrap();
+
+    // Typed only after tracking, the bytes still count towards the type
+    tracker
+        .associate_var(0x1000, "a".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker
+        .associate_var(0x2000, "b".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker
+        .associate_var(0x3000, "c".to_string(), "String".to_string())
+        .unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+
+    let usage = tracker.get_peak_memory_by_type().unwrap();
+    let rows: Vec<(&str, usize, usize)> = usage
+        .iter()
+        .map(|u| (u.type_name.as_str(), u.current_size, u.peak_size))
+        .collect();
+    assert_eq!(rows, vec![("Vec<u8>", 200, 500), ("String", 100, 100)]);
+}
+
+#[test]
+fn test_allocation_burst_detector() {
+    use std::time::Duration;
+
+    let tracker = MemoryTracker::new();
+    let mut ptr = 0x1000;
+    let mut allocate = |count: usize| {
+        for _ in 0..count {
+            tracker.track_allocation(ptr, 16).unwrap();
+            ptr += 16;
+        }
+    };
+
+    allocate(50);
+    std::thread::sleep(Duration::from_millis(100));
+    allocate(2);
+    std::thread::sleep(Duration::from_millis(100));
+    allocate(50);
+
+    let bursts = tracker.allocation_burst_detector(20, 20).unwrap();
+    assert_eq!(bursts.len(), 2);
+    assert!(bursts.iter().all(|b| b.allocation_count >= 21));
+    assert!(bursts[0].end_ms < bursts[1].start_ms);
+    assert!(tracker
+        .allocation_burst_detector(20, 1000)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_allocation_info_display() {
+    use memscope_rs::AllocationInfo;
+
+    let mut allocation = AllocationInfo::new(0x1000, 1536);
+    assert!(allocation.to_string().starts_with("0x1000 size=1.5KB age="));
+
+    allocation.type_name = Some("Vec<u8>".to_string());
+    allocation.var_name = Some("buffer".to_string());
+    allocation.seq = 7;
+    allocation.timestamp_dealloc = Some(allocation.timestamp_alloc + 25);
+    assert_eq!(
+        allocation.to_string(),
+        "0x1000 id=7 size=1.5KB type=Vec<u8> var=buffer lifetime=25ms"
+    );
+}
+
+#[test]
+fn test_synthetic_deallocation_keeps_active_memory_consistent() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    for (ptr, name) in [(0x2000, "shared"), (0x3000, "other")] {
+        tracker
+            .associate_var(ptr, name.to_string(), "Arc<String>".to_string())
+            .unwrap();
+    }
+    assert!(
+        tracker
+            .get_allocation_by_ptr(0x2000)
+            .unwrap()
+            .unwrap()
+            .size_estimated
+    );
+
+    // Freeing one synthetic entry while another is alive must not subtract an
+    // estimate never counted, nor fold the other estimate in
+    tracker.track_deallocation(0x2000).unwrap();
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.active_memory, 64);
+    assert_eq!(stats.active_allocations, 1);
+    assert_eq!(stats.total_deallocations, 0);
+    assert_eq!(stats.estimated_deallocations, 1);
+    assert!(tracker.get_allocation_by_ptr(0x3000).unwrap().is_some());
+    let freed = tracker
+        .get_allocation_history()
+        .unwrap()
+        .into_iter()
+        .filter(|a| a.ptr == 0x2000)
+        .count();
+    assert_eq!(freed, 0);
+
+    // After a reconciliation folded both live estimates into the statistics,
+    // freeing one takes exactly its own estimate out again
+    tracker
+        .associate_var(0x4000, "third".to_string(), "Rc<Vec<u8>>".to_string())
+        .unwrap();
+    tracker.reconcile().unwrap();
+    let estimate = |ptr| tracker.get_allocation_by_ptr(ptr).unwrap().unwrap().size;
+    let (estimate_3000, estimate_4000) = (estimate(0x3000), estimate(0x4000));
+    assert_eq!(
+        tracker.get_stats().unwrap().active_memory,
+        64 + estimate_3000 + estimate_4000
+    );
+    tracker.track_deallocation(0x3000).unwrap();
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.active_memory, 64 + estimate_4000);
+    assert_eq!(stats.active_allocations, 2);
+    assert_eq!(stats.estimated_deallocations, 2);
+    assert!(tracker.verify_consistency().unwrap().is_consistent());
+}
+
+#[test]
+fn test_churn_efficiency() {
+    let tracker = MemoryTracker::new();
+    assert_eq!(tracker.churn_efficiency().unwrap(), 0.0);
+
+    for i in 0..99 {
+        tracker.track_allocation(0x10_0000 + i * 16, 16).unwrap();
+        tracker.track_deallocation(0x10_0000 + i * 16).unwrap();
+    }
+    tracker.track_allocation(0x1000, 50).unwrap();
+    assert_eq!(tracker.churn_efficiency().unwrap(), 2.0);
+}
+
+#[test]
+fn test_text_report_sections() {
+    use memscope_rs::types::ReportOptions;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 2048).unwrap();
+    tracker
+        .associate_var(0x1000, "buffer".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+
+    let full = tracker.text_report(&ReportOptions::default()).unwrap();
+    for section in [
+        "Memory report",
+        "Top types",
+        "Leak candidates",
+        "Health",
+        "Size percentiles",
+    ] {
+        assert!(full.contains(section), "missing {section} in:\n{full}");
+    }
+    assert!(full.contains("1. Vec<u8> 2.0KB in 1 allocations"));
+
+    let ci_gate = tracker
+        .text_report(&ReportOptions {
+            top_types: 0,
+            leak_age_ms: 0,
+            include_percentiles: false,
+            ..Default::default()
+        })
+        .unwrap();
+    assert!(!ci_gate.contains("Top types"));
+    assert!(!ci_gate.contains("Size percentiles"));
+    assert!(ci_gate.contains("Leak candidates (older than 0ms)\n  1 allocations, 2.0KB"));
+    assert!(ci_gate.contains("var=buffer"));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_memory_by_span() {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let subscriber = tracing_subscriber::registry().with(tracing_subscriber::fmt::layer());
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 16).unwrap();
+    let span = tracing::info_span!("handle_request");
+    let span_id = span.id().map(|id| id.into_u64());
+    {
+        let _entered = span.enter();
+        tracker.track_allocation(0x2000, 256).unwrap();
+        tracker.track_allocation(0x3000, 256).unwrap();
+    }
+
+    let by_span = tracker.get_memory_by_span().unwrap();
+    assert_eq!(by_span.len(), 2);
+    assert!(span_id.is_some());
+    assert_eq!(by_span[0].span_id, span_id);
+    assert_eq!(by_span[0].total_size, 512);
+    assert_eq!(by_span[1].span_id, None);
+}
+
+#[test]
+fn test_coalesce_window_drops_short_lived_pairs() {
+    let tracker = MemoryTracker::new();
+    tracker.set_coalesce_window_ms(Some(60_000));
+
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 128).unwrap();
+    tracker.track_allocation(0x3000, 256).unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+
+    let history = tracker.get_allocation_history().unwrap();
+    assert_eq!(history.len(), 2);
+    assert!(history.iter().all(|allocation| allocation.ptr != 0x1000));
+
+    // Entries shifted by the removal are still matched when freed
+    tracker.set_coalesce_window_ms(None);
+    tracker.track_deallocation(0x3000).unwrap();
+    let history = tracker.get_allocation_history().unwrap();
+    let freed = history.iter().find(|a| a.ptr == 0x3000).unwrap();
+    assert!(!freed.is_active());
+
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.coalesced, 1);
+    assert_eq!(stats.total_deallocations, 2);
+}
+
+#[test]
+fn test_largest_retained_allocation() {
+    let tracker = MemoryTracker::new();
+    assert!(tracker.largest_retained_allocation().unwrap().is_none());
+
+    // A container of small containers outweighs the biggest single buffer
+    tracker.track_allocation(0x1000, 300).unwrap();
+    tracker.track_allocation(0x2000, 48).unwrap();
+    for (i, child) in [0x3000, 0x4000, 0x5000].into_iter().enumerate() {
+        tracker.track_allocation(child, 100 + i).unwrap();
+        tracker.link_child(0x2000, child).unwrap();
+    }
+
+    let (allocation, retained) = tracker.largest_retained_allocation().unwrap().unwrap();
+    assert_eq!(allocation.ptr, 0x2000);
+    assert_eq!(retained, 48 + 100 + 101 + 102);
+
+    tracker.track_deallocation(0x5000).unwrap();
+    let (allocation, retained) = tracker.largest_retained_allocation().unwrap().unwrap();
+    assert_eq!(allocation.ptr, 0x1000);
+    assert_eq!(retained, 300);
+}
+
+#[test]
+fn test_survival_by_generation() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 64).unwrap();
+
+    assert_eq!(tracker.advance_generation(), 1);
+    tracker.track_allocation(0x3000, 64).unwrap();
+    tracker.track_allocation(0x4000, 64).unwrap();
+    tracker.track_allocation(0x5000, 64).unwrap();
+    tracker.track_deallocation(0x3000).unwrap();
+    tracker.track_deallocation(0x4000).unwrap();
+
+    let info = tracker.get_allocation_by_ptr(0x5000).unwrap().unwrap();
+    assert_eq!(info.generation, 1);
+    assert_eq!(tracker.survival_by_generation(), vec![(0, 2, 2), (1, 3, 1)]);
+}
+
+#[test]
+fn test_annotate_matching() {
+    let tracker = MemoryTracker::new();
+    for (ptr, size) in [(0x1000, 2 << 20), (0x2000, 512), (0x3000, 4 << 20)] {
+        tracker.track_allocation(ptr, size).unwrap();
+        tracker
+            .associate_var(ptr, format!("buf_{ptr:x}"), "Vec<u8>".to_string())
+            .unwrap();
+    }
+
+    let is_large_buffer =
+        |a: &AllocationInfo| a.type_name.as_deref() == Some("Vec<u8>") && a.size > 1 << 20;
+    let tagged = tracker
+        .annotate_matching(is_large_buffer, "suspect".to_string())
+        .unwrap();
+    assert_eq!(tagged, 2);
+
+    let suspect = tracker.get_allocation_by_ptr(0x3000).unwrap().unwrap();
+    assert_eq!(suspect.metadata_tags, vec!["suspect".to_string()]);
+    let small = tracker.get_allocation_by_ptr(0x2000).unwrap().unwrap();
+    assert!(small.metadata_tags.is_empty());
+
+    // Re-applying the same tag changes nothing
+    let tagged = tracker
+        .annotate_matching(is_large_buffer, "suspect".to_string())
+        .unwrap();
+    assert_eq!(tagged, 0);
+}
+
+#[test]
+fn test_overallocation_report() {
+    let tracker = MemoryTracker::new();
+    tracker
+        .track_allocation_with_usable(0x1000, 100, 112)
+        .unwrap();
+    tracker
+        .track_allocation_with_usable(0x2000, 24, 24)
+        .unwrap();
+    tracker
+        .track_allocation_with_usable(0x3000, 1000, 1024)
+        .unwrap();
+    // Unknown usable size is skipped
+    tracker.track_allocation(0x4000, 10).unwrap();
+
+    let info = tracker.get_allocation_by_ptr(0x1000).unwrap().unwrap();
+    assert_eq!(info.size, 100);
+    assert_eq!(info.usable_size, Some(112));
+    assert_eq!(tracker.overallocation_report().unwrap(), 12 + 24);
+
+    tracker.track_deallocation(0x3000).unwrap();
+    assert_eq!(tracker.overallocation_report().unwrap(), 12);
+}
+
+#[test]
+fn test_monotonic_growth_types() {
+    let tracker = MemoryTracker::new();
+    let track = |ptr: usize, size: usize, type_name: &str| {
+        tracker.track_allocation(ptr, size).unwrap();
+        tracker
+            .associate_var(ptr, format!("v{ptr:x}"), type_name.to_string())
+            .unwrap();
+    };
+
+    track(0x1000, 100, "Cache");
+    track(0x2000, 100, "Buffer");
+    track(0x3000, 100, "Config");
+    tracker.record_type_sample().unwrap();
+    assert!(tracker.monotonic_growth_types().is_empty());
+
+    track(0x1100, 100, "Cache");
+    track(0x2100, 100, "Buffer");
+    tracker.record_type_sample().unwrap();
+
+    track(0x1200, 100, "Cache");
+    tracker.track_deallocation(0x2000).unwrap();
+    tracker.track_deallocation(0x2100).unwrap();
+    track(0x4000, 100, "Session");
+    tracker.record_type_sample().unwrap();
+
+    // Buffer shrank and Config stayed flat
+    assert_eq!(
+        tracker.monotonic_growth_types(),
+        vec!["Cache".to_string(), "Session".to_string()]
+    );
+}
+
+#[test]
+fn test_track_with_explicit_timestamps() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation_at(0x1000, 64, 1_000).unwrap();
+    tracker.track_allocation_at(0x2000, 32, 1_500).unwrap();
+    tracker.track_deallocation_at(0x1000, 1_250).unwrap();
+    // Deallocations before the allocation are clamped to a zero lifetime
+    tracker.track_deallocation_at(0x2000, 1_000).unwrap();
+
+    let history = tracker.get_allocation_history().unwrap();
+    assert_eq!(history[0].timestamp_alloc, 1_000);
+    assert_eq!(history[0].timestamp_dealloc, Some(1_250));
+    assert_eq!(history[0].lifetime_ms(), Some(250));
+    assert_eq!(history[1].lifetime_ms(), Some(0));
+
+    assert_eq!(
+        tracker.memory_timeline().unwrap(),
+        vec![(1_000, 64), (1_250, 0), (1_500, 32), (1_500, 0)]
+    );
+}
+
+#[test]
+fn test_pool_out_of_order_frees_are_flagged() {
+    use memscope_rs::types::TrackEvent;
+    use std::sync::{Arc, Mutex};
+
+    let tracker = MemoryTracker::new();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    tracker.add_event_hook(move |event| sink.lock().unwrap().push(event.clone()));
+
+    for ptr in [0x100, 0x200, 0x300] {
+        tracker.track_pool_allocation(7, ptr, 16).unwrap();
+    }
+    tracker.track_pool_allocation(8, 0x900, 16).unwrap();
+
+    // LIFO frees are fine
+    tracker.track_pool_deallocation(7, 0x300).unwrap();
+    assert_eq!(tracker.pool_violations(7), 0);
+
+    // Freeing the bottom while 0x200 is still on top is a violation
+    tracker.track_pool_deallocation(7, 0x100).unwrap();
+    tracker.track_pool_deallocation(7, 0x200).unwrap();
+    assert_eq!(tracker.pool_violations(7), 1);
+    assert_eq!(tracker.pool_violations(8), 0);
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![TrackEvent::PoolViolation {
+            pool_id: 7,
+            ptr: 0x100,
+            expected: 0x200
+        }]
+    );
+
+    // Unknown pools and pointers are rejected; pool allocations stay off the heap stats
+    assert!(tracker.track_pool_deallocation(7, 0x100).is_err());
+    assert!(tracker.track_pool_deallocation(9, 0x100).is_err());
+    assert_eq!(tracker.get_stats().unwrap().total_allocations, 0);
+}
+
+#[cfg(all(unix, feature = "socket-server"))]
+#[test]
+fn test_socket_server_streams_events_to_clients() {
+    use memscope_rs::events::LoggedEvent;
+    use memscope_rs::types::TrackEvent;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.sock");
+    let tracker = MemoryTracker::new();
+    tracker.start_socket_server(&path).unwrap();
+
+    let connect = || {
+        let stream = UnixStream::connect(&path).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(20)))
+            .unwrap();
+        BufReader::new(stream)
+    };
+    // Track until the client has been accepted and receives an event
+    let next_event = |reader: &mut BufReader<UnixStream>, base: usize| {
+        for i in 0..500 {
+            tracker.track_allocation(base + i * 16, 8).unwrap();
+            let mut line = String::new();
+            if reader.read_line(&mut line).is_ok() && line.ends_with('\n') {
+                return serde_json::from_str::<LoggedEvent>(&line).unwrap().event;
+            }
+        }
+        panic!("no event received");
+    };
+
+    let mut first = connect();
+    let mut second = connect();
+    assert!(matches!(
+        next_event(&mut first, 0x10_000),
+        TrackEvent::Alloc { size: 8, .. }
+    ));
+    assert!(matches!(
+        next_event(&mut second, 0x20_000),
+        TrackEvent::Alloc { size: 8, .. }
+    ));
+
+    // A client going away does not affect tracking or the other clients
+    drop(first);
+    for i in 0..100 {
+        tracker.track_allocation(0x30_000 + i * 16, 8).unwrap();
+    }
+    assert!(matches!(
+        next_event(&mut second, 0x40_000),
+        TrackEvent::Alloc { size: 8, .. }
+    ));
+
+    tracker.stop_socket_server().unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_allocation_balance() {
+    let tracker = MemoryTracker::new();
+    assert!(tracker.allocation_balance().is_balanced);
+
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+
+    let balance = tracker.allocation_balance();
+    assert!(!balance.is_balanced);
+    assert_eq!(balance.total_allocations, 2);
+    assert_eq!(balance.total_deallocations, 1);
+    assert_eq!(balance.outstanding, 1);
+    assert_eq!(balance.outstanding_bytes, 32);
+
+    tracker.track_deallocation(0x2000).unwrap();
+    assert!(tracker.allocation_balance().is_balanced);
+}
+
+#[test]
+fn test_memory_by_type_cached() {
+    use std::time::Duration;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "a".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+
+    let first = tracker
+        .get_memory_by_type_cached(Duration::from_secs(60))
+        .unwrap();
+    assert_eq!(first[0].total_size, 64);
+
+    tracker.track_allocation(0x2000, 36).unwrap();
+    tracker
+        .associate_var(0x2000, "b".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+
+    // Fresh enough: the cached breakdown is returned unchanged
+    let cached = tracker
+        .get_memory_by_type_cached(Duration::from_secs(60))
+        .unwrap();
+    assert_eq!(cached[0].total_size, 64);
+
+    // A zero max age always recomputes
+    let fresh = tracker.get_memory_by_type_cached(Duration::ZERO).unwrap();
+    assert_eq!(fresh[0].total_size, 100);
+    assert_eq!(fresh[0].allocation_count, 2);
+}
+
+#[test]
+fn test_leak_report_totals_and_flags() {
+    use std::time::Duration;
+
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 256).unwrap();
+    tracker
+        .associate_var(0x1000, "cache".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x2000, 128).unwrap();
+    tracker
+        .associate_var(0x2000, "buf".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation(0x3000, 64).unwrap();
+    // No allocation behind this pointer: a synthetic entry is created
+    tracker
+        .associate_var(0x4000, "shared".to_string(), "Arc<String>".to_string())
+        .unwrap();
+
+    let report = tracker.leak_report(Duration::ZERO).unwrap();
+    assert_eq!(report.leaks.len(), 4);
+    assert_eq!(
+        report.leaked_bytes,
+        report
+            .leaks
+            .iter()
+            .map(|l| l.allocation.size)
+            .sum::<usize>()
+    );
+    assert_eq!(report.by_type[0].type_name, "Vec<u8>");
+    assert_eq!(report.by_type[0].total_size, 384);
+    assert_eq!(report.by_type[0].allocation_count, 2);
+
+    let flags = |ptr: usize| {
+        let leak = report
+            .leaks
+            .iter()
+            .find(|l| l.allocation.ptr == ptr)
+            .unwrap();
+        (leak.unnamed, leak.synthetic)
+    };
+    assert_eq!(flags(0x1000), (false, false));
+    assert_eq!(flags(0x3000), (true, false));
+    assert_eq!(flags(0x4000), (false, true));
+}
+
+#[test]
+fn test_tracking_fidelity_counts_dropped_events() {
+    let tracker = MemoryTracker::new();
+    let fidelity = tracker.tracking_fidelity();
+    assert_eq!(fidelity.fidelity, 1.0);
+
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 64).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    // The predicate runs while the active map is locked, so these calls are dropped
+    tracker
+        .annotate_matching(
+            |_| {
+                tracker.track_allocation(0x3000, 64).unwrap();
+                tracker.track_deallocation(0x1000).unwrap();
+                false
+            },
+            "busy".to_string(),
+        )
+        .unwrap();
+
+    let fidelity = tracker.tracking_fidelity();
+    assert_eq!(fidelity.tracked_allocations, 2);
+    assert_eq!(fidelity.tracked_deallocations, 1);
+    assert_eq!(fidelity.dropped_allocations, 1);
+    assert_eq!(fidelity.dropped_deallocations, 1);
+    assert_eq!(fidelity.fidelity, 3.0 / 5.0);
+}
+
+#[test]
+fn test_reset_clears_everything() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    tracker.reset().unwrap();
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.total_allocations, 0);
+    assert_eq!(stats.peak_memory, 0);
+    assert!(tracker.get_active_allocations().unwrap().is_empty());
+    assert!(tracker.get_allocation_history().unwrap().is_empty());
+
+    // The tracker is usable again afterwards
+    tracker.track_allocation(0x3000, 16).unwrap();
+    assert_eq!(tracker.get_stats().unwrap().active_memory, 16);
+}
+
+#[test]
+fn test_reset_keep_active_restarts_from_live_baseline() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 1024).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    tracker.reset_keep_active().unwrap();
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.total_allocations, 0);
+    assert_eq!(stats.total_deallocations, 0);
+    assert_eq!(stats.active_allocations, 1);
+    assert_eq!(stats.active_memory, 64);
+    assert_eq!(stats.peak_memory, 64);
+
+    let history = tracker.get_allocation_history().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].ptr, 0x1000);
+
+    // The kept allocation can still be freed normally
+    tracker.track_deallocation(0x1000).unwrap();
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.active_memory, 0);
+    assert_eq!(stats.total_deallocations, 1);
+    assert!(!tracker.get_allocation_history().unwrap()[0].is_active());
+}
+
+#[inline(never)]
+fn allocate_here(tracker: &MemoryTracker, ptr: usize, size: usize) {
+    tracker.track_allocation(ptr, size).unwrap();
+}
+
+#[test]
+fn test_allocations_grouped_by_callsite() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 8).unwrap();
+    assert!(tracker.get_active_allocations().unwrap()[0]
+        .backtrace
+        .is_none());
+
+    let tracker = tracker.with_backtraces(true);
+    allocate_here(&tracker, 0x2000, 512);
+    allocate_here(&tracker, 0x3000, 512);
+
+    let by_callsite = tracker.get_allocations_by_callsite();
+    assert_eq!(by_callsite.len(), 2);
+    let (callsite, allocations) = &by_callsite[0];
+    assert!(callsite.contains("allocate_here"), "callsite: {callsite}");
+    assert_eq!(allocations.len(), 2);
+    assert_eq!(by_callsite[1].0, "Unknown");
+}
+
+#[inline(never)]
+fn replay_here(tracker: &MemoryTracker, ptr: usize) {
+    use memscope_rs::types::TrackEvent;
+    tracker
+        .apply_events(&[TrackEvent::Alloc { ptr, size: 256 }])
+        .unwrap();
+}
+
+#[test]
+fn test_callsite_skips_public_tracker_wrappers() {
+    let tracker = MemoryTracker::new().with_backtraces(true);
+    // Reaches track_allocation through test_support and, for an untracked
+    // pointer, the realloc fallback
+    replay_here(&tracker, 0x1000);
+    tracker.track_reallocation(0x9000, 0x2000, 64).unwrap();
+
+    let frames = tracker
+        .get_allocation_by_ptr(0x1000)
+        .unwrap()
+        .unwrap()
+        .backtrace
+        .unwrap();
+    assert!(frames[0].contains("replay_here"), "frames: {frames:?}");
+    let frames = tracker
+        .get_allocation_by_ptr(0x2000)
+        .unwrap()
+        .unwrap()
+        .backtrace
+        .unwrap();
+    assert!(!frames[0].contains("memscope_rs::"), "frames: {frames:?}");
+}
+
+#[test]
+fn test_history_limit_evicts_oldest_entries() {
+    let tracker = MemoryTracker::with_history_limit(3);
+    assert_eq!(tracker.history_capacity(), Some(3));
+    for i in 0..5 {
+        tracker.track_allocation(0x1000 + i * 0x100, 64).unwrap();
+    }
+    // Freeing an evicted allocation still updates the statistics
+    tracker.track_deallocation(0x1000).unwrap();
+    tracker.track_deallocation(0x1400).unwrap();
+
+    let history = tracker.get_allocation_history().unwrap();
+    let ptrs: Vec<usize> = history.iter().map(|a| a.ptr).collect();
+    assert_eq!(ptrs, vec![0x1200, 0x1300, 0x1400]);
+    assert!(!history[2].is_active());
+    assert_eq!(tracker.history_evicted_count(), 2);
+
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.total_allocations, 5);
+    assert_eq!(stats.total_deallocations, 2);
+    assert_eq!(stats.active_memory, 3 * 64);
+
+    let unbounded = MemoryTracker::new();
+    assert_eq!(unbounded.history_capacity(), None);
+}
+
+#[test]
+fn test_history_limit_zero_records_nothing() {
+    let tracker = MemoryTracker::with_history_limit(0);
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+
+    assert!(tracker.get_allocation_history().unwrap().is_empty());
+    assert_eq!(tracker.history_evicted_count(), 1);
+    assert_eq!(tracker.get_stats().unwrap().total_deallocations, 1);
+}
+
+#[test]
+fn test_memory_by_thread() {
+    let tracker = std::sync::Arc::new(MemoryTracker::new());
+    tracker.track_allocation(0x1000, 64).unwrap();
+
+    let worker = std::sync::Arc::clone(&tracker);
+    std::thread::Builder::new()
+        .name("pipeline-worker".to_string())
+        .spawn(move || {
+            worker.track_allocation(0x2000, 512).unwrap();
+            worker.track_allocation(0x3000, 512).unwrap();
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    let by_thread = tracker.get_memory_by_thread().unwrap();
+    assert_eq!(by_thread.len(), 2);
+    assert_eq!(by_thread[0].thread_name.as_deref(), Some("pipeline-worker"));
+    assert_eq!(by_thread[0].total_size, 1024);
+    assert_eq!(by_thread[0].allocation_count, 2);
+    assert_eq!(by_thread[1].total_size, 64);
+    assert_eq!(
+        by_thread[1].thread_id,
+        format!("{:?}", std::thread::current().id())
+    );
+}
+
+#[test]
+fn test_snapshot_diff_around_a_block() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_allocation(0x2000, 32).unwrap();
+    let before = tracker.snapshot();
+
+    tracker.track_allocation(0x3000, 256).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+    // A reused pointer is a different allocation
+    tracker.track_deallocation(0x1000).unwrap();
+    tracker.track_allocation(0x1000, 16).unwrap();
+    let after = tracker.snapshot();
+
+    let diff = before.diff(&after);
+    assert_eq!(diff.new_allocations, vec![0x1000, 0x3000]);
+    assert_eq!(diff.freed_allocations, vec![0x1000, 0x2000]);
+    assert_eq!(diff.bytes_added, 272);
+    assert_eq!(diff.bytes_removed, 96);
+    assert_eq!(diff.net_count_delta, 0);
+    assert_eq!(diff.net_bytes_delta(), 176);
+    assert_eq!(after.active_allocations(), 2);
+    assert_eq!(after.stats.active_memory, 272);
+
+    let unchanged = after.diff(&tracker.snapshot());
+    assert!(unchanged.new_allocations.is_empty() && unchanged.freed_allocations.is_empty());
+}
+
+#[test]
+fn test_associate_var_typed_uses_real_sizes() {
+    let tracker = MemoryTracker::new();
+    tracker
+        .associate_var_typed::<[u64; 8]>(0x1000, "table".to_string(), None)
+        .unwrap();
+    tracker
+        .associate_var_typed::<Vec<u8>>(0x2000, "buffer".to_string(), Some(1000))
+        .unwrap();
+    tracker.track_allocation(0x3000, 40).unwrap();
+    tracker
+        .associate_var_typed::<String>(0x3000, "name".to_string(), Some(1000))
+        .unwrap();
+
+    let by_ptr = |ptr: usize| {
+        tracker
+            .get_active_allocations()
+            .unwrap()
+            .into_iter()
+            .find(|a| a.ptr == ptr)
+            .unwrap()
+    };
+    let table = by_ptr(0x1000);
+    assert_eq!(table.size, 64);
+    assert_eq!(table.type_name.as_deref(), Some("[u64; 8]"));
+    assert_eq!(by_ptr(0x2000).size, std::mem::size_of::<Vec<u8>>() + 1000);
+    assert_eq!(
+        by_ptr(0x2000).type_name.as_deref(),
+        Some(std::any::type_name::<Vec<u8>>())
+    );
+    // A tracked allocation keeps its real size
+    assert_eq!(by_ptr(0x3000).size, 40);
+    assert_eq!(by_ptr(0x3000).var_name.as_deref(), Some("name"));
+
+    // Freeing a synthetic entry leaves only the real allocation counted
+    tracker.track_deallocation(0x1000).unwrap();
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.active_allocations, 1);
+    assert_eq!(stats.active_memory, 40);
+}
+
+#[test]
+fn test_find_by_var_name_and_prefix() {
+    let tracker = MemoryTracker::new();
+    for (i, ptr) in [0x1000, 0x2000, 0x3000].into_iter().enumerate() {
+        tracker.track_allocation(ptr, 64).unwrap();
+        tracker
+            .associate_var(ptr, format!("buffer_{i}"), "Vec<u8>".to_string())
+            .unwrap();
+    }
+    tracker.track_allocation(0x4000, 8).unwrap();
+    tracker
+        .associate_var(0x4000, "Buffer_3".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    // The same name reassociated with a reallocated buffer
+    tracker.track_allocation(0x5000, 128).unwrap();
+    tracker
+        .associate_var(0x5000, "buffer_1".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_deallocation(0x3000).unwrap();
+
+    let exact: Vec<usize> = tracker
+        .find_by_var_name("buffer_1")
+        .unwrap()
+        .iter()
+        .map(|a| a.ptr)
+        .collect();
+    assert_eq!(exact, vec![0x2000, 0x5000]);
+    assert!(tracker.find_by_var_name("buffer").unwrap().is_empty());
+
+    let prefixed: Vec<usize> = tracker
+        .find_by_var_prefix("buffer_")
+        .unwrap()
+        .iter()
+        .map(|a| a.ptr)
+        .collect();
+    assert_eq!(prefixed, vec![0x1000, 0x2000, 0x5000]);
+}
+
+#[test]
+fn test_top_allocations() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation_at(0x1000, 64, 100).unwrap();
+    tracker.track_allocation_at(0x2000, 512, 300).unwrap();
+    tracker.track_allocation_at(0x3000, 8, 100).unwrap();
+    tracker.track_allocation_at(0x4000, 512, 200).unwrap();
+    tracker.track_allocation_at(0x5000, 256, 100).unwrap();
+
+    let top: Vec<usize> = tracker
+        .top_allocations(3)
+        .unwrap()
+        .iter()
+        .map(|a| a.ptr)
+        .collect();
+    // Equal sizes: the older allocation comes first
+    assert_eq!(top, vec![0x4000, 0x2000, 0x5000]);
+
+    assert_eq!(tracker.top_allocations(10).unwrap().len(), 5);
+    assert_eq!(tracker.top_allocations(10).unwrap()[4].ptr, 0x3000);
+    assert!(tracker.top_allocations(0).unwrap().is_empty());
+}
+
+#[test]
+fn test_size_histogram() {
+    let tracker = MemoryTracker::new();
+    for (ptr, size) in [(0x1000, 8), (0x2000, 9), (0x3000, 16), (0x4000, 100)] {
+        tracker.track_allocation(ptr, size).unwrap();
+    }
+    // A zero-size synthetic entry
+    tracker
+        .associate_var_typed::<()>(0x5000, "unit".to_string(), None)
+        .unwrap();
+
+    let histogram = tracker.size_histogram().unwrap();
+    let buckets: Vec<(usize, usize, usize, usize)> = histogram
+        .buckets
+        .iter()
+        .map(|b| (b.min_size, b.max_size, b.count, b.total_bytes))
+        .collect();
+    assert_eq!(
+        buckets,
+        vec![
+            (0, 8, 2, 8),
+            (9, 16, 2, 25),
+            (17, 32, 0, 0),
+            (33, 64, 0, 0),
+            (65, 128, 1, 100),
+        ]
+    );
+
+    let custom = tracker.size_histogram_with_buckets(&[64, 10]).unwrap();
+    let counts: Vec<(usize, usize)> = custom
+        .buckets
+        .iter()
+        .map(|b| (b.max_size, b.count))
+        .collect();
+    assert_eq!(counts, vec![(10, 3), (64, 1), (usize::MAX, 1)]);
+}
+
+#[test]
+fn test_unknown_deallocations_and_double_frees() {
+    let tracker = MemoryTracker::new();
+    tracker.track_deallocation(0x9000).unwrap();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+    tracker.track_deallocation(0x1000).unwrap();
+    // Without a window, unknown frees are only counted
+    assert_eq!(tracker.get_stats().unwrap().unknown_deallocations, 2);
+    assert!(tracker.suspected_double_frees().is_empty());
+
+    tracker.set_double_free_window(Some(4));
+    tracker.track_allocation(0x2000, 64).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+    tracker.track_deallocation(0x8000).unwrap();
+    // A reallocated pointer may be freed again legitimately
+    tracker.track_allocation(0x2000, 32).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.unknown_deallocations, 4);
+    assert_eq!(stats.total_deallocations, 3);
+    assert_eq!(tracker.suspected_double_frees(), vec![0x2000]);
+}
+
+fn parse_request(tracker: &MemoryTracker) {
+    let _scope = tracker.scope("parse_request");
+    tracker.track_allocation(0x2000, 128).unwrap();
+    tracker.track_allocation(0x3000, 32).unwrap();
+    tracker.track_deallocation(0x3000).unwrap();
+}
+
+#[test]
+fn test_scope_guard_records_delta_on_drop() {
+    let tracker = MemoryTracker::new();
+    {
+        let _scope = tracker.scope("handle");
+        tracker.track_allocation(0x1000, 64).unwrap();
+        parse_request(&tracker);
+        // Discarded reports are not stored
+        drop(tracker.begin_scope("untracked"));
+    }
+
+    let reports = tracker.take_scope_reports();
+    let summary: Vec<(&str, usize, isize, isize)> = reports
+        .iter()
+        .map(|r| (r.name.as_str(), r.depth, r.net_bytes(), r.net_allocations()))
+        .collect();
+    // Each scope reports only its own delta, innermost closed first
+    assert_eq!(
+        summary,
+        vec![("parse_request", 1, 128, 1), ("handle", 0, 64, 1)]
+    );
+    assert!(tracker.scope_reports().is_empty());
+}
+
+#[test]
+fn test_blocking_mode_records_every_event() {
+    // A single shard makes every thread contend for the same lock
+    let tracker = MemoryTracker::new()
+        .with_shard_count(1)
+        .with_blocking_mode(true);
+    assert!(tracker.is_blocking_mode());
+    assert!(!MemoryTracker::new().is_blocking_mode());
+
+    std::thread::scope(|s| {
+        for thread in 0..8 {
+            let tracker = &tracker;
+            s.spawn(move || {
+                for i in 0..2_000 {
+                    let ptr = 0x1000_0000 + thread * 0x10_0000 + i * 16;
+                    tracker.track_allocation(ptr, 16).unwrap();
+                    tracker
+                        .associate_var(ptr, format!("item_{thread}_{i}"), "u128".to_string())
+                        .unwrap();
+                    if i % 2 == 0 {
+                        tracker.track_deallocation(ptr).unwrap();
+                    }
+                }
+            });
+        }
+    });
+
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.total_allocations, 16_000);
+    assert_eq!(stats.total_deallocations, 8_000);
+    assert_eq!(stats.active_allocations, 8_000);
+    assert_eq!(stats.active_memory, 8_000 * 16);
+
+    let active = tracker.get_active_allocations().unwrap();
+    assert_eq!(active.len(), 8_000);
+    assert!(active.iter().all(|a| a.var_name.is_some()));
+
+    let fidelity = tracker.tracking_fidelity();
+    assert_eq!(fidelity.dropped_allocations, 0);
+    assert_eq!(fidelity.dropped_deallocations, 0);
+    assert_eq!(fidelity.dropped_type_updates, 0);
+}
+
+#[test]
+fn test_type_totals_exact_across_shards_in_blocking_mode() {
+    // Every shard shares the per-type totals, so threads on different shards
+    // contend for them
+    let tracker = MemoryTracker::new().with_blocking_mode(true);
+    std::thread::scope(|s| {
+        for thread in 0..8 {
+            let tracker = &tracker;
+            s.spawn(move || {
+                for i in 0..1_000 {
+                    let ptr = 0x1000_0000 + thread * 0x10_0000 + i * 64;
+                    tracker.track_allocation(ptr, 32).unwrap();
+                    tracker
+                        .associate_var(ptr, format!("item_{i}"), "Vec<u8>".to_string())
+                        .unwrap();
+                    if i % 4 == 0 {
+                        tracker.track_deallocation(ptr).unwrap();
+                    }
+                }
+            });
+        }
+    });
+
+    assert_eq!(tracker.tracking_fidelity().dropped_type_updates, 0);
+    let usage = tracker.get_peak_memory_by_type().unwrap();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].current_size, 8 * 750 * 32);
+}
+
+#[test]
+fn test_lifetime_stats_by_type() {
+    let tracker = MemoryTracker::new();
+    let lifetimes = [(0x1000, 10), (0x2000, 40), (0x3000, 20), (0x4000, 30)];
+    for (ptr, lifetime) in lifetimes {
+        tracker.track_allocation_at(ptr, 16, 1_000).unwrap();
+        tracker
+            .associate_var(ptr, format!("buf_{ptr:x}"), "Vec<u8>".to_string())
+            .unwrap();
+        tracker
+            .track_deallocation_at(ptr, 1_000 + lifetime)
+            .unwrap();
+    }
+    tracker.track_allocation_at(0x5000, 16, 1_000).unwrap();
+    tracker
+        .associate_var(0x5000, "alive".to_string(), "Vec<u8>".to_string())
+        .unwrap();
+    tracker.track_allocation_at(0x6000, 8, 1_000).unwrap();
+    tracker.track_deallocation_at(0x6000, 1_005).unwrap();
+    tracker.track_allocation_at(0x7000, 8, 1_000).unwrap();
+    tracker
+        .associate_var(0x7000, "name".to_string(), "String".to_string())
+        .unwrap();
+
+    let stats = tracker.lifetime_stats_by_type().unwrap();
+    let names: Vec<&str> = stats.iter().map(|s| s.type_name.as_str()).collect();
+    assert_eq!(names, vec!["Vec<u8>", "Unknown", "String"]);
+
+    let vec_stats = &stats[0];
+    assert_eq!(vec_stats.completed_count, 4);
+    assert_eq!(vec_stats.still_alive, 1);
+    assert_eq!(vec_stats.min_lifetime_ms, 10);
+    assert_eq!(vec_stats.max_lifetime_ms, 40);
+    assert_eq!(vec_stats.mean_lifetime_ms, 25.0);
+    assert_eq!(vec_stats.median_lifetime_ms, 25.0);
+
+    assert_eq!(stats[1].median_lifetime_ms, 5.0);
+    // Only still-alive allocations: no lifetimes yet
+    assert_eq!(stats[2].completed_count, 0);
+    assert_eq!(stats[2].still_alive, 1);
+    assert_eq!(stats[2].max_lifetime_ms, 0);
+}
+
+#[test]
+fn test_threshold_fires_once_per_crossing() {
+    use std::sync::{Arc, Mutex};
+
+    let tracker = MemoryTracker::new();
+    let fired = Arc::new(Mutex::new(Vec::new()));
+    for bytes in [1_000, 2_000] {
+        let fired = fired.clone();
+        tracker.set_threshold(
+            bytes,
+            Box::new(move |stats| fired.lock().unwrap().push((bytes, stats.active_memory))),
+        );
+    }
+
+    tracker.track_allocation(0x1000, 600).unwrap();
+    tracker.track_allocation(0x2000, 600).unwrap();
+    tracker.track_allocation(0x3000, 600).unwrap();
+    assert_eq!(*fired.lock().unwrap(), vec![(1_000, 1_200)]);
+
+    // Still above: no repeat until usage drops back and crosses again
+    tracker.track_deallocation(0x3000).unwrap();
+    tracker.track_allocation(0x3000, 600).unwrap();
+    assert_eq!(fired.lock().unwrap().len(), 1);
+
+    tracker.track_deallocation(0x1000).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+    tracker.track_allocation(0x4000, 1_500).unwrap();
+    assert_eq!(
+        *fired.lock().unwrap(),
+        vec![(1_000, 1_200), (1_000, 2_100), (2_000, 2_100)]
+    );
+
+    // A callback may call back into the tracker
+    let reentrant = Arc::new(MemoryTracker::new());
+    let inner = Arc::clone(&reentrant);
+    reentrant.set_threshold(
+        10,
+        Box::new(move |_| inner.track_allocation(0x9000, 1).unwrap()),
+    );
+    reentrant.track_allocation(0x1000, 100).unwrap();
+    assert_eq!(reentrant.get_stats().unwrap().total_allocations, 2);
+}
+
+#[test]
+fn test_budget_exceeded_still_records_allocation() {
+    use memscope_rs::TrackingError;
+
+    let tracker = MemoryTracker::new();
+    tracker.set_budget(Some(1_000));
+    tracker.track_allocation(0x1000, 600).unwrap();
+    tracker.track_allocation(0x2000, 400).unwrap();
+
+    match tracker.track_allocation(0x3000, 300) {
+        Err(TrackingError::BudgetExceeded { limit, attempted }) => {
+            assert_eq!(limit, 1_000);
+            assert_eq!(attempted, 1_300);
+        }
+        other => panic!("expected BudgetExceeded, got {other:?}"),
+    }
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.active_memory, 1_300);
+    assert_eq!(stats.total_allocations, 3);
+
+    // Back under the budget, and no budget at all
+    tracker.track_deallocation(0x3000).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+    tracker.track_allocation(0x4000, 100).unwrap();
+    tracker.set_budget(None);
+    tracker.track_allocation(0x5000, 10_000).unwrap();
+}
+
+#[test]
+fn test_active_allocations_filtered_by_min_size() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 8).unwrap();
+    tracker.track_allocation(0x2000, 1024).unwrap();
+    tracker.track_allocation(0x3000, 4096).unwrap();
+    tracker.track_allocation(0x4000, 0).unwrap();
+
+    let mut large: Vec<usize> = tracker
+        .get_active_allocations_filtered(1024)
+        .unwrap()
+        .iter()
+        .map(|a| a.ptr)
+        .collect();
+    large.sort();
+    assert_eq!(large, vec![0x2000, 0x3000]);
+
+    assert_eq!(tracker.get_active_allocations_filtered(1).unwrap().len(), 3);
+    assert_eq!(tracker.get_active_allocations_filtered(0).unwrap().len(), 4);
+}
+
+#[test]
+fn test_fragmentation_report() {
+    let tracker = MemoryTracker::new();
+    let empty = tracker.fragmentation_report().unwrap();
+    assert_eq!(empty.address_span, 0);
+    assert_eq!(empty.density, 0.0);
+    assert_eq!(empty.utilization, 0.0);
+
+    tracker.track_allocation(0x1000, 100).unwrap();
+    tracker.track_allocation(0x1100, 200).unwrap();
+    tracker.track_allocation(0x1300, 100).unwrap();
+    tracker.track_allocation(0x2000, 400).unwrap();
+    tracker.track_deallocation(0x2000).unwrap();
+
+    let report = tracker.fragmentation_report().unwrap();
+    assert_eq!(report.active_allocations, 3);
+    assert_eq!(report.active_bytes, 400);
+    // 0x1000 up to the end of the allocation at 0x1300
+    assert_eq!(report.address_span, 0x300 + 100);
+    assert_eq!(report.density, 400.0 / 868.0);
+    assert!((report.average_allocation_size - 400.0 / 3.0).abs() < 1e-9);
+    // 100 -> 128, 200 -> 256
+    assert_eq!(report.distinct_size_classes, 2);
+    assert_eq!(report.utilization, 0.5);
+}
+
+#[test]
+fn test_reallocation_keeps_association() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 64).unwrap();
+    tracker
+        .associate_var(0x1000, "items".to_string(), "Vec<u32>".to_string())
+        .unwrap();
+
+    tracker.track_reallocation(0x1000, 0x2000, 128).unwrap();
+    assert!(tracker.get_allocation_by_ptr(0x1000).unwrap().is_none());
+    let moved = tracker.get_allocation_by_ptr(0x2000).unwrap().unwrap();
+    assert_eq!(moved.var_name.as_deref(), Some("items"));
+    assert_eq!(moved.type_name.as_deref(), Some("Vec<u32>"));
+    assert_eq!(moved.size, 128);
+    assert_eq!(moved.growth_events, 1);
+
+    // Only the byte difference is counted
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.total_allocations, 1);
+    assert_eq!(stats.total_deallocations, 0);
+    assert_eq!(stats.active_allocations, 1);
+    assert_eq!(stats.active_memory, 128);
+    assert_eq!(stats.total_allocated, 128);
+
+    // Shrinking in place
+    tracker.track_reallocation(0x2000, 0x2000, 32).unwrap();
+    let stats = tracker.get_stats().unwrap();
+    assert_eq!(stats.active_memory, 32);
+    assert_eq!(stats.total_deallocated, 96);
+    assert_eq!(stats.peak_memory, 128);
+
+    let by_type = tracker.get_memory_by_type().unwrap();
+    assert_eq!(by_type.len(), 1);
+    assert_eq!(by_type[0].type_name, "Vec<u32>");
+    assert_eq!(by_type[0].total_size, 32);
+
+    let history = tracker.get_allocation_history().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!((history[0].ptr, history[0].size), (0x2000, 32));
+    tracker.track_deallocation(0x2000).unwrap();
+    assert!(!tracker.get_allocation_history().unwrap()[0].is_active());
+
+    // An untracked old pointer falls back to a fresh allocation
+    tracker.track_reallocation(0x5000, 0x6000, 16).unwrap();
+    assert_eq!(tracker.get_stats().unwrap().total_allocations, 2);
+    assert_eq!(
+        tracker.get_allocation_by_ptr(0x6000).unwrap().unwrap().size,
+        16
+    );
+}
+
+#[test]
+fn test_rate_sample_between_calls() {
+    let tracker = MemoryTracker::new();
+    tracker.track_allocation(0x1000, 100).unwrap();
+    assert_eq!(tracker.rate_sample().allocations_per_sec, 0.0);
+
+    for i in 0..10 {
+        tracker.track_allocation(0x2000 + i * 0x100, 64).unwrap();
+    }
+    tracker.track_deallocation(0x1000).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+
+    let sample = tracker.rate_sample();
+    let secs = sample.interval.as_secs_f64();
+    assert!(secs >= 0.02);
+    assert_eq!(sample.allocations_per_sec, 10.0 / secs);
+    assert_eq!(sample.deallocations_per_sec, 1.0 / secs);
+    assert_eq!(sample.bytes_allocated_per_sec, 640.0 / secs);
+
+    // Rates only cover the time since the previous sample
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    let idle = tracker.rate_sample();
+    assert_eq!(idle.allocations_per_sec, 0.0);
+    assert!(idle.interval >= std::time::Duration::from_millis(5));
+}
+
+#[test]
+fn test_builder_configures_tracker() {
+    let tracker = MemoryTracker::builder()
+        .history_limit(2)
+        .blocking_mode(true)
+        .capture_backtraces(true)
+        .shard_count(4)
+        .budget(Some(100))
+        .build();
+
+    assert_eq!(tracker.history_capacity(), Some(2));
+    assert!(tracker.is_blocking_mode());
+    assert_eq!(tracker.shard_count(), 4);
+
+    tracker.track_allocation(0x1000, 64).unwrap();
+    assert!(tracker
+        .get_allocation_by_ptr(0x1000)
+        .unwrap()
+        .unwrap()
+        .backtrace
+        .is_some());
+    assert!(matches!(
+        tracker.track_allocation(0x2000, 64),
+        Err(memscope_rs::TrackingError::BudgetExceeded {
+            limit: 100,
+            attempted: 128
+        })
+    ));
+    tracker.track_allocation(0x3000, 8).unwrap_err();
+    assert_eq!(tracker.get_allocation_history().unwrap().len(), 2);
+    assert_eq!(tracker.history_evicted_count(), 1);
+
+    // The defaults match `new()`
+    let default = memscope_rs::MemoryTrackerBuilder::new().build();
+    assert_eq!(default.history_capacity(), None);
+    assert!(!default.is_blocking_mode());
+    assert_eq!(default.shard_count(), MemoryTracker::new().shard_count());
+    default.track_allocation(0x1000, 1 << 20).unwrap();
+    assert!(default
+        .get_allocation_by_ptr(0x1000)
+        .unwrap()
+        .unwrap()
+        .backtrace
+        .is_none());
+}